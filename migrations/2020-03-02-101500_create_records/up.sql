@@ -0,0 +1,16 @@
+CREATE TABLE records (
+  id UUID PRIMARY KEY NOT NULL,
+  project_id UUID REFERENCES projects(id) ON DELETE CASCADE NOT NULL,
+  domain_id UUID REFERENCES documents(id) ON DELETE CASCADE NOT NULL,
+  entity_id INTEGER NOT NULL,
+  body jsonb NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX records_entity ON records (project_id, domain_id, entity_id);
+
+CREATE TRIGGER set_records_timestamp
+BEFORE UPDATE ON records
+  FOR EACH ROW
+  EXECUTE PROCEDURE trigger_set_timestamp();
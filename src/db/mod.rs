@@ -1,3 +1,4 @@
 pub mod connection;
 pub mod models;
+pub mod records;
 pub mod schema;
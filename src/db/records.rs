@@ -0,0 +1,583 @@
+use super::models::Document as DBDocument;
+use super::schema::{documents, records};
+use crate::diesel::Connection;
+use crate::diesel::ExpressionMethods;
+use crate::diesel::QueryDsl;
+use crate::diesel::RunQueryDsl;
+use crate::engine::computed::{derive, ComputedError};
+use crate::engine::events::{fire, EventError, RecordEvent, XFlowRunner};
+use crate::engine::validation::{validate_record, validation_xflows};
+use crate::messages::QueryPage;
+use crate::structure::domain::{AttributeStorage, DomainDocument, Entity};
+use crate::structure::record::{
+    check_record, reference_pattern, reference_property, referenced_ids, RecordViolation,
+};
+use crate::structure::xflow::XFlowDocument;
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::result::Error as DieselError;
+use diesel::sql_types::{Bool, Jsonb, Text};
+use serde_json::Value;
+use std::error;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum RecordError {
+    Database(DieselError),
+    EntityDoesNotExist(i32),
+    /// The record belongs to another domain document than the one given
+    WrongDomain(Uuid),
+    Invalid(Vec<RecordViolation>),
+    Event(EventError),
+    Computed(ComputedError),
+    /// Other records still refer to the record
+    Referenced(Uuid, Vec<Uuid>),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Database(err) => write!(f, "{}", err),
+            RecordError::EntityDoesNotExist(id) => write!(f, "entity {} does not exist", id),
+            RecordError::WrongDomain(id) => {
+                write!(f, "record {} belongs to another domain", id)
+            }
+            RecordError::Invalid(violations) => {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                write!(f, "invalid record: {}", violations.join("; "))
+            }
+            RecordError::Event(err) => write!(f, "{}", err),
+            RecordError::Computed(err) => write!(f, "{}", err),
+            RecordError::Referenced(id, referrers) => {
+                let referrers: Vec<String> = referrers.iter().map(|r| r.to_string()).collect();
+                write!(
+                    f,
+                    "record {} is referred to by {}",
+                    id,
+                    referrers.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RecordError::Database(err) => Some(err),
+            RecordError::Event(err) => Some(err),
+            RecordError::Computed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DieselError> for RecordError {
+    fn from(err: DieselError) -> Self {
+        RecordError::Database(err)
+    }
+}
+
+impl From<EventError> for RecordError {
+    fn from(err: EventError) -> Self {
+        RecordError::Event(err)
+    }
+}
+
+impl From<ComputedError> for RecordError {
+    fn from(err: ComputedError) -> Self {
+        RecordError::Computed(err)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Insertable)]
+#[table_name = "records"]
+pub struct NewRecord {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub body: Value,
+}
+
+/// An instance of an `Entity` of a domain document
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Queryable, Identifiable)]
+#[table_name = "records"]
+pub struct Record {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub body: Value,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Record {
+    /// The entity of the records, as defined in `doc`
+    pub fn entity(doc: &DomainDocument, entity_id: i32) -> Result<&Entity, RecordError> {
+        doc.body
+            .get_entity(entity_id)
+            .map_err(|_| RecordError::EntityDoesNotExist(entity_id))
+    }
+
+    /// Everything wrong with a record body of `entity`, to be stored as
+    /// record `id` or as a new record. Besides what `check_record` covers,
+    /// unique attributes have to be unique among the records of the entity,
+    /// referenced records have to exist and the validations of the
+    /// attributes have to pass.
+    pub fn violations(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity: &Entity,
+        id: Option<&Uuid>,
+        body: &Value,
+    ) -> Result<Vec<RecordViolation>, DieselError> {
+        let mut violations = check_record(&doc.body, entity, body);
+        if !violations.is_empty() {
+            return Ok(violations);
+        }
+
+        let nil = Uuid::nil();
+        let unique_attributes = entity
+            .attributes
+            .iter()
+            .filter(|a| a.constraints.unique && a.storage == AttributeStorage::Persistent);
+        for attribute in unique_attributes {
+            let value = match body.get(&attribute.name) {
+                Some(value) if !value.is_null() => value,
+                _ => continue,
+            };
+            let same_value = sql::<Bool>("body -> ")
+                .bind::<Text, _>(&attribute.name)
+                .sql(" = ")
+                .bind::<Jsonb, _>(value);
+            let taken: i64 = records::table
+                .filter(records::domain_id.eq(doc.id))
+                .filter(records::entity_id.eq(entity.id))
+                .filter(records::id.ne(id.unwrap_or(&nil)))
+                .filter(same_value)
+                .count()
+                .get_result(conn)?;
+            if taken > 0 {
+                violations.push(RecordViolation::new(
+                    Some(&attribute.name),
+                    "the value is not unique",
+                ));
+            }
+        }
+
+        for reference in &entity.references {
+            let other = match doc.body.entities.iter().find(|e| e.name == reference.other) {
+                Some(other) => other,
+                None => continue,
+            };
+            let ids = referenced_ids(reference, body);
+            if ids.is_empty() {
+                continue;
+            }
+            let existing: Vec<Uuid> = records::table
+                .select(records::id)
+                .filter(records::domain_id.eq(doc.id))
+                .filter(records::entity_id.eq(other.id))
+                .filter(records::id.eq_any(&ids))
+                .load(conn)?;
+            for id in ids.iter().filter(|id| !existing.contains(id)) {
+                violations.push(RecordViolation::new(
+                    Some(&reference_property(reference)),
+                    &format!("{} {} does not exist", other.name, id),
+                ));
+            }
+        }
+
+        let ids = validation_xflows(entity);
+        if !ids.is_empty() {
+            let xflows = Self::xflows(conn, &ids)?;
+            violations.append(&mut validate_record(
+                entity,
+                id,
+                body,
+                &xflows,
+                &XFlowRunner,
+            ));
+        }
+        Ok(violations)
+    }
+
+    /// Hold off other writes to the records of `entity` until the
+    /// transaction ends, when it has unique attributes. Their uniqueness is
+    /// checked before a record is stored, two writes at once could both
+    /// pass.
+    fn lock_unique(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity: &Entity,
+    ) -> Result<(), DieselError> {
+        let has_unique = entity
+            .attributes
+            .iter()
+            .any(|a| a.constraints.unique && a.storage == AttributeStorage::Persistent);
+        if has_unique {
+            diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind::<Text, _>(format!("{}/{}", doc.id, entity.id))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Check a record body of `entity` before it is stored as record `id`,
+    /// see `violations`
+    pub fn check(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity: &Entity,
+        id: &Uuid,
+        body: &Value,
+    ) -> Result<(), RecordError> {
+        let violations = Self::violations(conn, doc, entity, Some(id), body)?;
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(RecordError::Invalid(violations))
+        }
+    }
+
+    /// The xflow documents with the given ids. Ids of other documents are
+    /// left out.
+    fn xflows(conn: &PgConnection, ids: &[Uuid]) -> Result<Vec<XFlowDocument>, DieselError> {
+        Ok(documents::table
+            .filter(documents::id.eq_any(ids))
+            .filter(documents::doctype.eq("xflow"))
+            .load::<DBDocument>(conn)?
+            .iter()
+            .filter_map(|xflow| xflow.as_xflow().ok())
+            .collect())
+    }
+
+    /// The xflows of `doc` to run on `event`, in the order they run
+    fn event_xflows(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        event: RecordEvent,
+    ) -> Result<Vec<XFlowDocument>, RecordError> {
+        let ids: Vec<Uuid> = event
+            .xflows(&doc.body.events)
+            .iter()
+            .map(|reference| reference.id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut found = Self::xflows(conn, &ids)?;
+        let mut xflows = Vec::<XFlowDocument>::new();
+        for id in ids {
+            match found.iter().position(|xflow| xflow.id == id) {
+                Some(index) => xflows.push(found.swap_remove(index)),
+                None => {
+                    return Err(EventError::Failed {
+                        xflow: id,
+                        message: "the xflow does not exist".to_owned(),
+                    }
+                    .into())
+                }
+            }
+        }
+        Ok(xflows)
+    }
+
+    /// Run the xflows of `event` on record `id` and return its body as they
+    /// left it
+    fn fire(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        event: RecordEvent,
+        entity: &Entity,
+        id: &Uuid,
+        body: Value,
+        previous: Option<&Value>,
+    ) -> Result<Value, RecordError> {
+        let xflows = Self::event_xflows(conn, doc, event)?;
+        if xflows.is_empty() {
+            return Ok(body);
+        }
+        Ok(fire(
+            event,
+            entity,
+            id,
+            body,
+            previous,
+            &xflows,
+            &XFlowRunner,
+        )?)
+    }
+
+    /// Validate and store a new record of entity `entity_id`, once the
+    /// xflows of the create event let it through
+    pub fn create(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity_id: i32,
+        body: Value,
+    ) -> Result<Record, RecordError> {
+        let entity = Self::entity(doc, entity_id)?;
+        let id = Uuid::new_v4();
+        let body = Self::fire(conn, doc, RecordEvent::Create, entity, &id, body, None)?;
+        let record = NewRecord {
+            id,
+            project_id: doc.project_id,
+            domain_id: doc.id,
+            entity_id,
+            body,
+        };
+        let record = conn.transaction::<_, RecordError, _>(|| {
+            Self::lock_unique(conn, doc, entity)?;
+            Self::check(conn, doc, entity, &record.id, &record.body)?;
+            Ok(diesel::insert_into(records::table)
+                .values(&record)
+                .get_result(conn)?)
+        })?;
+        Self::derive(doc, record)
+    }
+
+    /// The record `id` as it is stored, without its computed attributes,
+    /// see `read`
+    pub fn by_id(conn: &PgConnection, id: &Uuid) -> Result<Record, DieselError> {
+        records::table.find(id).first::<Record>(conn)
+    }
+
+    /// `record` with the values of the computed attributes of its entity
+    /// added, they are derived on every read and never stored
+    pub fn derive(doc: &DomainDocument, record: Record) -> Result<Record, RecordError> {
+        let entity = Self::entity(doc, record.entity_id)?;
+        let body = derive(entity, record.body)?;
+        Ok(Record { body, ..record })
+    }
+
+    /// Derive the computed attributes of `record` and run the xflows of the
+    /// read event of `doc` on it, and return it as they left it
+    pub fn read(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        record: Record,
+    ) -> Result<Record, RecordError> {
+        let entity = Self::entity(doc, record.entity_id)?;
+        let body = Self::fire(
+            conn,
+            doc,
+            RecordEvent::Read,
+            entity,
+            &record.id,
+            derive(entity, record.body)?,
+            None,
+        )?;
+        Ok(Record { body, ..record })
+    }
+
+    /// `read` for a list of records of `doc`, leaving out the records the
+    /// xflows veto
+    pub fn read_all(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        records: Vec<Record>,
+    ) -> Result<Vec<Record>, RecordError> {
+        let records = records
+            .into_iter()
+            .map(|record| Self::derive(doc, record))
+            .collect::<Result<Vec<_>, _>>()?;
+        let xflows = Self::event_xflows(conn, doc, RecordEvent::Read)?;
+        if xflows.is_empty() {
+            return Ok(records);
+        }
+        let mut read = Vec::<Record>::new();
+        for record in records {
+            let entity = Self::entity(doc, record.entity_id)?;
+            let body = fire(
+                RecordEvent::Read,
+                entity,
+                &record.id,
+                record.body,
+                None,
+                &xflows,
+                &XFlowRunner,
+            );
+            match body {
+                Ok(body) => read.push(Record { body, ..record }),
+                Err(EventError::Vetoed { .. }) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(read)
+    }
+
+    /// The records of `doc` with the given ids, in the order of `ids`, as
+    /// `read_all` has them. Ids without a record are left out.
+    pub fn by_ids(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        ids: &[Uuid],
+    ) -> Result<Vec<Record>, RecordError> {
+        let records = records::table
+            .filter(records::domain_id.eq(doc.id))
+            .filter(records::id.eq_any(ids))
+            .load::<Record>(conn)?;
+        let records = ids
+            .iter()
+            .filter_map(|id| records.iter().find(|r| &r.id == id).cloned())
+            .collect();
+        Self::read_all(conn, doc, records)
+    }
+
+    /// The records of entity `entity_id` whose body contains `pattern`, as
+    /// the `@>` operator of JSONB has it. `{"post_id": id}` finds the
+    /// records referring to post `id`, `{"tags_ids": [id]}` the records
+    /// holding tag `id` in a list. The records are as `read_all` has them.
+    pub fn find_containing(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity_id: i32,
+        pattern: &Value,
+    ) -> Result<Vec<Record>, RecordError> {
+        let records = records::table
+            .filter(records::domain_id.eq(doc.id))
+            .filter(records::entity_id.eq(entity_id))
+            .filter(sql::<Bool>("body @> ").bind::<Jsonb, _>(pattern))
+            .order((records::created_at.asc(), records::id.asc()))
+            .load::<Record>(conn)?;
+        Self::read_all(conn, doc, records)
+    }
+
+    /// The records of entity `entity_id` in the domain document `doc`,
+    /// oldest first, as `read_all` has them
+    pub fn find(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity_id: i32,
+        paging: Option<QueryPage>,
+    ) -> Result<Vec<Record>, RecordError> {
+        let p = paging.unwrap_or_default();
+        let query = records::table
+            .filter(records::domain_id.eq(doc.id))
+            .filter(records::entity_id.eq(entity_id))
+            .order((records::created_at.asc(), records::id.asc()));
+        let records = if let Some(limit) = p.limit {
+            query
+                .limit(limit.into())
+                .offset(p.offset.unwrap_or(0).into())
+                .load::<Record>(conn)?
+        } else {
+            query.load::<Record>(conn)?
+        };
+        Self::read_all(conn, doc, records)
+    }
+
+    /// Merge `changes` into the body of record `id` and store it, once the
+    /// xflows of the update event let it through and the result is valid.
+    /// Properties set to null in `changes` are removed.
+    pub fn update(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        id: &Uuid,
+        changes: Value,
+    ) -> Result<Record, RecordError> {
+        let record = conn.transaction::<_, RecordError, _>(|| {
+            let record = Self::by_id(conn, id)?;
+            if record.domain_id != doc.id {
+                return Err(RecordError::WrongDomain(record.id));
+            }
+            let entity = Self::entity(doc, record.entity_id)?;
+
+            let body = match (record.body.clone(), changes) {
+                (Value::Object(body), Value::Object(changes)) => {
+                    let mut body = body;
+                    for (property, value) in changes {
+                        if value.is_null() {
+                            body.remove(&property);
+                        } else {
+                            body.insert(property, value);
+                        }
+                    }
+                    Value::Object(body)
+                }
+                (_, changes) => changes,
+            };
+            let body = Self::fire(
+                conn,
+                doc,
+                RecordEvent::Update,
+                entity,
+                id,
+                body,
+                Some(&record.body),
+            )?;
+            Self::lock_unique(conn, doc, entity)?;
+            Self::check(conn, doc, entity, id, &body)?;
+
+            Ok(diesel::update(records::table.find(id))
+                .set(records::body.eq(&body))
+                .get_result(conn)?)
+        })?;
+        Self::derive(doc, record)
+    }
+
+    /// The ids of the other records of `doc` that refer to record `id` of
+    /// `entity`
+    fn referrers(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        entity: &Entity,
+        id: &Uuid,
+    ) -> Result<Vec<Uuid>, DieselError> {
+        let mut referrers = Vec::<Uuid>::new();
+        for other in &doc.body.entities {
+            for reference in other.references.iter().filter(|r| r.other == entity.name) {
+                let pattern = reference_pattern(reference, id);
+                let found: Vec<Uuid> = records::table
+                    .select(records::id)
+                    .filter(records::domain_id.eq(doc.id))
+                    .filter(records::entity_id.eq(other.id))
+                    .filter(records::id.ne(id))
+                    .filter(sql::<Bool>("body @> ").bind::<Jsonb, _>(pattern))
+                    .order((records::created_at.asc(), records::id.asc()))
+                    .load(conn)?;
+                for found in found {
+                    if !referrers.contains(&found) {
+                        referrers.push(found);
+                    }
+                }
+            }
+        }
+        Ok(referrers)
+    }
+
+    /// Delete record `id` of `doc`, once the xflows of the delete event let
+    /// it go. Records other records still refer to are not deleted.
+    pub fn delete(
+        conn: &PgConnection,
+        doc: &DomainDocument,
+        id: &Uuid,
+    ) -> Result<usize, RecordError> {
+        conn.transaction(|| {
+            let record = Self::by_id(conn, id)?;
+            if record.domain_id != doc.id {
+                return Err(RecordError::WrongDomain(record.id));
+            }
+            let entity = Self::entity(doc, record.entity_id)?;
+            let referrers = Self::referrers(conn, doc, entity, id)?;
+            if !referrers.is_empty() {
+                return Err(RecordError::Referenced(record.id, referrers));
+            }
+            Self::fire(
+                conn,
+                doc,
+                RecordEvent::Delete,
+                entity,
+                id,
+                record.body,
+                None,
+            )?;
+            Ok(diesel::delete(records::table.find(id)).execute(conn)?)
+        })
+    }
+}
@@ -22,7 +22,20 @@ table! {
     }
 }
 
+table! {
+    records (id) {
+        id -> Uuid,
+        project_id -> Uuid,
+        domain_id -> Uuid,
+        entity_id -> Int4,
+        body -> Jsonb,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
     documents,
     projects,
+    records,
 );
@@ -0,0 +1,129 @@
+//! Computed attributes, derived from the record whenever it is read
+//!
+//! The expression of a computed attribute is a flox expression over the
+//! persistent attributes of its entity, by name, such as `len(title)`. The
+//! value is added to the body of a record on its way out of the store and
+//! never stored. A computed attribute has no value when an attribute its
+//! expression uses has none.
+
+use super::flox::check::{check, fits, flox_type};
+use super::flox::eval::evaluate;
+use super::flox::parser::parse;
+use super::flox::{FloxError, Node};
+use super::xflow::Variables;
+use crate::structure::data::{DocumentVariables, VType, VariableDefinition};
+use crate::structure::domain::{Attribute, AttributeStorage, Entity};
+use serde_json::Value;
+use std::error;
+use std::fmt;
+
+/// A computed attribute whose value can not be derived
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedError {
+    pub attribute: String,
+    pub error: FloxError,
+}
+
+impl fmt::Display for ComputedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "computed attribute {}: {}", self.attribute, self.error)
+    }
+}
+
+impl error::Error for ComputedError {}
+
+/// The variables the expressions of `entity` can use, its persistent
+/// attributes
+pub fn variables(entity: &Entity) -> DocumentVariables {
+    let input = entity
+        .stored_attributes()
+        .map(|attribute| VariableDefinition {
+            id: attribute.id,
+            name: attribute.name.clone(),
+            vtype: attribute.vtype.clone(),
+        })
+        .collect();
+    DocumentVariables {
+        input,
+        ..DocumentVariables::default()
+    }
+}
+
+/// Check that `expression` can compute values of `vtype` from
+/// `definitions`
+pub fn check_expression(
+    expression: &str,
+    vtype: &VType,
+    definitions: &DocumentVariables,
+) -> Result<Node, FloxError> {
+    let node = parse(expression)?;
+    let found = check(&node, definitions)?;
+    let expected = flox_type(vtype).ok_or_else(|| {
+        FloxError::new(
+            0,
+            &format!("a {} attribute can not be computed", vtype.type_name()),
+        )
+    })?;
+    if !fits(found, expected) {
+        return Err(FloxError::new(
+            0,
+            &format!(
+                "the expression is of type {}, the attribute of type {}",
+                found, expected
+            ),
+        ));
+    }
+    Ok(node)
+}
+
+/// The value of the computed `attribute` for the record with `values`
+fn derive_value(
+    attribute: &Attribute,
+    expression: &str,
+    definitions: &DocumentVariables,
+    values: &Variables,
+) -> Result<Value, FloxError> {
+    let node = check_expression(expression, &attribute.vtype, definitions)?;
+    if node
+        .variables()
+        .iter()
+        .any(|name| !values.contains_key(*name))
+    {
+        return Ok(Value::Null);
+    }
+    let value = evaluate(&node, values)?.to_json();
+    Ok(value)
+}
+
+/// `body` with the values of the computed attributes of `entity` added.
+/// Values of persistent attributes that do not fit their type count as
+/// missing, `check_record` reports them.
+pub fn derive(entity: &Entity, body: Value) -> Result<Value, ComputedError> {
+    let mut properties = match body {
+        Value::Object(properties) => properties,
+        body => return Ok(body),
+    };
+    let definitions = variables(entity);
+    let values: Variables = entity
+        .stored_attributes()
+        .filter_map(|attribute| {
+            let value = properties.get(&attribute.name).filter(|v| !v.is_null())?;
+            let container = attribute.vtype.container_from_json(value).ok()?;
+            Some((attribute.name.clone(), container))
+        })
+        .collect();
+
+    for attribute in &entity.attributes {
+        if let AttributeStorage::Computed { expression } = &attribute.storage {
+            let value =
+                derive_value(attribute, expression, &definitions, &values).map_err(|error| {
+                    ComputedError {
+                        attribute: attribute.name.clone(),
+                        error,
+                    }
+                })?;
+            properties.insert(attribute.name.clone(), value);
+        }
+    }
+    Ok(Value::Object(properties))
+}
@@ -0,0 +1,206 @@
+//! Domain events, the xflows run when records change or are read
+//!
+//! The xflows of an event get the record through their input variables,
+//! by name:
+//!
+//! - `event`: a string, `create`, `update`, `read` or `delete`
+//! - `entity`: a string, the name of the entity of the record
+//! - `record`: json, the body of the record including its `id`
+//! - `previous`: json, for updates the body before the update
+//!
+//! and act on the operation through their output variables:
+//!
+//! - `veto`: a boolean, true to stop the operation
+//! - `message`: a string, the reason for a veto
+//! - `record`: json, the body to go on with instead, when not null
+//!
+//! Flows without these variables only observe. The xflows of an event run
+//! one after the other, each getting the record as the previous one left it.
+
+use super::xflow;
+use crate::structure::common::DocumentReference;
+use crate::structure::domain::{Entity, Events};
+use crate::structure::xflow::XFlowDocument;
+use serde_json::{Map, Value};
+use std::error;
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordEvent {
+    Create,
+    Update,
+    Read,
+    Delete,
+}
+
+impl fmt::Display for RecordEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordEvent::Create => write!(f, "create"),
+            RecordEvent::Update => write!(f, "update"),
+            RecordEvent::Read => write!(f, "read"),
+            RecordEvent::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+impl RecordEvent {
+    /// The xflows to run on this event: the ones of its own list, then
+    /// `change` for creates, updates and deletes, then `all`. An xflow is
+    /// run once, even when it is in several lists.
+    pub fn xflows(self, events: &Events) -> Vec<&DocumentReference> {
+        let own: &[DocumentReference] = match self {
+            RecordEvent::Create => &[],
+            RecordEvent::Update => &events.update,
+            RecordEvent::Read => &events.read,
+            RecordEvent::Delete => &events.delete,
+        };
+        let change: &[DocumentReference] = match self {
+            RecordEvent::Read => &[],
+            _ => &events.change,
+        };
+
+        let mut xflows = Vec::<&DocumentReference>::new();
+        for reference in own.iter().chain(change).chain(&events.all) {
+            if !xflows.iter().any(|r| r.id == reference.id) {
+                xflows.push(reference);
+            }
+        }
+        xflows
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventError {
+    /// An xflow stopped the operation
+    Vetoed { xflow: Uuid, message: String },
+    /// An xflow could not be loaded or run
+    Failed { xflow: Uuid, message: String },
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventError::Vetoed { xflow, message } => {
+                write!(f, "vetoed by xflow {}: {}", xflow, message)
+            }
+            EventError::Failed { xflow, message } => {
+                write!(f, "xflow {} failed: {}", xflow, message)
+            }
+        }
+    }
+}
+
+impl error::Error for EventError {}
+
+/// Runs the xflows of events
+pub trait FlowRunner {
+    fn run(
+        &self,
+        xflow: &XFlowDocument,
+        input: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, String>;
+}
+
+/// Runs xflows with `engine::xflow::run`
+pub struct XFlowRunner;
+
+impl FlowRunner for XFlowRunner {
+    fn run(
+        &self,
+        xflow: &XFlowDocument,
+        input: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, String> {
+        xflow::run(&xflow.body, input).map_err(|err| err.to_string())
+    }
+}
+
+/// What an xflow did with the record
+enum Outcome {
+    Passed,
+    Modified(Map<String, Value>),
+    Vetoed(String),
+}
+
+fn outcome(output: &Map<String, Value>) -> Result<Outcome, String> {
+    if output.get("veto") == Some(&Value::Bool(true)) {
+        let message = match output.get("message") {
+            Some(Value::String(message)) if !message.is_empty() => message.clone(),
+            _ => "the operation is not allowed".to_owned(),
+        };
+        return Ok(Outcome::Vetoed(message));
+    }
+    match output.get("record") {
+        None | Some(Value::Null) => Ok(Outcome::Passed),
+        Some(Value::Object(body)) => {
+            let mut body = body.clone();
+            body.remove("id");
+            Ok(Outcome::Modified(body))
+        }
+        Some(_) => Err("the record output is not a JSON object".to_owned()),
+    }
+}
+
+/// Run `xflows` on `event` of record `id` of `entity`, and return the body
+/// of the record as the xflows left it. Every run is logged.
+pub fn fire(
+    event: RecordEvent,
+    entity: &Entity,
+    id: &Uuid,
+    body: Value,
+    previous: Option<&Value>,
+    xflows: &[XFlowDocument],
+    runner: &dyn FlowRunner,
+) -> Result<Value, EventError> {
+    let with_id = |body: &Value| {
+        let mut body = body.clone();
+        if let Value::Object(properties) = &mut body {
+            properties.insert("id".to_owned(), Value::from(id.to_string()));
+        }
+        body
+    };
+
+    let mut body = body;
+    for xflow in xflows {
+        let mut input = Map::new();
+        input.insert("event".to_owned(), Value::from(event.to_string()));
+        input.insert("entity".to_owned(), Value::from(entity.name.clone()));
+        input.insert("record".to_owned(), with_id(&body));
+        if let Some(previous) = previous {
+            input.insert("previous".to_owned(), with_id(previous));
+        }
+
+        let result = runner
+            .run(xflow, &input)
+            .and_then(|output| outcome(&output));
+        let done = match &result {
+            Ok(Outcome::Passed) => "passed".to_owned(),
+            Ok(Outcome::Modified(_)) => "modified the record".to_owned(),
+            Ok(Outcome::Vetoed(message)) => format!("vetoed: {}", message),
+            Err(err) => format!("failed: {}", err),
+        };
+        info!(
+            "xflow {} ({}) on {} of {} {}: {}",
+            xflow.id, xflow.name, event, entity.name, id, done
+        );
+
+        match result {
+            Ok(Outcome::Passed) => {}
+            Ok(Outcome::Modified(modified)) => body = Value::Object(modified),
+            Ok(Outcome::Vetoed(message)) => {
+                return Err(EventError::Vetoed {
+                    xflow: xflow.id,
+                    message,
+                })
+            }
+            Err(message) => {
+                return Err(EventError::Failed {
+                    xflow: xflow.id,
+                    message,
+                })
+            }
+        }
+    }
+    Ok(body)
+}
@@ -0,0 +1,254 @@
+use super::parser::parse;
+use super::{BinaryOp, Expr, FloxError, FloxType, Node, UnaryOp};
+use crate::structure::data::{DocumentVariables, VType};
+use crate::structure::xflow::{FloxParameters, XFlow, XFlowNodeParameters};
+
+/// The type values of `vtype` have in expressions, `None` when expressions
+/// can not use them
+pub fn flox_type(vtype: &VType) -> Option<FloxType> {
+    match vtype {
+        VType::VTypeString(_) | VType::VTypeEnum(_) => Some(FloxType::String),
+        VType::VTypeInteger(_) => Some(FloxType::Integer),
+        VType::VTypeFloat(_) => Some(FloxType::Float),
+        VType::VTypeBoolean(_) => Some(FloxType::Boolean),
+        _ => None,
+    }
+}
+
+/// Whether values of type `from` can be used where `to` is expected
+pub fn fits(from: FloxType, to: FloxType) -> bool {
+    from == to || (from == FloxType::Integer && to == FloxType::Float)
+}
+
+/// The common type of two numbers
+fn widen(left: FloxType, right: FloxType) -> FloxType {
+    if left == FloxType::Float || right == FloxType::Float {
+        FloxType::Float
+    } else {
+        FloxType::Integer
+    }
+}
+
+fn expect(node: &Node, found: FloxType, expected: FloxType) -> Result<(), FloxError> {
+    if fits(found, expected) {
+        Ok(())
+    } else {
+        Err(FloxError::new(
+            node.position,
+            &format!("expected {}, found {}", expected, found),
+        ))
+    }
+}
+
+fn expect_number(node: &Node, found: FloxType) -> Result<(), FloxError> {
+    if found.is_number() {
+        Ok(())
+    } else {
+        Err(FloxError::new(
+            node.position,
+            &format!("expected a number, found {}", found),
+        ))
+    }
+}
+
+/// The parameter and result types of the function `name`, `string` takes
+/// any value and is left out
+pub fn signature(name: &str) -> Option<(&'static [FloxType], FloxType)> {
+    use super::FloxType::{Boolean, Integer, String};
+
+    let signature: (&'static [FloxType], FloxType) = match name {
+        "len" => (&[String], Integer),
+        "upper" | "lower" | "trim" => (&[String], String),
+        "contains" | "starts_with" | "ends_with" => (&[String, String], Boolean),
+        "replace" => (&[String, String, String], String),
+        "substring" => (&[String, Integer, Integer], String),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+fn check_call(
+    node: &Node,
+    name: &str,
+    arguments: &[Node],
+    variables: &DocumentVariables,
+) -> Result<FloxType, FloxError> {
+    let (parameters, result) = match (name, signature(name)) {
+        ("string", _) => (&[FloxType::String][..], FloxType::String),
+        (_, Some(signature)) => signature,
+        (_, None) => {
+            return Err(FloxError::new(
+                node.position,
+                &format!("unknown function {}", name),
+            ))
+        }
+    };
+    if arguments.len() != parameters.len() {
+        return Err(FloxError::new(
+            node.position,
+            &format!(
+                "{} takes {} argument(s), not {}",
+                name,
+                parameters.len(),
+                arguments.len()
+            ),
+        ));
+    }
+    for (argument, parameter) in arguments.iter().zip(parameters) {
+        let found = check(argument, variables)?;
+        if name != "string" {
+            expect(argument, found, *parameter)?;
+        }
+    }
+    Ok(result)
+}
+
+/// The type of the expression `node` in a flow with `variables`
+pub fn check(node: &Node, variables: &DocumentVariables) -> Result<FloxType, FloxError> {
+    match &node.expr {
+        Expr::Literal(value) => Ok(value.flox_type()),
+        Expr::Variable(name) => {
+            let xvar = variables.get_variable(name).ok_or_else(|| {
+                FloxError::new(node.position, &format!("unknown variable {}", name))
+            })?;
+            flox_type(&xvar.vtype).ok_or_else(|| {
+                FloxError::new(
+                    node.position,
+                    &format!(
+                        "variable {} is a {}, which expressions can not use",
+                        name,
+                        xvar.vtype.type_name()
+                    ),
+                )
+            })
+        }
+        Expr::Unary(UnaryOp::Neg, operand) => {
+            let found = check(operand, variables)?;
+            expect_number(operand, found)?;
+            Ok(found)
+        }
+        Expr::Unary(UnaryOp::Not, operand) => {
+            let found = check(operand, variables)?;
+            expect(operand, found, FloxType::Boolean)?;
+            Ok(FloxType::Boolean)
+        }
+        Expr::Binary(op, left, right) => {
+            let l = check(left, variables)?;
+            let r = check(right, variables)?;
+            let mismatch = || {
+                FloxError::new(
+                    node.position,
+                    &format!("{} can not be applied to {} and {}", op, l, r),
+                )
+            };
+            match op {
+                BinaryOp::Or | BinaryOp::And => {
+                    expect(left, l, FloxType::Boolean)?;
+                    expect(right, r, FloxType::Boolean)?;
+                    Ok(FloxType::Boolean)
+                }
+                BinaryOp::Eq | BinaryOp::Ne => {
+                    if l == r || (l.is_number() && r.is_number()) {
+                        Ok(FloxType::Boolean)
+                    } else {
+                        Err(mismatch())
+                    }
+                }
+                BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                    let strings = l == FloxType::String && r == FloxType::String;
+                    if strings || (l.is_number() && r.is_number()) {
+                        Ok(FloxType::Boolean)
+                    } else {
+                        Err(mismatch())
+                    }
+                }
+                BinaryOp::Add if l == FloxType::String && r == FloxType::String => {
+                    Ok(FloxType::String)
+                }
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                    if l.is_number() && r.is_number() {
+                        Ok(widen(l, r))
+                    } else {
+                        Err(mismatch())
+                    }
+                }
+                BinaryOp::Rem => {
+                    expect(left, l, FloxType::Integer)?;
+                    expect(right, r, FloxType::Integer)?;
+                    Ok(FloxType::Integer)
+                }
+            }
+        }
+        Expr::Call(name, arguments) => check_call(node, name, arguments, variables),
+    }
+}
+
+/// Parse and check the expression of a flox node, and that its value fits
+/// the `returns` variable, which the flow has to have with the same type
+pub fn check_parameters(
+    parameters: &FloxParameters,
+    variables: &DocumentVariables,
+) -> Result<Node, FloxError> {
+    let node = parse(&parameters.expression)?;
+    let found = check(&node, variables)?;
+
+    let returns = &parameters.returns;
+    let expected = flox_type(&returns.vtype).ok_or_else(|| {
+        FloxError::new(
+            0,
+            &format!(
+                "variable {} is a {}, which expressions can not compute",
+                returns.name,
+                returns.vtype.type_name()
+            ),
+        )
+    })?;
+    let xvar = variables.get_variable(&returns.name).ok_or_else(|| {
+        FloxError::new(
+            0,
+            &format!("the node returns unknown variable {}", returns.name),
+        )
+    })?;
+    if xvar.vtype.type_name() != returns.vtype.type_name() {
+        return Err(FloxError::new(
+            0,
+            &format!(
+                "variable {} is of type {}, the node returns {}",
+                returns.name,
+                xvar.vtype.type_name(),
+                returns.vtype.type_name()
+            ),
+        ));
+    }
+    if !fits(found, expected) {
+        return Err(FloxError::new(
+            0,
+            &format!(
+                "the expression is of type {}, variable {} of type {}",
+                found, returns.name, expected
+            ),
+        ));
+    }
+    Ok(node)
+}
+
+/// The ids of the flox nodes of `xflow` that use the variable `name`, in
+/// their expression or as the variable they return. Expressions that do
+/// not parse are passed over.
+pub fn nodes_using(xflow: &XFlow, name: &str) -> Vec<i32> {
+    let uses = |parameters: &FloxParameters| {
+        let used = parse(&parameters.expression)
+            .map(|node| node.variables().contains(&name))
+            .unwrap_or(false);
+        used || parameters.returns.name == name
+    };
+    xflow
+        .nodes
+        .iter()
+        .filter(|node| match &node.parameters {
+            XFlowNodeParameters::Flox(parameters) => uses(parameters),
+            _ => false,
+        })
+        .map(|node| node.id)
+        .collect()
+}
@@ -0,0 +1,253 @@
+use super::check::{check_parameters, signature};
+use super::{BinaryOp, Expr, FloxError, FloxValue, Node, UnaryOp};
+use crate::engine::xflow::Variables;
+use crate::structure::data::{DocumentVariables, VTypeContainer};
+use crate::structure::xflow::FloxParameters;
+use std::cmp::Ordering;
+
+fn value_of(node: &Node, name: &str, variables: &Variables) -> Result<FloxValue, FloxError> {
+    let value = match variables.get(name) {
+        Some(VTypeContainer::VTypeStringContainer(c)) => FloxValue::String(c.value.clone()),
+        Some(VTypeContainer::VTypeEnumContainer(c)) => FloxValue::String(c.value.clone()),
+        Some(VTypeContainer::VTypeIntegerContainer(c)) => FloxValue::Integer(c.value),
+        Some(VTypeContainer::VTypeFloatContainer(c)) => FloxValue::Float(c.value),
+        Some(VTypeContainer::VTypeBooleanContainer(c)) => FloxValue::Boolean(c.value),
+        Some(_) => {
+            return Err(FloxError::new(
+                node.position,
+                &format!("variable {} can not be used in expressions", name),
+            ))
+        }
+        None => {
+            return Err(FloxError::new(
+                node.position,
+                &format!("variable {} has no value", name),
+            ))
+        }
+    };
+    Ok(value)
+}
+
+fn type_error(node: &Node, value: &FloxValue) -> FloxError {
+    FloxError::new(
+        node.position,
+        &format!("unexpected {} value", value.flox_type()),
+    )
+}
+
+fn float(node: &Node, value: &FloxValue) -> Result<f64, FloxError> {
+    match value {
+        FloxValue::Integer(value) => Ok(f64::from(*value)),
+        FloxValue::Float(value) => Ok(*value),
+        _ => Err(type_error(node, value)),
+    }
+}
+
+fn string<'a>(node: &Node, value: &'a FloxValue) -> Result<&'a str, FloxError> {
+    match value {
+        FloxValue::String(value) => Ok(value),
+        _ => Err(type_error(node, value)),
+    }
+}
+
+fn integer(node: &Node, value: &FloxValue) -> Result<i32, FloxError> {
+    match value {
+        FloxValue::Integer(value) => Ok(*value),
+        _ => Err(type_error(node, value)),
+    }
+}
+
+fn boolean(node: &Node, value: &FloxValue) -> Result<bool, FloxError> {
+    match value {
+        FloxValue::Boolean(value) => Ok(*value),
+        _ => Err(type_error(node, value)),
+    }
+}
+
+fn compare(node: &Node, left: &FloxValue, right: &FloxValue) -> Result<Ordering, FloxError> {
+    let ordering = match (left, right) {
+        (FloxValue::String(l), FloxValue::String(r)) => Some(l.cmp(r)),
+        (FloxValue::Boolean(l), FloxValue::Boolean(r)) => Some(l.cmp(r)),
+        (FloxValue::Integer(l), FloxValue::Integer(r)) => Some(l.cmp(r)),
+        _ => float(node, left)?.partial_cmp(&float(node, right)?),
+    };
+    ordering.ok_or_else(|| FloxError::new(node.position, "the numbers can not be compared"))
+}
+
+fn arithmetic(
+    node: &Node,
+    op: BinaryOp,
+    left: &FloxValue,
+    right: &FloxValue,
+) -> Result<FloxValue, FloxError> {
+    if let (FloxValue::Integer(l), FloxValue::Integer(r)) = (left, right) {
+        if (op == BinaryOp::Div || op == BinaryOp::Rem) && *r == 0 {
+            return Err(FloxError::new(node.position, "division by zero"));
+        }
+        let result = match op {
+            BinaryOp::Add => l.checked_add(*r),
+            BinaryOp::Sub => l.checked_sub(*r),
+            BinaryOp::Mul => l.checked_mul(*r),
+            BinaryOp::Div => l.checked_div(*r),
+            _ => l.checked_rem(*r),
+        };
+        return result
+            .map(FloxValue::Integer)
+            .ok_or_else(|| FloxError::new(node.position, "the integer is out of range"));
+    }
+
+    let (l, r) = (float(node, left)?, float(node, right)?);
+    let result = match op {
+        BinaryOp::Add => l + r,
+        BinaryOp::Sub => l - r,
+        BinaryOp::Mul => l * r,
+        BinaryOp::Div => l / r,
+        _ => l % r,
+    };
+    if result.is_finite() {
+        Ok(FloxValue::Float(result))
+    } else {
+        Err(FloxError::new(
+            node.position,
+            "the result is not a finite number",
+        ))
+    }
+}
+
+fn binary(
+    node: &Node,
+    op: BinaryOp,
+    left: &Node,
+    right: &Node,
+    variables: &Variables,
+) -> Result<FloxValue, FloxError> {
+    let l = evaluate(left, variables)?;
+    match op {
+        BinaryOp::And if !boolean(left, &l)? => return Ok(FloxValue::Boolean(false)),
+        BinaryOp::Or if boolean(left, &l)? => return Ok(FloxValue::Boolean(true)),
+        _ => {}
+    }
+    let r = evaluate(right, variables)?;
+
+    let value = match op {
+        BinaryOp::And | BinaryOp::Or => FloxValue::Boolean(boolean(right, &r)?),
+        BinaryOp::Eq => FloxValue::Boolean(compare(node, &l, &r)? == Ordering::Equal),
+        BinaryOp::Ne => FloxValue::Boolean(compare(node, &l, &r)? != Ordering::Equal),
+        BinaryOp::Lt => FloxValue::Boolean(compare(node, &l, &r)? == Ordering::Less),
+        BinaryOp::Le => FloxValue::Boolean(compare(node, &l, &r)? != Ordering::Greater),
+        BinaryOp::Gt => FloxValue::Boolean(compare(node, &l, &r)? == Ordering::Greater),
+        BinaryOp::Ge => FloxValue::Boolean(compare(node, &l, &r)? != Ordering::Less),
+        BinaryOp::Add if l.flox_type() == super::FloxType::String => {
+            FloxValue::String(format!("{}{}", string(left, &l)?, string(right, &r)?))
+        }
+        _ => arithmetic(node, op, &l, &r)?,
+    };
+    Ok(value)
+}
+
+/// `length` characters of `value` from character `start` on, as far as
+/// there are any
+fn substring(value: &str, start: i32, length: i32) -> String {
+    let start = start.max(0) as usize;
+    let length = length.max(0) as usize;
+    value.chars().skip(start).take(length).collect()
+}
+
+fn call(
+    node: &Node,
+    name: &str,
+    arguments: &[Node],
+    variables: &Variables,
+) -> Result<FloxValue, FloxError> {
+    let arity = match (name, signature(name)) {
+        ("string", _) => 1,
+        (_, Some((parameters, _))) => parameters.len(),
+        (_, None) => {
+            return Err(FloxError::new(
+                node.position,
+                &format!("unknown function {}", name),
+            ))
+        }
+    };
+    if arguments.len() != arity {
+        return Err(FloxError::new(
+            node.position,
+            &format!(
+                "{} takes {} argument(s), not {}",
+                name,
+                arity,
+                arguments.len()
+            ),
+        ));
+    }
+    let values = arguments
+        .iter()
+        .map(|argument| evaluate(argument, variables))
+        .collect::<Result<Vec<_>, _>>()?;
+    let s = |i: usize| string(&arguments[i], &values[i]);
+
+    let value = match name {
+        "string" => FloxValue::String(values[0].to_string()),
+        "len" => FloxValue::Integer(s(0)?.chars().count() as i32),
+        "upper" => FloxValue::String(s(0)?.to_uppercase()),
+        "lower" => FloxValue::String(s(0)?.to_lowercase()),
+        "trim" => FloxValue::String(s(0)?.trim().to_owned()),
+        "contains" => FloxValue::Boolean(s(0)?.contains(s(1)?)),
+        "starts_with" => FloxValue::Boolean(s(0)?.starts_with(s(1)?)),
+        "ends_with" => FloxValue::Boolean(s(0)?.ends_with(s(1)?)),
+        "replace" => FloxValue::String(s(0)?.replace(s(1)?, s(2)?)),
+        "substring" => FloxValue::String(substring(
+            s(0)?,
+            integer(&arguments[1], &values[1])?,
+            integer(&arguments[2], &values[2])?,
+        )),
+        _ => {
+            return Err(FloxError::new(
+                node.position,
+                &format!("unknown function {}", name),
+            ))
+        }
+    };
+    Ok(value)
+}
+
+/// The value of the expression `node` with the values of the flow
+/// `variables`. Expressions that passed `check` only fail on values, such
+/// as a division by zero.
+pub fn evaluate(node: &Node, variables: &Variables) -> Result<FloxValue, FloxError> {
+    match &node.expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Variable(name) => value_of(node, name, variables),
+        Expr::Unary(UnaryOp::Neg, operand) => match evaluate(operand, variables)? {
+            FloxValue::Integer(value) => value
+                .checked_neg()
+                .map(FloxValue::Integer)
+                .ok_or_else(|| FloxError::new(node.position, "the integer is out of range")),
+            FloxValue::Float(value) => Ok(FloxValue::Float(-value)),
+            value => Err(type_error(operand, &value)),
+        },
+        Expr::Unary(UnaryOp::Not, operand) => {
+            let value = evaluate(operand, variables)?;
+            Ok(FloxValue::Boolean(!boolean(operand, &value)?))
+        }
+        Expr::Binary(op, left, right) => binary(node, *op, left, right, variables),
+        Expr::Call(name, arguments) => call(node, name, arguments, variables),
+    }
+}
+
+/// Compute the value of the `returns` variable of a flox node, in a flow
+/// with the variable definitions `definitions` and the current values
+/// `variables`
+pub fn run(
+    parameters: &FloxParameters,
+    definitions: &DocumentVariables,
+    variables: &Variables,
+) -> Result<VTypeContainer, FloxError> {
+    let node = check_parameters(parameters, definitions)?;
+    let value = evaluate(&node, variables)?;
+    parameters
+        .returns
+        .vtype
+        .container_from_json(&value.to_json())
+        .map_err(|err| FloxError::new(0, &err))
+}
@@ -0,0 +1,219 @@
+//! Flox, the expression language of flox nodes
+//!
+//! A flox node computes the value of its `returns` variable from the
+//! variables of the flow with an expression such as
+//!
+//! ```text
+//! upper(trim(name)) + "!"
+//! count * 2 >= limit && !done
+//! ```
+//!
+//! Expressions are made of
+//!
+//! - literals: integers (`42`), floats (`4.2`), strings (`"a \"quoted\"
+//!   word\n"`) and booleans (`true`, `false`)
+//! - the names of the input, local and output variables of the flow
+//! - the operators `||`, `&&`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `+`, `-`,
+//!   `*`, `/`, `%`, unary `-` and `!`, from the loosest to the tightest
+//!   binding, and parentheses
+//! - calls of the string functions `len`, `upper`, `lower`, `trim`,
+//!   `contains`, `starts_with`, `ends_with`, `replace`, `substring` and of
+//!   `string`, which turns any value into a string
+//!
+//! Values are strings, integers, floats and booleans. Variables of the
+//! string and enum types are strings, those of the integer, float and
+//! boolean types are what their names say. Integers are widened to floats
+//! where the two meet, `+` concatenates strings.
+//!
+//! An expression is parsed with `parser::parse`, type checked against the
+//! variables of its flow with `check::check` and evaluated with
+//! `eval::evaluate`. `eval::run` does all of that for the parameters of a
+//! flox node.
+
+pub mod check;
+pub mod eval;
+pub mod parser;
+
+use std::error;
+use std::fmt;
+
+/// A problem with an expression, at the character `position` counted from
+/// zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloxError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl FloxError {
+    pub fn new(position: usize, message: &str) -> Self {
+        FloxError {
+            position,
+            message: message.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for FloxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl error::Error for FloxError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloxType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+impl FloxType {
+    pub fn is_number(self) -> bool {
+        self == FloxType::Integer || self == FloxType::Float
+    }
+}
+
+impl fmt::Display for FloxType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FloxType::String => write!(f, "string"),
+            FloxType::Integer => write!(f, "integer"),
+            FloxType::Float => write!(f, "float"),
+            FloxType::Boolean => write!(f, "boolean"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FloxValue {
+    String(String),
+    Integer(i32),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl FloxValue {
+    pub fn flox_type(&self) -> FloxType {
+        match self {
+            FloxValue::String(_) => FloxType::String,
+            FloxValue::Integer(_) => FloxType::Integer,
+            FloxValue::Float(_) => FloxType::Float,
+            FloxValue::Boolean(_) => FloxType::Boolean,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            FloxValue::String(value) => serde_json::Value::from(value.clone()),
+            FloxValue::Integer(value) => serde_json::Value::from(*value),
+            FloxValue::Float(value) => serde_json::Value::from(*value),
+            FloxValue::Boolean(value) => serde_json::Value::from(*value),
+        }
+    }
+}
+
+impl fmt::Display for FloxValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FloxValue::String(value) => write!(f, "{}", value),
+            FloxValue::Integer(value) => write!(f, "{}", value),
+            FloxValue::Float(value) => write!(f, "{:?}", value),
+            FloxValue::Boolean(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(FloxValue),
+    Variable(String),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+}
+
+/// A parsed expression, `position` is where it starts, or where the
+/// operator is for binary expressions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub expr: Expr,
+    pub position: usize,
+}
+
+impl Node {
+    /// The names of the variables the expression uses, each once, in the
+    /// order they appear in
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::<&str>::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match &self.expr {
+            Expr::Literal(_) => {}
+            Expr::Variable(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Unary(_, operand) => operand.collect_variables(names),
+            Expr::Binary(_, left, right) => {
+                left.collect_variables(names);
+                right.collect_variables(names);
+            }
+            Expr::Call(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_variables(names);
+                }
+            }
+        }
+    }
+}
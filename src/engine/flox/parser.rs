@@ -0,0 +1,327 @@
+use super::{BinaryOp, Expr, FloxError, FloxValue, Node, UnaryOp};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(FloxValue),
+    Name(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Literal(FloxValue::String(value)) => format!("string {:?}", value),
+            Token::Literal(value) => format!("`{}`", value),
+            Token::Name(name) => format!("`{}`", name),
+            Token::Symbol(symbol) => format!("`{}`", symbol),
+            Token::End => "the end of the expression".to_owned(),
+        }
+    }
+}
+
+/// Longer symbols first, so `<=` is not read as `<`
+const SYMBOLS: [&str; 17] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", ",",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, FloxError> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::<(Token, usize)>::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let float = i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit();
+            if float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = if float {
+                text.parse().map(FloxValue::Float).ok()
+            } else {
+                text.parse().map(FloxValue::Integer).ok()
+            };
+            let value = value.ok_or_else(|| FloxError::new(start, "the number is too large"))?;
+            tokens.push((Token::Literal(value), start));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            let token = match name.as_str() {
+                "true" => Token::Literal(FloxValue::Boolean(true)),
+                "false" => Token::Literal(FloxValue::Boolean(false)),
+                _ => Token::Name(name),
+            };
+            tokens.push((token, start));
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(FloxError::new(start, "the string is not closed")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        let escaped = match chars.get(i + 1) {
+                            Some('"') => '"',
+                            Some('\\') => '\\',
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            _ => return Err(FloxError::new(i, "unknown escape sequence")),
+                        };
+                        value.push(escaped);
+                        i += 2;
+                    }
+                    Some(c) => {
+                        value.push(*c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Literal(FloxValue::String(value)), start));
+        } else {
+            let symbol = SYMBOLS.iter().find(|symbol| {
+                let symbol: Vec<char> = symbol.chars().collect();
+                chars[i..].starts_with(&symbol)
+            });
+            match symbol {
+                Some(symbol) => {
+                    i += symbol.len();
+                    tokens.push((Token::Symbol(symbol), start));
+                }
+                None => {
+                    return Err(FloxError::new(
+                        start,
+                        &format!("unexpected character `{}`", c),
+                    ))
+                }
+            }
+        }
+    }
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+/// How deeply parentheses, calls and unary operators may nest, the parser
+/// recurses for each level
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        if token.0 != Token::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self, expected: &str) -> FloxError {
+        FloxError::new(
+            self.position(),
+            &format!("expected {}, found {}", expected, self.peek().describe()),
+        )
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), FloxError> {
+        if *self.peek() == Token::Symbol(symbol) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", symbol)))
+        }
+    }
+
+    /// Parse one level deeper with `parse`, for the symbol at `position`
+    fn nested<T>(
+        &mut self,
+        position: usize,
+        parse: fn(&mut Self) -> Result<T, FloxError>,
+    ) -> Result<T, FloxError> {
+        if self.depth == MAX_DEPTH {
+            return Err(FloxError::new(
+                position,
+                "the expression is nested too deeply",
+            ));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// The binary operator next in line, if it is one of `ops`
+    fn operator(&self, ops: &[BinaryOp]) -> Option<BinaryOp> {
+        match self.peek() {
+            Token::Symbol(symbol) => ops.iter().find(|op| op.to_string() == *symbol).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Left associative binary operators `ops`, with operands parsed by
+    /// `operand`
+    fn binary(
+        &mut self,
+        ops: &[BinaryOp],
+        operand: fn(&mut Self) -> Result<Node, FloxError>,
+    ) -> Result<Node, FloxError> {
+        let mut left = operand(self)?;
+        while let Some(op) = self.operator(ops) {
+            let (_, position) = self.advance();
+            let right = operand(self)?;
+            left = Node {
+                expr: Expr::Binary(op, Box::new(left), Box::new(right)),
+                position,
+            };
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Node, FloxError> {
+        self.binary(&[BinaryOp::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node, FloxError> {
+        self.binary(&[BinaryOp::And], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Node, FloxError> {
+        let ops = [
+            BinaryOp::Eq,
+            BinaryOp::Ne,
+            BinaryOp::Le,
+            BinaryOp::Ge,
+            BinaryOp::Lt,
+            BinaryOp::Gt,
+        ];
+        let left = self.sum()?;
+        let op = match self.operator(&ops) {
+            Some(op) => op,
+            None => return Ok(left),
+        };
+        let (_, position) = self.advance();
+        let right = self.sum()?;
+        if self.operator(&ops).is_some() {
+            return Err(FloxError::new(
+                self.position(),
+                "comparisons can not be chained, use parentheses",
+            ));
+        }
+        Ok(Node {
+            expr: Expr::Binary(op, Box::new(left), Box::new(right)),
+            position,
+        })
+    }
+
+    fn sum(&mut self) -> Result<Node, FloxError> {
+        self.binary(&[BinaryOp::Add, BinaryOp::Sub], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Node, FloxError> {
+        self.binary(&[BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, FloxError> {
+        let op = match self.peek() {
+            Token::Symbol("-") => UnaryOp::Neg,
+            Token::Symbol("!") => UnaryOp::Not,
+            _ => return self.primary(),
+        };
+        let (_, position) = self.advance();
+        let operand = self.nested(position, Self::unary)?;
+        Ok(Node {
+            expr: Expr::Unary(op, Box::new(operand)),
+            position,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Node, FloxError> {
+        let position = self.position();
+        let expr = match self.peek().clone() {
+            Token::Literal(value) => {
+                self.advance();
+                Expr::Literal(value)
+            }
+            Token::Name(name) => {
+                self.advance();
+                if *self.peek() == Token::Symbol("(") {
+                    Expr::Call(name, self.arguments()?)
+                } else {
+                    Expr::Variable(name)
+                }
+            }
+            Token::Symbol("(") => {
+                self.advance();
+                let node = self.nested(position, Self::or)?;
+                self.expect(")")?;
+                return Ok(node);
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        Ok(Node { expr, position })
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Node>, FloxError> {
+        let position = self.position();
+        self.expect("(")?;
+        let mut arguments = Vec::<Node>::new();
+        if *self.peek() == Token::Symbol(")") {
+            self.advance();
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.nested(position, Self::or)?);
+            match self.peek() {
+                Token::Symbol(",") => {
+                    self.advance();
+                }
+                Token::Symbol(")") => {
+                    self.advance();
+                    return Ok(arguments);
+                }
+                _ => return Err(self.unexpected("`,` or `)`")),
+            }
+        }
+    }
+}
+
+/// Parse `expression`, errors point at the offending character
+pub fn parse(expression: &str) -> Result<Node, FloxError> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        next: 0,
+        depth: 0,
+    };
+    let node = parser.or()?;
+    if *parser.peek() != Token::End {
+        return Err(parser.unexpected("an operator"));
+    }
+    Ok(node)
+}
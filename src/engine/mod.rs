@@ -0,0 +1,5 @@
+pub mod computed;
+pub mod events;
+pub mod flox;
+pub mod validation;
+pub mod xflow;
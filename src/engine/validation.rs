@@ -0,0 +1,110 @@
+//! Attribute validations, the xflows that decide whether a value is valid
+//!
+//! The xflow of a `Validation` gets the value through its input variables,
+//! by name:
+//!
+//! - `value`: the value of the attribute, of the type the flow declares
+//! - `record`: json, the body of the record, including its `id` when it
+//!   has one
+//! - `entity`: a string, the name of the entity
+//! - `attribute`: a string, the name of the attribute
+//!
+//! and passes the value by setting its boolean output variable `valid` to
+//! true. Any other outcome fails the validation with its `message`.
+//! Attributes without a value are not validated, `required` is up to the
+//! constraints.
+
+use super::events::FlowRunner;
+use crate::structure::domain::{Attribute, AttributeStorage, Entity, Validation};
+use crate::structure::record::RecordViolation;
+use crate::structure::xflow::XFlowDocument;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// The ids of the xflows the validations of `entity` refer to
+pub fn validation_xflows(entity: &Entity) -> Vec<Uuid> {
+    let mut ids = Vec::<Uuid>::new();
+    let validations = entity.attributes.iter().flat_map(|a| &a.validations);
+    for validation in validations {
+        if !ids.contains(&validation.xflow.id) {
+            ids.push(validation.xflow.id);
+        }
+    }
+    ids
+}
+
+/// Run one validation on `value`, `Ok(false)` when it fails
+pub fn run_validation(
+    entity: &Entity,
+    attribute: &Attribute,
+    validation: &Validation,
+    value: &Value,
+    record: &Value,
+    xflows: &[XFlowDocument],
+    runner: &dyn FlowRunner,
+) -> Result<bool, String> {
+    let xflow = xflows
+        .iter()
+        .find(|xflow| xflow.id == validation.xflow.id)
+        .ok_or_else(|| format!("xflow {} does not exist", validation.xflow.id))?;
+
+    let mut input = Map::new();
+    input.insert("value".to_owned(), value.clone());
+    input.insert("record".to_owned(), record.clone());
+    input.insert("entity".to_owned(), Value::from(entity.name.clone()));
+    input.insert("attribute".to_owned(), Value::from(attribute.name.clone()));
+    let output = runner.run(xflow, &input)?;
+    let valid = output.get("valid") == Some(&Value::Bool(true));
+    debug!(
+        "validation {} of {}.{}: {}",
+        xflow.id,
+        entity.name,
+        attribute.name,
+        if valid { "passed" } else { "failed" }
+    );
+    Ok(valid)
+}
+
+/// Run the validations of the persistent attributes of `entity` on the
+/// record `body`, reporting the message of every failing validation.
+/// Validations that can not be run are reported as well.
+pub fn validate_record(
+    entity: &Entity,
+    id: Option<&Uuid>,
+    body: &Value,
+    xflows: &[XFlowDocument],
+    runner: &dyn FlowRunner,
+) -> Vec<RecordViolation> {
+    let mut record = body.clone();
+    if let (Value::Object(properties), Some(id)) = (&mut record, id) {
+        properties.insert("id".to_owned(), Value::from(id.to_string()));
+    }
+
+    let mut violations = Vec::<RecordViolation>::new();
+    for attribute in &entity.attributes {
+        if attribute.storage != AttributeStorage::Persistent {
+            continue;
+        }
+        let value = match body.get(&attribute.name) {
+            Some(value) if !value.is_null() => value,
+            _ => continue,
+        };
+        for validation in &attribute.validations {
+            let run = run_validation(
+                entity, attribute, validation, value, &record, xflows, runner,
+            );
+            match run {
+                Ok(true) => {}
+                Ok(false) => violations.push(RecordViolation::new(
+                    Some(&attribute.name),
+                    &validation.message,
+                )),
+                Err(err) => violations.push(RecordViolation::new(
+                    Some(&attribute.name),
+                    &format!("the validation could not be run: {}", err),
+                )),
+            }
+        }
+    }
+    violations
+}
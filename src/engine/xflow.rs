@@ -0,0 +1,218 @@
+//! Running xflows
+//!
+//! A run starts at the entry node of a flow and follows its edges until it
+//! reaches a terminal node. Input variables are seeded from the values
+//! given, or from their defaults, all other variables from their defaults.
+//! The values of the output variables are the result of the run.
+//!
+//! A node with a single outgoing edge leads along it. Where a node has more
+//! than one, the branches of the edges decide: an edge is taken when the
+//! variable named by one of its branches has the value the branch gives as
+//! the default of its type. The one edge without a branch is taken when no
+//! branch matches.
+//!
+//! Flow nodes do nothing but lead on, flox nodes set their `returns`
+//! variable to the value of their expression, see `engine::flox`.
+
+use super::flox;
+use crate::structure::data::{VTypeContainer, VariableDefinition};
+use crate::structure::xflow::{
+    XFlow, XFlowBranch, XFlowEdge, XFlowError, XFlowNode, XFlowNodeParameters, XFlowNodeType,
+};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::mem;
+
+/// The number of nodes a run may visit before it is given up
+pub const MAX_STEPS: usize = 10_000;
+
+#[derive(Debug)]
+pub enum XFlowRunError {
+    /// The flow can not be run at all
+    Structure(XFlowError),
+    /// An input value does not fit its variable
+    Input(String, String),
+    /// Running a node failed
+    Node(i32, String),
+}
+
+impl fmt::Display for XFlowRunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XFlowRunError::Structure(err) => write!(f, "{}", err),
+            XFlowRunError::Input(name, message) => write!(f, "input {}: {}", name, message),
+            XFlowRunError::Node(id, message) => write!(f, "node {}: {}", id, message),
+        }
+    }
+}
+
+impl error::Error for XFlowRunError {}
+
+impl From<XFlowError> for XFlowRunError {
+    fn from(err: XFlowError) -> Self {
+        XFlowRunError::Structure(err)
+    }
+}
+
+/// The values of the variables of a run, by name
+pub type Variables = HashMap<String, VTypeContainer>;
+
+fn seed(xflow: &XFlow, input: &Map<String, Value>) -> Result<Variables, XFlowRunError> {
+    let mut variables = Variables::new();
+    for xvar in &xflow.variables.input {
+        let value = match input.get(&xvar.name).filter(|v| !v.is_null()) {
+            Some(value) => xvar
+                .vtype
+                .container_from_json(value)
+                .map_err(|err| XFlowRunError::Input(xvar.name.clone(), err))?,
+            None => xvar.vtype.new_instance(),
+        };
+        variables.insert(xvar.name.clone(), value);
+    }
+    let others = xflow.variables.local.iter().chain(&xflow.variables.output);
+    for xvar in others {
+        variables.insert(xvar.name.clone(), xvar.vtype.new_instance());
+    }
+    Ok(variables)
+}
+
+fn outputs(definitions: &[VariableDefinition], variables: &Variables) -> Map<String, Value> {
+    definitions
+        .iter()
+        .filter_map(|xvar| {
+            variables
+                .get(&xvar.name)
+                .map(|value| (xvar.name.clone(), value.to_json()))
+        })
+        .collect()
+}
+
+fn step(xflow: &XFlow, node: &XFlowNode, variables: &mut Variables) -> Result<(), XFlowRunError> {
+    match (&node.nodetype, &node.parameters) {
+        (XFlowNodeType::Flow, _) => Ok(()),
+        (XFlowNodeType::Flox, XFlowNodeParameters::Flox(parameters)) => {
+            let value = flox::eval::run(parameters, &xflow.variables, variables)
+                .map_err(|err| XFlowRunError::Node(node.id, err.to_string()))?;
+            variables.insert(parameters.returns.name.clone(), value);
+            Ok(())
+        }
+        (XFlowNodeType::Flox, _) => Err(XFlowRunError::Node(
+            node.id,
+            "the node does not have flox parameters".to_owned(),
+        )),
+        (XFlowNodeType::Call, _) => Err(XFlowRunError::Node(
+            node.id,
+            "Call nodes can not be run yet".to_owned(),
+        )),
+    }
+}
+
+/// Whether `branch` matches the current value of its variable
+fn matches(
+    node: &XFlowNode,
+    branch: &XFlowBranch,
+    variables: &Variables,
+) -> Result<bool, XFlowRunError> {
+    let xvar = &branch.xvar;
+    let value = variables.get(&xvar.name).ok_or_else(|| {
+        XFlowRunError::Node(node.id, format!("variable {} does not exist", xvar.name))
+    })?;
+    let expected = xvar.vtype.new_instance();
+    if mem::discriminant(value) != mem::discriminant(&expected) {
+        return Err(XFlowRunError::Node(
+            node.id,
+            format!(
+                "the branch on variable {} is for {} values",
+                xvar.name,
+                xvar.vtype.type_name()
+            ),
+        ));
+    }
+    Ok(*value == expected)
+}
+
+/// Choose the edge to leave `node` by
+fn choose<'a>(
+    xflow: &'a XFlow,
+    node: &XFlowNode,
+    variables: &Variables,
+) -> Result<&'a XFlowEdge, XFlowRunError> {
+    let edges = xflow.get_out_edges(node);
+    if let [edge] = edges.as_slice() {
+        return Ok(edge);
+    }
+
+    let mut taken = Vec::<&XFlowEdge>::new();
+    let mut otherwise = Vec::<&XFlowEdge>::new();
+    for edge in edges {
+        let branches = xflow.get_branches_for(edge);
+        if branches.is_empty() {
+            otherwise.push(edge);
+            continue;
+        }
+        for branch in branches {
+            if matches(node, branch, variables)? {
+                taken.push(edge);
+                break;
+            }
+        }
+    }
+
+    match (taken.as_slice(), otherwise.as_slice()) {
+        ([edge], _) | ([], [edge]) => Ok(edge),
+        ([], []) => Err(XFlowRunError::Node(
+            node.id,
+            "no branch matches and there is no edge without one".to_owned(),
+        )),
+        ([], _) => Err(XFlowRunError::Node(
+            node.id,
+            "no branch matches and more than one edge has none".to_owned(),
+        )),
+        _ => Err(XFlowRunError::Node(
+            node.id,
+            "the branches of more than one edge match".to_owned(),
+        )),
+    }
+}
+
+fn next<'a>(
+    xflow: &'a XFlow,
+    node: &XFlowNode,
+    variables: &Variables,
+) -> Result<&'a XFlowNode, XFlowRunError> {
+    if xflow.get_out_edges(node).is_empty() {
+        return Err(XFlowRunError::Node(
+            node.id,
+            "the node leads nowhere".to_owned(),
+        ));
+    }
+    let edge = choose(xflow, node, variables)?;
+    xflow
+        .get_node_id(edge.target)
+        .ok_or_else(|| XFlowRunError::Node(node.id, format!("node {} does not exist", edge.target)))
+}
+
+/// Run `xflow` with `input` as the values of its input variables, by name.
+/// Values for variables the flow does not have are ignored.
+pub fn run(xflow: &XFlow, input: &Map<String, Value>) -> Result<Map<String, Value>, XFlowRunError> {
+    let mut variables = seed(xflow, input)?;
+    let terminals: Vec<i32> = xflow
+        .get_terminal_nodes()?
+        .iter()
+        .map(|node| node.id)
+        .collect();
+    let mut node = xflow.get_entry_node()?;
+    for _ in 0..MAX_STEPS {
+        if terminals.contains(&node.id) {
+            return Ok(outputs(&xflow.variables.output, &variables));
+        }
+        step(xflow, node, &mut variables)?;
+        node = next(xflow, node, &variables)?;
+    }
+    Err(XFlowRunError::Node(
+        node.id,
+        format!("the flow did not end within {} steps", MAX_STEPS),
+    ))
+}
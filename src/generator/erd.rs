@@ -0,0 +1,289 @@
+//! Entity relationship diagrams for a `Domain`
+//!
+//! Diagrams are written as PlantUML, Mermaid or Graphviz DOT text. Every
+//! entity is drawn with its id and typed attributes, every relationship once
+//! with the cardinality of both ends. A `belongs_to` that is the inverse of a
+//! `has_one` or `has_many` is drawn by the owning side only.
+
+use super::sorted_entities;
+use crate::structure::data::VType;
+use crate::structure::domain::{Attribute, Domain, DomainDocument, Entity, ReferenceType};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(GraphQLEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ErdFormat {
+    #[graphql(name = "PLANTUML")]
+    PlantUml,
+    Mermaid,
+    Dot,
+}
+
+impl fmt::Display for ErdFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErdFormat::PlantUml => write!(f, "plantuml"),
+            ErdFormat::Mermaid => write!(f, "mermaid"),
+            ErdFormat::Dot => write!(f, "dot"),
+        }
+    }
+}
+
+impl FromStr for ErdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plantuml" => Ok(ErdFormat::PlantUml),
+            "mermaid" => Ok(ErdFormat::Mermaid),
+            "dot" => Ok(ErdFormat::Dot),
+            _ => Err(format!("unknown diagram format {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cardinality {
+    ZeroOrOne,
+    ExactlyOne,
+    ZeroOrMany,
+}
+
+/// A line between two entities, read as "`from` `name` `to`"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relationship {
+    pub from: String,
+    pub from_cardinality: Cardinality,
+    pub to: String,
+    pub to_cardinality: Cardinality,
+    pub name: String,
+}
+
+/// The type of an attribute as shown in a diagram, enums by their name
+pub fn type_label(vtype: &VType) -> String {
+    match vtype {
+        VType::VTypeEnum(t) => t.name.clone(),
+        VType::VTypeList(t) => format!("{}[]", type_label(&t.item)),
+        _ => vtype.type_name().to_owned(),
+    }
+}
+
+/// The relationships of `domain`, one per related pair of references
+pub fn relationships(domain: &Domain) -> Vec<Relationship> {
+    let mut relationships = Vec::<Relationship>::new();
+    for entity in sorted_entities(domain) {
+        for reference in &entity.references {
+            let other = domain.entities.iter().find(|e| e.name == reference.other);
+            let back = |reftype: ReferenceType| {
+                other
+                    .iter()
+                    .flat_map(|o| o.references.iter())
+                    .filter(|r| r.id != reference.id)
+                    .find(|r| r.reftype == reftype && r.other == entity.name)
+            };
+            let (from_cardinality, to_cardinality) = match reference.reftype {
+                ReferenceType::HasOne => (Cardinality::ExactlyOne, Cardinality::ZeroOrOne),
+                ReferenceType::HasMany => (Cardinality::ExactlyOne, Cardinality::ZeroOrMany),
+                ReferenceType::BelongsTo => {
+                    if back(ReferenceType::HasOne).is_some()
+                        || back(ReferenceType::HasMany).is_some()
+                    {
+                        continue;
+                    }
+                    (Cardinality::ZeroOrMany, Cardinality::ExactlyOne)
+                }
+                ReferenceType::ManyToMany => {
+                    // Draw the pair from the entity that comes first
+                    let drawn = match (other, back(ReferenceType::ManyToMany)) {
+                        (Some(other), Some(r)) => (other.id, r.id) < (entity.id, reference.id),
+                        _ => false,
+                    };
+                    if drawn {
+                        continue;
+                    }
+                    (Cardinality::ZeroOrMany, Cardinality::ZeroOrMany)
+                }
+            };
+            relationships.push(Relationship {
+                from: entity.name.clone(),
+                from_cardinality,
+                to: reference.other.clone(),
+                to_cardinality,
+                name: reference.name.clone(),
+            });
+        }
+    }
+    relationships
+}
+
+/// Crow's foot ends as PlantUML and Mermaid write them
+fn crows_foot(cardinality: Cardinality, left: bool) -> &'static str {
+    match (cardinality, left) {
+        (Cardinality::ZeroOrOne, true) => "|o",
+        (Cardinality::ZeroOrOne, false) => "o|",
+        (Cardinality::ExactlyOne, _) => "||",
+        (Cardinality::ZeroOrMany, true) => "}o",
+        (Cardinality::ZeroOrMany, false) => "o{",
+    }
+}
+
+fn plantuml_attribute(attribute: &Attribute) -> String {
+    format!(
+        "  {}{} : {}\n",
+        if attribute.constraints.required {
+            "* "
+        } else {
+            ""
+        },
+        attribute.name,
+        type_label(&attribute.vtype)
+    )
+}
+
+fn plantuml_entity(entity: &Entity) -> String {
+    let attributes: String = entity.attributes.iter().map(plantuml_attribute).collect();
+    format!(
+        "entity {} {{\n  * id : uuid <<PK>>\n  --\n{}}}\n",
+        entity.name, attributes
+    )
+}
+
+fn mermaid_attribute(attribute: &Attribute) -> String {
+    format!(
+        "    {} {}{}\n",
+        type_label(&attribute.vtype),
+        attribute.name,
+        if attribute.constraints.unique {
+            " UK"
+        } else {
+            ""
+        }
+    )
+}
+
+fn mermaid_entity(entity: &Entity) -> String {
+    let attributes: String = entity.attributes.iter().map(mermaid_attribute).collect();
+    format!("  {} {{\n    uuid id PK\n{}  }}\n", entity.name, attributes)
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Escape the characters that structure a record label
+fn dot_record_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+fn dot_entity(entity: &Entity) -> String {
+    let attributes: String = entity
+        .attributes
+        .iter()
+        .map(|a| dot_record_escape(&format!("{} : {}", a.name, type_label(&a.vtype))) + "\\l")
+        .collect();
+    format!(
+        "  {} [label=\"{{{}|id : uuid\\l{}}}\"];\n",
+        dot_quote(&entity.name),
+        dot_record_escape(&entity.name),
+        attributes
+    )
+}
+
+fn dot_arrow(cardinality: Cardinality) -> &'static str {
+    match cardinality {
+        Cardinality::ZeroOrOne => "teeodot",
+        Cardinality::ExactlyOne => "teetee",
+        Cardinality::ZeroOrMany => "crowodot",
+    }
+}
+
+fn plantuml(domain: &Domain, title: Option<&str>) -> String {
+    let mut out = String::from("@startuml\n");
+    if let Some(title) = title {
+        out.push_str(&format!("title {}\n", title));
+    }
+    for entity in sorted_entities(domain) {
+        out.push('\n');
+        out.push_str(&plantuml_entity(entity));
+    }
+    let relationships = relationships(domain);
+    if !relationships.is_empty() {
+        out.push('\n');
+    }
+    for r in relationships {
+        out.push_str(&format!(
+            "{} {}--{} {} : {}\n",
+            r.from,
+            crows_foot(r.from_cardinality, true),
+            crows_foot(r.to_cardinality, false),
+            r.to,
+            r.name
+        ));
+    }
+    out.push_str("\n@enduml\n");
+    out
+}
+
+fn mermaid(domain: &Domain, title: Option<&str>) -> String {
+    let mut out = String::new();
+    if let Some(title) = title {
+        out.push_str(&format!("---\ntitle: {}\n---\n", title));
+    }
+    out.push_str("erDiagram\n");
+    for entity in sorted_entities(domain) {
+        out.push_str(&mermaid_entity(entity));
+    }
+    for r in relationships(domain) {
+        out.push_str(&format!(
+            "  {} {}--{} {} : {}\n",
+            r.from,
+            crows_foot(r.from_cardinality, true),
+            crows_foot(r.to_cardinality, false),
+            r.to,
+            dot_quote(&r.name)
+        ));
+    }
+    out
+}
+
+fn dot(domain: &Domain, title: Option<&str>) -> String {
+    let mut out = format!("digraph {} {{\n", dot_quote(title.unwrap_or("domain")));
+    if let Some(title) = title {
+        out.push_str(&format!("  label={};\n", dot_quote(title)));
+    }
+    out.push_str("  rankdir=LR;\n  node [shape=record];\n");
+    for entity in sorted_entities(domain) {
+        out.push_str(&dot_entity(entity));
+    }
+    for r in relationships(domain) {
+        out.push_str(&format!(
+            "  {} -> {} [label={}, dir=both, arrowtail={}, arrowhead={}];\n",
+            dot_quote(&r.from),
+            dot_quote(&r.to),
+            dot_quote(&r.name),
+            dot_arrow(r.from_cardinality),
+            dot_arrow(r.to_cardinality)
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Draw the entities of `domain` and their relationships
+pub fn domain_to_erd(domain: &Domain, format: ErdFormat, title: Option<&str>) -> String {
+    match format {
+        ErdFormat::PlantUml => plantuml(domain, title),
+        ErdFormat::Mermaid => mermaid(domain, title),
+        ErdFormat::Dot => dot(domain, title),
+    }
+}
+
+/// Draw a domain document, titled with the name of the document
+pub fn document_to_erd(doc: &DomainDocument, format: ErdFormat) -> String {
+    domain_to_erd(&doc.body, format, Some(&doc.name))
+}
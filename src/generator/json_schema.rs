@@ -0,0 +1,261 @@
+//! JSON Schema (draft 2020-12) for a `Domain`
+//!
+//! Every entity and every enum of the type catalog becomes a definition
+//! under `$defs`. Attributes become properties, with their constraints as
+//! validation keywords, and references become `$ref`s to the definition of
+//! the other entity. Uniqueness and indexes are left to the database.
+
+use super::{check_domain, sorted_entities, GeneratorError};
+use crate::structure::data::VType;
+use crate::structure::domain::{
+    Attribute, AttributeStorage, Domain, DomainDocument, Entity, EnumDefinition, Reference,
+    ReferenceType,
+};
+use serde_json::{json, Map, Value};
+
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The `$ref` of a definition under `$defs`, escaped as a JSON pointer
+pub fn definition_ref(name: &str) -> String {
+    format!("#/$defs/{}", name.replace('~', "~0").replace('/', "~1"))
+}
+
+const DATE_TIME_PATTERN: &str =
+    "^[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}(\\.[0-9]+)?$";
+
+fn decimal_pattern(precision: Option<i32>, scale: Option<i32>) -> String {
+    match (precision, scale) {
+        (Some(precision), Some(0)) | (Some(precision), None) => {
+            format!("^[-+]?[0-9]{{1,{}}}$", precision)
+        }
+        (Some(precision), Some(scale)) => format!(
+            "^[-+]?[0-9]{{1,{}}}(\\.[0-9]{{1,{}}})?$",
+            (precision - scale).max(1),
+            scale
+        ),
+        _ => "^[-+]?[0-9]+(\\.[0-9]+)?$".to_owned(),
+    }
+}
+
+/// The schema of the values of `vtype`, including its default
+pub fn vtype_schema(vtype: &VType) -> Map<String, Value> {
+    let mut schema = Map::new();
+    let mut set = |key: &str, value: Value| {
+        schema.insert(key.to_owned(), value);
+    };
+
+    match vtype {
+        VType::VTypeString(t) => {
+            set("type", json!("string"));
+            if let Some(default) = &t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeBoolean(t) => {
+            set("type", json!("boolean"));
+            if let Some(default) = t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeInteger(t) => {
+            set("type", json!("integer"));
+            if let Some(min) = t.min {
+                set("minimum", json!(min));
+            }
+            if let Some(max) = t.max {
+                set("maximum", json!(max));
+            }
+            if let Some(default) = t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeFloat(t) => {
+            set("type", json!("number"));
+            if let Some(min) = t.min {
+                set("minimum", json!(min));
+            }
+            if let Some(max) = t.max {
+                set("maximum", json!(max));
+            }
+            if let Some(default) = t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeDecimal(t) => {
+            // Decimals travel as strings so they keep their precision
+            set("type", json!("string"));
+            set("pattern", json!(decimal_pattern(t.precision, t.scale)));
+            if let Some(default) = &t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeDate(t) => {
+            set("type", json!("string"));
+            set("format", json!("date"));
+            if let Some(default) = t.default {
+                set("default", json!(default.to_string()));
+            }
+        }
+        VType::VTypeDateTime(t) => {
+            // Timestamps carry no time zone, which the `date-time` format
+            // requires
+            set("type", json!("string"));
+            set("pattern", json!(DATE_TIME_PATTERN));
+            if let Some(default) = t.default {
+                set(
+                    "default",
+                    json!(default.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                );
+            }
+        }
+        VType::VTypeUuid(t) => {
+            set("type", json!("string"));
+            set("format", json!("uuid"));
+            if let Some(default) = t.default {
+                set("default", json!(default.to_string()));
+            }
+        }
+        VType::VTypeJson(t) => {
+            if let Some(default) = &t.default {
+                if let Ok(default) = serde_json::from_str::<Value>(default) {
+                    set("default", default);
+                }
+            }
+        }
+        VType::VTypeEnum(t) => {
+            set("$ref", json!(definition_ref(&t.name)));
+            if let Some(default) = &t.default {
+                set("default", json!(default));
+            }
+        }
+        VType::VTypeList(t) => {
+            set("type", json!("array"));
+            set("items", Value::Object(vtype_schema(&t.item)));
+        }
+    }
+    schema
+}
+
+/// The schema of an attribute, its value type narrowed by its constraints
+pub fn attribute_schema(attribute: &Attribute) -> Value {
+    let mut schema = vtype_schema(&attribute.vtype);
+    let constraints = &attribute.constraints;
+    let (min_key, max_key) = match attribute.vtype {
+        VType::VTypeList(_) => ("minItems", "maxItems"),
+        _ => ("minLength", "maxLength"),
+    };
+    if let Some(min) = constraints.min_length {
+        schema.insert(min_key.to_owned(), json!(min));
+    }
+    if let Some(max) = constraints.max_length {
+        schema.insert(max_key.to_owned(), json!(max));
+    }
+    if let Some(pattern) = &constraints.pattern {
+        schema.insert("pattern".to_owned(), json!(pattern));
+    }
+    if let AttributeStorage::Computed { .. } = attribute.storage {
+        schema.insert("readOnly".to_owned(), json!(true));
+    }
+    Value::Object(schema)
+}
+
+pub fn reference_schema(reference: &Reference) -> Value {
+    let target = json!({ "$ref": definition_ref(&reference.other) });
+    match reference.reftype {
+        ReferenceType::BelongsTo | ReferenceType::HasOne => target,
+        ReferenceType::HasMany | ReferenceType::ManyToMany => json!({
+            "type": "array",
+            "items": target,
+        }),
+    }
+}
+
+pub fn entity_schema(entity: &Entity) -> Value {
+    let mut properties = Map::new();
+    properties.insert(
+        "id".to_owned(),
+        json!({ "type": "string", "format": "uuid", "readOnly": true }),
+    );
+    let mut required = Vec::<String>::new();
+    for attribute in &entity.attributes {
+        properties.insert(attribute.name.clone(), attribute_schema(attribute));
+        if attribute.constraints.required {
+            required.push(attribute.name.clone());
+        }
+    }
+    for reference in &entity.references {
+        properties.insert(reference.name.clone(), reference_schema(reference));
+    }
+
+    let mut schema = Map::new();
+    schema.insert("title".to_owned(), json!(entity.name));
+    schema.insert("type".to_owned(), json!("object"));
+    schema.insert("properties".to_owned(), Value::Object(properties));
+    if !required.is_empty() {
+        schema.insert("required".to_owned(), json!(required));
+    }
+    schema.insert("additionalProperties".to_owned(), json!(false));
+    Value::Object(schema)
+}
+
+pub fn enum_schema(def: &EnumDefinition) -> Value {
+    json!({
+        "title": def.name,
+        "type": "string",
+        "enum": def.values,
+    })
+}
+
+/// Generate the `$defs` for all entities and enums of `domain`
+pub fn domain_to_json_schema(domain: &Domain) -> Result<Map<String, Value>, GeneratorError> {
+    check_domain(domain)?;
+
+    // Entities and enums share `$defs`, attributes and references share the
+    // properties of their entity
+    let mut clashes = Vec::<String>::new();
+    for def in &domain.types.enums {
+        if domain.entities.iter().any(|e| e.name == def.name) {
+            clashes.push(format!("Enum {} has the name of an entity", def.name));
+        }
+    }
+    for entity in &domain.entities {
+        for reference in &entity.references {
+            if reference.name == "id" {
+                clashes.push(format!(
+                    "Reference {}.id clashes with the primary key",
+                    entity.name
+                ));
+            }
+            if entity.attributes.iter().any(|a| a.name == reference.name) {
+                clashes.push(format!(
+                    "Reference {}.{} has the name of an attribute",
+                    entity.name, reference.name
+                ));
+            }
+        }
+    }
+    if !clashes.is_empty() {
+        return Err(GeneratorError::InvalidDomain(clashes));
+    }
+
+    let mut defs = Map::new();
+    for def in &domain.types.enums {
+        defs.insert(def.name.clone(), enum_schema(def));
+    }
+    for entity in sorted_entities(domain) {
+        defs.insert(entity.name.clone(), entity_schema(entity));
+    }
+    Ok(defs)
+}
+
+/// Generate the JSON Schema of a domain document, identified by the id of
+/// the document
+pub fn document_to_json_schema(doc: &DomainDocument) -> Result<Value, GeneratorError> {
+    let defs = domain_to_json_schema(&doc.body)?;
+    Ok(json!({
+        "$schema": DIALECT,
+        "$id": format!("urn:uuid:{}", doc.id),
+        "title": doc.name,
+        "$defs": defs,
+    }))
+}
@@ -0,0 +1,450 @@
+//! PostgreSQL migrations between two versions of a `Domain`
+//!
+//! A migration is a pair of scripts, `up.sql` moves the database from the
+//! older version of the domain to the newer one and `down.sql` moves it
+//! back. They are laid out the way diesel expects them, in a directory
+//! named after the time the migration was made, as in `migrations/`.
+
+use super::sql::{
+    check_name, column_check, column_definition, create_enum, create_index, create_table,
+    foreign_key_constraint, foreign_key_name, foreign_keys, has_index, index_name, quote_ident,
+    quote_literal, sql_default, sql_type, unique_name, ForeignKey,
+};
+use super::{check_domain, GeneratorError};
+use crate::structure::domain::{Attribute, Domain};
+use crate::structure::domain_diff::{DomainChange, DomainDiff};
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Migration {
+    pub up: String,
+    pub down: String,
+}
+
+impl Migration {
+    pub fn is_empty(&self) -> bool {
+        self.up.is_empty() && self.down.is_empty()
+    }
+
+    /// The name of the directory holding the migration, e.g.
+    /// `2019-12-27-091508_create_projects`
+    pub fn directory_name(name: &str, at: &NaiveDateTime) -> String {
+        format!("{}_{}", at.format("%Y-%m-%d-%H%M%S"), name)
+    }
+
+    /// Write `up.sql` and `down.sql` into a new migration directory in `dir`
+    pub fn write(&self, dir: &Path, name: &str, at: &NaiveDateTime) -> io::Result<PathBuf> {
+        let path = dir.join(Migration::directory_name(name, at));
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("up.sql"), &self.up)?;
+        fs::write(path.join("down.sql"), &self.down)?;
+        Ok(path)
+    }
+}
+
+/// Generate the migration from `from` to `to`
+pub fn domain_migration(from: &Domain, to: &Domain) -> Result<Migration, GeneratorError> {
+    check_domain(from)?;
+    check_domain(to)?;
+    Ok(Migration {
+        up: migration_sql(from, to),
+        down: migration_sql(to, from),
+    })
+}
+
+/// The statements of a migration, grouped by the order they have to run in
+#[derive(Default)]
+struct Statements {
+    create_types: Vec<String>,
+    renames: Vec<String>,
+    drop_foreign_keys: Vec<String>,
+    drops: Vec<String>,
+    creates: Vec<String>,
+    alters: Vec<String>,
+    add_foreign_keys: Vec<String>,
+    drop_types: Vec<String>,
+}
+
+impl Statements {
+    fn into_sql(self) -> String {
+        let statements: Vec<String> = vec![
+            self.create_types,
+            self.renames,
+            self.drop_foreign_keys,
+            self.drops,
+            self.creates,
+            self.alters,
+            self.add_foreign_keys,
+            self.drop_types,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        statements.join("\n")
+    }
+}
+
+fn alter_table(table: &str, action: &str) -> String {
+    format!("ALTER TABLE {} {};\n", quote_ident(table), action)
+}
+
+fn rename_constraint(table: &str, from: &str, to: &str) -> String {
+    alter_table(
+        table,
+        &format!(
+            "RENAME CONSTRAINT {} TO {}",
+            quote_ident(from),
+            quote_ident(to)
+        ),
+    )
+}
+
+fn rename_index(from: &str, to: &str) -> String {
+    format!(
+        "ALTER INDEX {} RENAME TO {};\n",
+        quote_ident(from),
+        quote_ident(to)
+    )
+}
+
+/// Rename the index and constraints of a column after its table or the
+/// column itself got renamed
+fn rename_column_objects(
+    statements: &mut Vec<String>,
+    table: &str,
+    attribute: &Attribute,
+    (from_table, from_column): (&str, &str),
+    (to_table, to_column): (&str, &str),
+) {
+    if has_index(attribute) {
+        statements.push(rename_index(
+            &index_name(from_table, from_column),
+            &index_name(to_table, to_column),
+        ));
+    }
+    if attribute.constraints.unique {
+        statements.push(rename_constraint(
+            table,
+            &unique_name(from_table, from_column),
+            &unique_name(to_table, to_column),
+        ));
+    }
+    if column_check(attribute).is_some() {
+        statements.push(rename_constraint(
+            table,
+            &check_name(from_table, from_column),
+            &check_name(to_table, to_column),
+        ));
+    }
+}
+
+fn add_column(statements: &mut Vec<String>, table: &str, attribute: &Attribute) {
+    statements.push(alter_table(
+        table,
+        &format!("ADD COLUMN {}", column_definition(table, attribute)),
+    ));
+    if has_index(attribute) {
+        statements.push(create_index(table, &attribute.name));
+    }
+}
+
+fn drop_column(table: &str, column: &str) -> String {
+    alter_table(table, &format!("DROP COLUMN {}", quote_ident(column)))
+}
+
+/// Alter a stored column from `from` to `to`, both under the name of `to`
+fn alter_column(statements: &mut Vec<String>, table: &str, from: &Attribute, to: &Attribute) {
+    let column = quote_ident(&to.name);
+    let alter_column =
+        |action: String| alter_table(table, &format!("ALTER COLUMN {} {}", column, action));
+
+    let new_type = sql_type(&to.vtype);
+    let type_changed = sql_type(&from.vtype) != new_type;
+    let (old_check, new_check) = (column_check(from), column_check(to));
+    let check_changed = type_changed || old_check != new_check;
+    let (old_default, new_default) = (sql_default(&from.vtype), sql_default(&to.vtype));
+    let default_changed = type_changed || old_default != new_default;
+
+    if old_check.is_some() && check_changed {
+        statements.push(alter_table(
+            table,
+            &format!(
+                "DROP CONSTRAINT {}",
+                quote_ident(&check_name(table, &to.name))
+            ),
+        ));
+    }
+    if from.constraints.unique && !to.constraints.unique {
+        statements.push(alter_table(
+            table,
+            &format!(
+                "DROP CONSTRAINT {}",
+                quote_ident(&unique_name(table, &to.name))
+            ),
+        ));
+    }
+    if has_index(from) && !has_index(to) {
+        statements.push(format!(
+            "DROP INDEX {};\n",
+            quote_ident(&index_name(table, &to.name))
+        ));
+    }
+    if old_default.is_some() && default_changed {
+        statements.push(alter_column("DROP DEFAULT".to_owned()));
+    }
+    if type_changed {
+        statements.push(alter_column(format!(
+            "TYPE {} USING {}::{}",
+            new_type, column, new_type
+        )));
+    }
+    if let Some(default) = new_default.filter(|_| default_changed) {
+        statements.push(alter_column(format!("SET DEFAULT {}", default)));
+    }
+    match (from.constraints.required, to.constraints.required) {
+        (false, true) => statements.push(alter_column("SET NOT NULL".to_owned())),
+        (true, false) => statements.push(alter_column("DROP NOT NULL".to_owned())),
+        _ => {}
+    }
+    if !from.constraints.unique && to.constraints.unique {
+        statements.push(alter_table(
+            table,
+            &format!(
+                "ADD CONSTRAINT {} UNIQUE ({})",
+                quote_ident(&unique_name(table, &to.name)),
+                column
+            ),
+        ));
+    }
+    if let Some(check) = new_check.filter(|_| check_changed) {
+        statements.push(alter_table(
+            table,
+            &format!(
+                "ADD CONSTRAINT {} CHECK ({})",
+                quote_ident(&check_name(table, &to.name)),
+                check
+            ),
+        ));
+    }
+    if !has_index(from) && has_index(to) {
+        statements.push(create_index(table, &to.name));
+    }
+}
+
+fn apply_change(statements: &mut Statements, change: &DomainChange) {
+    match change {
+        DomainChange::AddEnum(def) => statements.create_types.push(create_enum(def)),
+        DomainChange::RemoveEnum(def) => statements
+            .drop_types
+            .push(format!("DROP TYPE {};\n", quote_ident(&def.name))),
+        DomainChange::ChangeEnum { from, to } => {
+            if from.name != to.name {
+                statements.create_types.push(format!(
+                    "ALTER TYPE {} RENAME TO {};\n",
+                    quote_ident(&from.name),
+                    quote_ident(&to.name)
+                ));
+            }
+            for value in to.values.iter().filter(|v| !from.values.contains(v)) {
+                statements.create_types.push(format!(
+                    "ALTER TYPE {} ADD VALUE {};\n",
+                    quote_ident(&to.name),
+                    quote_literal(value)
+                ));
+            }
+            for value in from.values.iter().filter(|v| !to.values.contains(v)) {
+                statements.create_types.push(format!(
+                    "-- PostgreSQL can not drop value {} from {}\n",
+                    quote_literal(value),
+                    quote_ident(&to.name)
+                ));
+            }
+        }
+        DomainChange::AddEntity(entity) => {
+            statements.creates.push(create_table(entity, &[]));
+            for attribute in entity.stored_attributes().filter(|a| has_index(a)) {
+                statements
+                    .creates
+                    .push(create_index(&entity.name, &attribute.name));
+            }
+        }
+        DomainChange::RemoveEntity(entity) => statements
+            .drops
+            .push(format!("DROP TABLE {};\n", quote_ident(&entity.name))),
+        DomainChange::RenameEntity { from, to } => {
+            statements.renames.push(alter_table(
+                &from.name,
+                &format!("RENAME TO {}", quote_ident(&to.name)),
+            ));
+            for attribute in from.stored_attributes() {
+                rename_column_objects(
+                    &mut statements.renames,
+                    &to.name,
+                    attribute,
+                    (&from.name, &attribute.name),
+                    (&to.name, &attribute.name),
+                );
+            }
+        }
+        DomainChange::AddAttribute { entity, attribute } => {
+            if attribute.storage.is_stored() {
+                add_column(&mut statements.alters, entity, attribute);
+            }
+        }
+        DomainChange::RemoveAttribute { entity, attribute } => {
+            if attribute.storage.is_stored() {
+                statements.drops.push(drop_column(entity, &attribute.name));
+            }
+        }
+        DomainChange::RenameAttribute { entity, from, to } => {
+            if from.storage.is_stored() {
+                statements.renames.push(alter_table(
+                    entity,
+                    &format!(
+                        "RENAME COLUMN {} TO {}",
+                        quote_ident(&from.name),
+                        quote_ident(&to.name)
+                    ),
+                ));
+                rename_column_objects(
+                    &mut statements.renames,
+                    entity,
+                    from,
+                    (entity, &from.name),
+                    (entity, &to.name),
+                );
+            }
+        }
+        DomainChange::RetypeAttribute { entity, from, to } => {
+            // A rename of the attribute has already been applied
+            let from = Attribute {
+                name: to.name.clone(),
+                ..from.clone()
+            };
+            match (from.storage.is_stored(), to.storage.is_stored()) {
+                (true, true) => alter_column(&mut statements.alters, entity, &from, to),
+                (true, false) => statements.drops.push(drop_column(entity, &to.name)),
+                (false, true) => add_column(&mut statements.alters, entity, to),
+                (false, false) => {}
+            }
+        }
+        // References are migrated through the foreign keys they give rise to
+        DomainChange::AddReference { .. }
+        | DomainChange::RemoveReference { .. }
+        | DomainChange::RenameReference { .. }
+        | DomainChange::RetypeReference { .. } => {}
+    }
+}
+
+fn add_foreign_key_column(statements: &mut Statements, fk: &ForeignKey) {
+    statements.add_foreign_keys.push(alter_table(
+        &fk.table,
+        &format!(
+            "ADD COLUMN {} UUID,\n  ADD {}",
+            quote_ident(&fk.column),
+            foreign_key_constraint(fk)
+        ),
+    ));
+}
+
+/// Migrate the foreign keys of `from` to those of `to`, matching them up by
+/// the reference they stem from
+fn apply_foreign_keys(statements: &mut Statements, from: &Domain, to: &Domain) {
+    let renamed: HashMap<&str, &str> = from
+        .entities
+        .iter()
+        .filter_map(|old| {
+            to.entities
+                .iter()
+                .find(|new| new.id == old.id)
+                .map(|new| (old.name.as_str(), new.name.as_str()))
+        })
+        .collect();
+    let removed = |table: &str| !renamed.contains_key(table);
+    let rename = |table: &str| {
+        renamed
+            .get(table)
+            .map_or(table.to_string(), |t| t.to_string())
+    };
+
+    let old_fks = foreign_keys(from);
+    let new_fks = foreign_keys(to);
+
+    for old in &old_fks {
+        let kept = new_fks
+            .iter()
+            .any(|new| new.reference == old.reference && new.table == rename(&old.table));
+        if kept {
+            continue;
+        }
+        // The constraints of dropped tables go first, in case the tables
+        // refer to one another
+        if removed(&old.table) {
+            statements.drop_foreign_keys.push(alter_table(
+                &old.table,
+                &format!("DROP CONSTRAINT {}", quote_ident(&foreign_key_name(old))),
+            ));
+        } else {
+            statements
+                .drop_foreign_keys
+                .push(drop_column(&rename(&old.table), &old.column));
+        }
+    }
+
+    for new in &new_fks {
+        let old = old_fks
+            .iter()
+            .find(|old| old.reference == new.reference && rename(&old.table) == new.table);
+        let old = match old {
+            Some(old) => old,
+            None => {
+                add_foreign_key_column(statements, new);
+                continue;
+            }
+        };
+
+        if old.column != new.column {
+            statements.renames.push(alter_table(
+                &new.table,
+                &format!(
+                    "RENAME COLUMN {} TO {}",
+                    quote_ident(&old.column),
+                    quote_ident(&new.column)
+                ),
+            ));
+        }
+        if rename(&old.target) != new.target {
+            statements.drop_foreign_keys.push(alter_table(
+                &new.table,
+                &format!("DROP CONSTRAINT {}", quote_ident(&foreign_key_name(old))),
+            ));
+            statements.add_foreign_keys.push(alter_table(
+                &new.table,
+                &format!("ADD {}", foreign_key_constraint(new)),
+            ));
+        } else if foreign_key_name(old) != foreign_key_name(new) {
+            statements.renames.push(rename_constraint(
+                &new.table,
+                &foreign_key_name(old),
+                &foreign_key_name(new),
+            ));
+        }
+    }
+}
+
+fn migration_sql(from: &Domain, to: &Domain) -> String {
+    let diff = DomainDiff::between(from, to);
+    if diff.is_empty() {
+        return String::new();
+    }
+    let mut statements = Statements::default();
+    for change in &diff.changes {
+        apply_change(&mut statements, change);
+    }
+    apply_foreign_keys(&mut statements, from, to);
+    statements.into_sql()
+}
@@ -0,0 +1,85 @@
+use crate::structure::domain::{DiagnosticLevel, Domain, Entity};
+use std::error;
+use std::fmt;
+
+pub mod erd;
+pub mod json_schema;
+pub mod migration;
+pub mod model;
+pub mod sql;
+
+#[derive(Debug, PartialEq)]
+pub enum GeneratorError {
+    InvalidDomain(Vec<String>),
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeneratorError::InvalidDomain(problems) => {
+                write!(f, "invalid domain: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl error::Error for GeneratorError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+pub fn sorted_entities(domain: &Domain) -> Vec<&Entity> {
+    let mut entities: Vec<&Entity> = domain.entities.iter().collect();
+    entities.sort_by_key(|e| e.id);
+    entities
+}
+
+/// Refuse domains that no code can be generated for
+pub fn check_domain(domain: &Domain) -> Result<(), GeneratorError> {
+    let mut problems: Vec<String> = domain
+        .validate()
+        .into_iter()
+        .filter(|d| d.level == DiagnosticLevel::Error)
+        .map(|d| d.message)
+        .collect();
+    for entity in &domain.entities {
+        if entity.stored_attributes().any(|a| a.name == "id") {
+            problems.push(format!(
+                "Attribute {}.id clashes with the primary key",
+                entity.name
+            ));
+        }
+    }
+    // Foreign key columns are named after their reference, they must not
+    // collide with a column or with each other
+    let fks = sql::foreign_keys(domain);
+    for (i, fk) in fks.iter().enumerate() {
+        let clashes = domain
+            .entities
+            .iter()
+            .filter(|e| e.name == fk.table)
+            .flat_map(|e| e.stored_attributes())
+            .any(|a| a.name == fk.column);
+        if clashes {
+            problems.push(format!(
+                "Foreign key {}.{} clashes with an attribute",
+                fk.table, fk.column
+            ));
+        }
+        if fks[..i]
+            .iter()
+            .any(|other| other.table == fk.table && other.column == fk.column)
+        {
+            problems.push(format!(
+                "Foreign key {}.{} stems from more than one reference",
+                fk.table, fk.column
+            ));
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(GeneratorError::InvalidDomain(problems))
+    }
+}
@@ -0,0 +1,379 @@
+//! Rust and TypeScript types for the records of a `Domain`
+//!
+//! Every entity becomes a serde-derivable Rust struct and a TypeScript
+//! interface with the same JSON shape, every enum of the type catalog a Rust
+//! enum and a TypeScript union of string literals. Entities and enums are
+//! written in the order of their ids, so the output only changes with the
+//! domain.
+
+use super::{check_domain, sorted_entities, GeneratorError};
+use crate::structure::data::VType;
+use crate::structure::domain::{
+    Attribute, Domain, DomainDocument, Entity, EnumDefinition, Reference,
+};
+use crate::structure::record::{self, is_many};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How references show up in the generated types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReferenceStyle {
+    /// `author_id` and `book_ids`, holding the ids of the other records
+    Ids,
+    /// `author` and `books`, holding the other records themselves
+    Nested,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Models {
+    pub rust: String,
+    pub typescript: String,
+}
+
+impl Models {
+    /// Write `models.rs` and `models.ts` into `dir`
+    pub fn write(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        let rust = dir.join("models.rs");
+        fs::write(&rust, &self.rust)?;
+        let typescript = dir.join("models.ts");
+        fs::write(&typescript, &self.typescript)?;
+        Ok(vec![rust, typescript])
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+    "unsized", "virtual", "yield",
+];
+
+/// Keywords that can not be raw identifiers
+const RUST_RESERVED: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Replace whatever can not be part of an identifier by `_`
+fn identifier(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+/// `book_shelf` and `book shelf` become `BookShelf`
+pub fn pascal_case(name: &str) -> String {
+    let ident: String = identifier(name)
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", ident)
+    } else {
+        ident
+    }
+}
+
+/// The name of a struct field for a JSON property, with the `serde`
+/// attribute it needs to keep the property name
+fn rust_field(name: &str) -> (String, Option<String>) {
+    let ident = identifier(name);
+    if RUST_RESERVED.contains(&ident.as_str()) {
+        (format!("{}_", ident), Some(name.to_owned()))
+    } else if RUST_KEYWORDS.contains(&ident.as_str()) {
+        (format!("r#{}", ident), None)
+    } else if ident != name {
+        (ident, Some(name.to_owned()))
+    } else {
+        (ident, None)
+    }
+}
+
+fn quote_string(text: &str) -> String {
+    format!("{:?}", text)
+}
+
+/// The name of the property holding a reference in the JSON shape of a
+/// record
+fn reference_property(reference: &Reference, style: ReferenceStyle) -> String {
+    match style {
+        ReferenceStyle::Ids => record::reference_property(reference),
+        ReferenceStyle::Nested => reference.name.clone(),
+    }
+}
+
+/// Refuse domains whose names clash once they are turned into types and
+/// properties
+fn check_names(domain: &Domain, style: ReferenceStyle) -> Result<(), GeneratorError> {
+    let mut problems = Vec::<String>::new();
+
+    // The types the generated code refers to besides its own
+    let mut types: HashSet<String> = ["Box", "Deserialize", "Option", "Serialize", "String", "Vec"]
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+    for def in &domain.types.enums {
+        if !types.insert(pascal_case(&def.name)) {
+            problems.push(format!("Enum {} clashes with another type", def.name));
+        }
+        let mut variants = HashSet::<String>::new();
+        for value in &def.values {
+            let variant = pascal_case(value);
+            if variant == "Self" || !variants.insert(variant) {
+                problems.push(format!(
+                    "Value {} of enum {} clashes with another value",
+                    value, def.name
+                ));
+            }
+        }
+    }
+    for entity in sorted_entities(domain) {
+        if !types.insert(pascal_case(&entity.name)) {
+            problems.push(format!("Entity {} clashes with another type", entity.name));
+        }
+    }
+
+    for entity in sorted_entities(domain) {
+        let mut fields = HashSet::<String>::new();
+        fields.insert("id".to_owned());
+        let properties = entity.attributes.iter().map(|a| a.name.clone()).chain(
+            entity
+                .references
+                .iter()
+                .map(|r| reference_property(r, style)),
+        );
+        for property in properties {
+            if !fields.insert(rust_field(&property).0) {
+                problems.push(format!(
+                    "Property {}.{} clashes with another property",
+                    entity.name, property
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(GeneratorError::InvalidDomain(problems))
+    }
+}
+
+pub fn rust_type(vtype: &VType) -> String {
+    match vtype {
+        VType::VTypeString(_) => "String".to_owned(),
+        VType::VTypeBoolean(_) => "bool".to_owned(),
+        VType::VTypeInteger(_) => "i32".to_owned(),
+        VType::VTypeFloat(_) => "f64".to_owned(),
+        // Decimals travel as strings so they keep their precision
+        VType::VTypeDecimal(_) => "String".to_owned(),
+        VType::VTypeDate(_) => "chrono::NaiveDate".to_owned(),
+        VType::VTypeDateTime(_) => "chrono::NaiveDateTime".to_owned(),
+        VType::VTypeUuid(_) => "uuid::Uuid".to_owned(),
+        VType::VTypeJson(_) => "serde_json::Value".to_owned(),
+        VType::VTypeEnum(t) => pascal_case(&t.name),
+        VType::VTypeList(t) => format!("Vec<{}>", rust_type(&t.item)),
+    }
+}
+
+fn rust_enum(def: &EnumDefinition) -> String {
+    let variants: String = def
+        .values
+        .iter()
+        .map(|value| {
+            format!(
+                "    #[serde(rename = {})]\n    {},\n",
+                quote_string(value),
+                pascal_case(value)
+            )
+        })
+        .collect();
+    format!(
+        "#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]\n\
+         pub enum {} {{\n{}}}\n",
+        pascal_case(&def.name),
+        variants
+    )
+}
+
+fn rust_struct_field(property: &str, rust_type: &str) -> String {
+    let (field, rename) = rust_field(property);
+    let rename = match rename {
+        Some(name) => format!("    #[serde(rename = {})]\n", quote_string(&name)),
+        None => String::new(),
+    };
+    format!("{}    pub {}: {},\n", rename, field, rust_type)
+}
+
+fn rust_attribute(attribute: &Attribute) -> String {
+    let vtype = rust_type(&attribute.vtype);
+    if attribute.constraints.required {
+        rust_struct_field(&attribute.name, &vtype)
+    } else {
+        rust_struct_field(&attribute.name, &format!("Option<{}>", vtype))
+    }
+}
+
+fn rust_reference(reference: &Reference, style: ReferenceStyle) -> String {
+    let target = match style {
+        ReferenceStyle::Ids => "uuid::Uuid".to_owned(),
+        ReferenceStyle::Nested => pascal_case(&reference.other),
+    };
+    let rust_type = match (style, is_many(reference)) {
+        (_, true) => format!("Vec<{}>", target),
+        (ReferenceStyle::Ids, false) => format!("Option<{}>", target),
+        // Boxed, records may refer to records of their own entity
+        (ReferenceStyle::Nested, false) => format!("Option<Box<{}>>", target),
+    };
+    rust_struct_field(&reference_property(reference, style), &rust_type)
+}
+
+fn rust_struct(entity: &Entity, style: ReferenceStyle) -> String {
+    let mut fields = rust_struct_field("id", "uuid::Uuid");
+    for attribute in &entity.attributes {
+        fields.push_str(&rust_attribute(attribute));
+    }
+    for reference in &entity.references {
+        fields.push_str(&rust_reference(reference, style));
+    }
+    format!(
+        "#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]\n\
+         pub struct {} {{\n{}}}\n",
+        pascal_case(&entity.name),
+        fields
+    )
+}
+
+/// Generate a Rust module with a struct for every entity of `domain`
+pub fn domain_to_rust(domain: &Domain, style: ReferenceStyle) -> Result<String, GeneratorError> {
+    check_domain(domain)?;
+    check_names(domain, style)?;
+
+    let mut items = vec!["use serde::{Deserialize, Serialize};\n".to_owned()];
+    for def in &domain.types.enums {
+        items.push(rust_enum(def));
+    }
+    for entity in sorted_entities(domain) {
+        items.push(rust_struct(entity, style));
+    }
+    Ok(items.join("\n"))
+}
+
+pub fn typescript_type(vtype: &VType) -> String {
+    match vtype {
+        VType::VTypeString(_)
+        | VType::VTypeDecimal(_)
+        | VType::VTypeDate(_)
+        | VType::VTypeDateTime(_)
+        | VType::VTypeUuid(_) => "string".to_owned(),
+        VType::VTypeBoolean(_) => "boolean".to_owned(),
+        VType::VTypeInteger(_) | VType::VTypeFloat(_) => "number".to_owned(),
+        VType::VTypeJson(_) => "unknown".to_owned(),
+        VType::VTypeEnum(t) => pascal_case(&t.name),
+        VType::VTypeList(t) => format!("{}[]", typescript_type(&t.item)),
+    }
+}
+
+fn typescript_property_name(name: &str) -> String {
+    if identifier(name) == name {
+        name.to_owned()
+    } else {
+        quote_string(name)
+    }
+}
+
+fn typescript_enum(def: &EnumDefinition) -> String {
+    let values: Vec<String> = def.values.iter().map(|v| quote_string(v)).collect();
+    let values = if values.is_empty() {
+        "never".to_owned()
+    } else {
+        values.join(" | ")
+    };
+    format!("export type {} = {};\n", pascal_case(&def.name), values)
+}
+
+fn typescript_property(name: &str, optional: bool, typescript_type: &str) -> String {
+    format!(
+        "  {}{}: {};\n",
+        typescript_property_name(name),
+        if optional { "?" } else { "" },
+        typescript_type
+    )
+}
+
+fn typescript_interface(entity: &Entity, style: ReferenceStyle) -> String {
+    let mut properties = typescript_property("id", false, "string");
+    for attribute in &entity.attributes {
+        properties.push_str(&typescript_property(
+            &attribute.name,
+            !attribute.constraints.required,
+            &typescript_type(&attribute.vtype),
+        ));
+    }
+    for reference in &entity.references {
+        let target = match style {
+            ReferenceStyle::Ids => "string".to_owned(),
+            ReferenceStyle::Nested => pascal_case(&reference.other),
+        };
+        let property = reference_property(reference, style);
+        properties.push_str(&if is_many(reference) {
+            typescript_property(&property, false, &format!("{}[]", target))
+        } else {
+            typescript_property(&property, true, &target)
+        });
+    }
+    format!(
+        "export interface {} {{\n{}}}\n",
+        pascal_case(&entity.name),
+        properties
+    )
+}
+
+/// Generate a TypeScript module with an interface for every entity of
+/// `domain`
+pub fn domain_to_typescript(
+    domain: &Domain,
+    style: ReferenceStyle,
+) -> Result<String, GeneratorError> {
+    check_domain(domain)?;
+    check_names(domain, style)?;
+
+    let mut items = Vec::<String>::new();
+    for def in &domain.types.enums {
+        items.push(typescript_enum(def));
+    }
+    for entity in sorted_entities(domain) {
+        items.push(typescript_interface(entity, style));
+    }
+    Ok(items.join("\n"))
+}
+
+/// Generate both modules for a domain document
+pub fn document_models(
+    doc: &DomainDocument,
+    style: ReferenceStyle,
+) -> Result<Models, GeneratorError> {
+    let header = format!(
+        "// Generated from domain {} ({}) version {}\n\n",
+        quote_string(&doc.name),
+        doc.id,
+        doc.version
+    );
+    Ok(Models {
+        rust: header.clone() + &domain_to_rust(&doc.body, style)?,
+        typescript: header + &domain_to_typescript(&doc.body, style)?,
+    })
+}
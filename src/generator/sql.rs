@@ -0,0 +1,304 @@
+//! PostgreSQL DDL for a `Domain`
+//!
+//! Every entity becomes a table with a `UUID` primary key named `id`, every
+//! stored attribute a column. References become foreign key columns, a
+//! `belongs_to` reference on the entity that holds it and a `has_one` or
+//! `has_many` reference without a matching `belongs_to` on the other entity.
+//! Either way the column is named after the reference, `{reference}_id`.
+//! The join entity of a `many_to_many` reference is a table of its own.
+//!
+//! The output only depends on the domain, so it can be checked in and diffed.
+
+use super::{check_domain, sorted_entities, GeneratorError};
+use crate::structure::data::VType;
+use crate::structure::domain::{
+    Attribute, Domain, DomainDocument, Entity, EnumDefinition, ReferenceType,
+};
+
+/// A foreign key column added to a table for a reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    /// The id of the reference the foreign key stems from
+    pub reference: i32,
+    pub table: String,
+    pub column: String,
+    pub target: String,
+}
+
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// The PostgreSQL type of a column holding values of `vtype`
+pub fn sql_type(vtype: &VType) -> String {
+    match vtype {
+        VType::VTypeString(_) => "TEXT".to_owned(),
+        VType::VTypeBoolean(_) => "BOOLEAN".to_owned(),
+        VType::VTypeInteger(_) => "INTEGER".to_owned(),
+        VType::VTypeFloat(_) => "DOUBLE PRECISION".to_owned(),
+        VType::VTypeDecimal(t) => match (t.precision, t.scale) {
+            (Some(precision), Some(scale)) => format!("NUMERIC({}, {})", precision, scale),
+            (Some(precision), None) => format!("NUMERIC({})", precision),
+            _ => "NUMERIC".to_owned(),
+        },
+        VType::VTypeDate(_) => "DATE".to_owned(),
+        VType::VTypeDateTime(_) => "TIMESTAMP".to_owned(),
+        VType::VTypeUuid(_) => "UUID".to_owned(),
+        VType::VTypeJson(_) => "JSONB".to_owned(),
+        VType::VTypeEnum(t) => quote_ident(&t.name),
+        VType::VTypeList(t) => format!("{}[]", sql_type(&t.item)),
+    }
+}
+
+/// The SQL literal for the default value of `vtype`, if it has one
+pub fn sql_default(vtype: &VType) -> Option<String> {
+    match vtype {
+        VType::VTypeString(t) => t.default.as_ref().map(|d| quote_literal(d)),
+        VType::VTypeBoolean(t) => t
+            .default
+            .map(|d| if d { "TRUE" } else { "FALSE" }.to_owned()),
+        VType::VTypeInteger(t) => t.default.map(|d| d.to_string()),
+        VType::VTypeFloat(t) => t.default.map(|d| format!("{:?}", d)),
+        VType::VTypeDecimal(t) => t.default.clone(),
+        VType::VTypeDate(t) => t.default.map(|d| quote_literal(&d.to_string())),
+        VType::VTypeDateTime(t) => t.default.map(|d| quote_literal(&d.to_string())),
+        VType::VTypeUuid(t) => t.default.map(|d| quote_literal(&d.to_string())),
+        VType::VTypeJson(t) => t.default.as_ref().map(|d| quote_literal(d)),
+        VType::VTypeEnum(t) => t.default.as_ref().map(|d| quote_literal(d)),
+        VType::VTypeList(_) => None,
+    }
+}
+
+/// `CHECK` expressions for the range, length and pattern of a column
+fn sql_checks(attribute: &Attribute) -> Vec<String> {
+    let column = quote_ident(&attribute.name);
+    let mut checks = Vec::<String>::new();
+
+    match &attribute.vtype {
+        VType::VTypeInteger(t) => {
+            if let Some(min) = t.min {
+                checks.push(format!("{} >= {}", column, min));
+            }
+            if let Some(max) = t.max {
+                checks.push(format!("{} <= {}", column, max));
+            }
+        }
+        VType::VTypeFloat(t) => {
+            if let Some(min) = t.min {
+                checks.push(format!("{} >= {:?}", column, min));
+            }
+            if let Some(max) = t.max {
+                checks.push(format!("{} <= {:?}", column, max));
+            }
+        }
+        _ => {}
+    }
+
+    let length = match &attribute.vtype {
+        VType::VTypeList(_) => format!("cardinality({})", column),
+        _ => format!("char_length({})", column),
+    };
+    let constraints = &attribute.constraints;
+    if let Some(min) = constraints.min_length {
+        checks.push(format!("{} >= {}", length, min));
+    }
+    if let Some(max) = constraints.max_length {
+        checks.push(format!("{} <= {}", length, max));
+    }
+    if let Some(pattern) = &constraints.pattern {
+        checks.push(format!("{} ~ {}", column, quote_literal(pattern)));
+    }
+    checks
+}
+
+/// The `CHECK` expression of a column, if it has any checks
+pub fn column_check(attribute: &Attribute) -> Option<String> {
+    let checks = sql_checks(attribute);
+    if checks.is_empty() {
+        None
+    } else {
+        Some(checks.join(" AND "))
+    }
+}
+
+pub fn unique_name(table: &str, column: &str) -> String {
+    format!("{}_{}_key", table, column)
+}
+
+pub fn check_name(table: &str, column: &str) -> String {
+    format!("{}_{}_check", table, column)
+}
+
+/// Whether a column gets an index of its own, unique columns are indexed
+/// through their constraint
+pub fn has_index(attribute: &Attribute) -> bool {
+    attribute.constraints.indexed && !attribute.constraints.unique
+}
+
+/// The column definition of a stored attribute, as used in `CREATE TABLE`
+/// and `ALTER TABLE ... ADD COLUMN`. Constraints are named so migrations
+/// can find them again.
+pub fn column_definition(table: &str, attribute: &Attribute) -> String {
+    let mut parts = vec![quote_ident(&attribute.name), sql_type(&attribute.vtype)];
+    if attribute.constraints.required {
+        parts.push("NOT NULL".to_owned());
+    }
+    if let Some(default) = sql_default(&attribute.vtype) {
+        parts.push(format!("DEFAULT {}", default));
+    }
+    if attribute.constraints.unique {
+        parts.push(format!(
+            "CONSTRAINT {} UNIQUE",
+            quote_ident(&unique_name(table, &attribute.name))
+        ));
+    }
+    if let Some(check) = column_check(attribute) {
+        parts.push(format!(
+            "CONSTRAINT {} CHECK ({})",
+            quote_ident(&check_name(table, &attribute.name)),
+            check
+        ));
+    }
+    parts.join(" ")
+}
+
+pub fn create_enum(def: &EnumDefinition) -> String {
+    let values: Vec<String> = def.values.iter().map(|v| quote_literal(v)).collect();
+    format!(
+        "CREATE TYPE {} AS ENUM ({});\n",
+        quote_ident(&def.name),
+        values.join(", ")
+    )
+}
+
+pub fn index_name(table: &str, column: &str) -> String {
+    format!("{}_{}_idx", table, column)
+}
+
+pub fn create_index(table: &str, column: &str) -> String {
+    format!(
+        "CREATE INDEX {} ON {} ({});\n",
+        quote_ident(&index_name(table, column)),
+        quote_ident(table),
+        quote_ident(column)
+    )
+}
+
+pub fn foreign_key_name(fk: &ForeignKey) -> String {
+    format!("{}_{}_fkey", fk.table, fk.column)
+}
+
+pub fn foreign_key_constraint(fk: &ForeignKey) -> String {
+    format!(
+        "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} (\"id\")",
+        quote_ident(&foreign_key_name(fk)),
+        quote_ident(&fk.column),
+        quote_ident(&fk.target)
+    )
+}
+
+pub fn add_foreign_key(fk: &ForeignKey) -> String {
+    format!(
+        "ALTER TABLE {}\n  ADD {};\n",
+        quote_ident(&fk.table),
+        foreign_key_constraint(fk)
+    )
+}
+
+/// All foreign key columns the references in `domain` give rise to, in
+/// entity id order
+pub fn foreign_keys(domain: &Domain) -> Vec<ForeignKey> {
+    let mut fks = Vec::<ForeignKey>::new();
+    for entity in sorted_entities(domain) {
+        for reference in &entity.references {
+            match reference.reftype {
+                ReferenceType::BelongsTo => fks.push(ForeignKey {
+                    reference: reference.id,
+                    table: entity.name.clone(),
+                    column: format!("{}_id", reference.name),
+                    target: reference.other.clone(),
+                }),
+                ReferenceType::HasOne | ReferenceType::HasMany => {
+                    let has_inverse = domain
+                        .entities
+                        .iter()
+                        .filter(|e| e.name == reference.other)
+                        .flat_map(|e| e.references.iter())
+                        .any(|r| r.reftype == ReferenceType::BelongsTo && r.other == entity.name);
+                    if !has_inverse {
+                        fks.push(ForeignKey {
+                            reference: reference.id,
+                            table: reference.other.clone(),
+                            column: format!("{}_id", reference.name),
+                            target: entity.name.clone(),
+                        });
+                    }
+                }
+                ReferenceType::ManyToMany => {}
+            }
+        }
+    }
+    fks
+}
+
+pub fn create_table(entity: &Entity, fks: &[ForeignKey]) -> String {
+    let mut columns = vec![format!("{} UUID PRIMARY KEY NOT NULL", quote_ident("id"))];
+    for attribute in entity.stored_attributes() {
+        columns.push(column_definition(&entity.name, attribute));
+    }
+    for fk in fks.iter().filter(|fk| fk.table == entity.name) {
+        columns.push(format!("{} UUID", quote_ident(&fk.column)));
+    }
+    let columns: Vec<String> = columns.iter().map(|c| format!("  {}", c)).collect();
+    format!(
+        "CREATE TABLE {} (\n{}\n);\n",
+        quote_ident(&entity.name),
+        columns.join(",\n")
+    )
+}
+
+/// Generate the DDL for all types, tables, indexes and foreign keys of
+/// `domain`
+pub fn domain_to_ddl(domain: &Domain) -> Result<String, GeneratorError> {
+    check_domain(domain)?;
+
+    let mut statements = Vec::<String>::new();
+
+    let mut enums: Vec<&EnumDefinition> = domain.types.enums.iter().collect();
+    enums.sort_by_key(|e| e.id);
+    for def in enums {
+        statements.push(create_enum(def));
+    }
+
+    let fks = foreign_keys(domain);
+    for entity in sorted_entities(domain) {
+        statements.push(create_table(entity, &fks));
+        for attribute in entity.stored_attributes() {
+            if has_index(attribute) {
+                statements.push(create_index(&entity.name, &attribute.name));
+            }
+        }
+    }
+
+    for fk in &fks {
+        statements.push(add_foreign_key(fk));
+    }
+
+    Ok(statements.join("\n"))
+}
+
+/// Generate the DDL for a domain document, headed by a comment naming it
+pub fn document_to_ddl(doc: &DomainDocument) -> Result<String, GeneratorError> {
+    let ddl = domain_to_ddl(&doc.body)?;
+    Ok(format!(
+        "-- Generated from domain {} ({}) version {}\n\n{}",
+        quote_literal(&doc.name),
+        doc.id,
+        doc.version,
+        ddl
+    ))
+}
@@ -0,0 +1,832 @@
+//! A GraphQL API over the records of a project, built from its domains
+//!
+//! Every entity of the domain documents of a project becomes an object type
+//! with its persistent and computed attributes and references as fields,
+//! computed attributes being read only, and gets
+//! operations to get, list, create, update and delete its records. The
+//! schema is built for every request, so it always follows the domains.
+
+use super::schema::Context;
+use crate::db::models::Document as DBDocument;
+use crate::db::records::{Record, RecordError};
+use crate::generator::model::pascal_case;
+use crate::structure::data::VType;
+use crate::structure::domain::{AttributeStorage, DomainDocument, ReferenceType};
+use crate::structure::record::{is_many, reference_property};
+use diesel::pg::PgConnection;
+use diesel::result::Error as DieselError;
+use juniper::meta::{Field, MetaType};
+use juniper::{
+    to_camel_case, Arguments, DefaultScalarValue, ExecutionResult, Executor, FieldError,
+    FieldResult, GraphQLType, Registry, RootNode, ID,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// The GraphQL types attribute values are shown as
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarKind {
+    String,
+    Int,
+    Float,
+    Boolean,
+    /// Any JSON value, as JSON text
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeField {
+    /// The name of the field
+    pub name: String,
+    /// The property of the record holding the value
+    pub property: String,
+    pub kind: ScalarKind,
+    pub list: bool,
+    /// Computed attributes are derived on read and can not be set
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceField {
+    /// The name of the field holding the other records
+    pub name: String,
+    /// The name of the field and argument holding the ids of the other
+    /// records
+    pub ids_name: String,
+    /// The property of the record holding the ids
+    pub property: String,
+    /// The index of the other entity in `ProjectApi::entities`
+    pub other: usize,
+    pub many: bool,
+    /// Where to look for the other records when the record does not hold
+    /// their ids: the property of the other records that holds the id of
+    /// this one, and whether it holds a list of ids
+    pub inverse: Option<(String, bool)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityApi {
+    pub type_name: String,
+    pub domain: Arc<DomainDocument>,
+    pub entity_id: i32,
+    pub attributes: Vec<AttributeField>,
+    pub references: Vec<ReferenceField>,
+}
+
+impl EntityApi {
+    fn field_name(&self) -> String {
+        let mut chars = self.type_name.chars();
+        match chars.next() {
+            Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+            None => String::new(),
+        }
+    }
+
+    pub fn get_field(&self) -> String {
+        self.field_name()
+    }
+
+    pub fn list_field(&self) -> String {
+        format!("{}List", self.field_name())
+    }
+
+    pub fn create_field(&self) -> String {
+        format!("create{}", self.type_name)
+    }
+
+    pub fn update_field(&self) -> String {
+        format!("update{}", self.type_name)
+    }
+
+    pub fn delete_field(&self) -> String {
+        format!("delete{}", self.type_name)
+    }
+}
+
+/// The entities of a project, with the names they have in the API
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectApi {
+    pub project_id: Uuid,
+    pub entities: Vec<EntityApi>,
+}
+
+/// Names that are taken by GraphQL itself
+const RESERVED_TYPES: &[&str] = &[
+    "Boolean", "Float", "ID", "Int", "Mutation", "Query", "String",
+];
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        && !name.starts_with("__")
+}
+
+fn scalar_kind(vtype: &VType) -> ScalarKind {
+    match vtype {
+        VType::VTypeInteger(_) => ScalarKind::Int,
+        VType::VTypeFloat(_) => ScalarKind::Float,
+        VType::VTypeBoolean(_) => ScalarKind::Boolean,
+        VType::VTypeJson(_) | VType::VTypeList(_) => ScalarKind::Json,
+        _ => ScalarKind::String,
+    }
+}
+
+impl ProjectApi {
+    /// Lay out the API of the entities of `domains`. Entities are named
+    /// after themselves, or after their domain and themselves when the name
+    /// is taken. Attributes and references whose names can not be used are
+    /// left out.
+    pub fn new(project_id: Uuid, domains: Vec<DomainDocument>) -> Self {
+        let domains: Vec<Arc<DomainDocument>> = domains.into_iter().map(Arc::new).collect();
+
+        let mut taken: HashSet<String> = RESERVED_TYPES.iter().map(|t| t.to_string()).collect();
+        let mut entities = Vec::<EntityApi>::new();
+        for domain in &domains {
+            for entity in &domain.body.entities {
+                let candidates = vec![
+                    pascal_case(&entity.name),
+                    pascal_case(&domain.name) + &pascal_case(&entity.name),
+                    format!("{}{}", pascal_case(&entity.name), entity.id),
+                ];
+                let type_name = candidates
+                    .into_iter()
+                    .find(|name| is_name(name) && !taken.contains(name));
+                let type_name = match type_name {
+                    Some(type_name) => type_name,
+                    None => {
+                        warn!("Entity {} has no usable name, it is left out", entity.name);
+                        continue;
+                    }
+                };
+                taken.insert(type_name.clone());
+                entities.push(EntityApi {
+                    type_name,
+                    domain: domain.clone(),
+                    entity_id: entity.id,
+                    attributes: vec![],
+                    references: vec![],
+                });
+            }
+        }
+
+        let index_of = |domain: &Arc<DomainDocument>, name: &str| {
+            entities.iter().position(|e| {
+                Arc::ptr_eq(&e.domain, domain)
+                    && domain
+                        .body
+                        .entities
+                        .iter()
+                        .any(|o| o.id == e.entity_id && o.name == name)
+            })
+        };
+        let mut fields = Vec::<(Vec<AttributeField>, Vec<ReferenceField>)>::new();
+        for api in &entities {
+            let entity = api
+                .domain
+                .body
+                .entities
+                .iter()
+                .find(|e| e.id == api.entity_id)
+                .unwrap();
+            let mut names: HashSet<String> = ["id".to_string()].iter().cloned().collect();
+            let mut usable = |name: &str, what: &str| {
+                if is_name(name) && names.insert(name.to_owned()) {
+                    true
+                } else {
+                    warn!("{} {}.{} is left out of the API", what, entity.name, name);
+                    false
+                }
+            };
+
+            let mut attributes = Vec::<AttributeField>::new();
+            for attribute in &entity.attributes {
+                if attribute.storage == AttributeStorage::Transient {
+                    continue;
+                }
+                let name = to_camel_case(&attribute.name).into_owned();
+                if !usable(&name, "Attribute") {
+                    continue;
+                }
+                let (kind, list) = match &attribute.vtype {
+                    VType::VTypeList(t) if scalar_kind(&t.item) != ScalarKind::Json => {
+                        (scalar_kind(&t.item), true)
+                    }
+                    vtype => (scalar_kind(vtype), false),
+                };
+                attributes.push(AttributeField {
+                    name,
+                    property: attribute.name.clone(),
+                    kind,
+                    list,
+                    read_only: attribute.storage.is_computed(),
+                });
+            }
+
+            let mut references = Vec::<ReferenceField>::new();
+            for reference in &entity.references {
+                let other = match index_of(&api.domain, &reference.other) {
+                    Some(other) => other,
+                    None => continue,
+                };
+                let property = reference_property(reference);
+                let name = to_camel_case(&reference.name).into_owned();
+                let ids_name = to_camel_case(&property).into_owned();
+                if !usable(&name, "Reference") || !usable(&ids_name, "Reference") {
+                    continue;
+                }
+
+                // The references of the other entity pointing back
+                let inverse = api
+                    .domain
+                    .body
+                    .entities
+                    .iter()
+                    .find(|e| e.name == reference.other)
+                    .and_then(|other| {
+                        other.references.iter().find(|r| {
+                            r.other == entity.name
+                                && r.id != reference.id
+                                && match reference.reftype {
+                                    ReferenceType::HasOne | ReferenceType::HasMany => {
+                                        r.reftype == ReferenceType::BelongsTo
+                                    }
+                                    ReferenceType::BelongsTo => false,
+                                    ReferenceType::ManyToMany => {
+                                        r.reftype == ReferenceType::ManyToMany
+                                    }
+                                }
+                        })
+                    })
+                    .map(|r| (reference_property(r), is_many(r)));
+                references.push(ReferenceField {
+                    name,
+                    ids_name,
+                    property,
+                    other,
+                    many: is_many(reference),
+                    inverse,
+                });
+            }
+            fields.push((attributes, references));
+        }
+        for (api, (attributes, references)) in entities.iter_mut().zip(fields) {
+            api.attributes = attributes;
+            api.references = references;
+        }
+
+        ProjectApi {
+            project_id,
+            entities,
+        }
+    }
+
+    /// Lay out the API of the domain documents of project `project_id`
+    pub fn load(conn: &PgConnection, project_id: &Uuid) -> Result<Self, DieselError> {
+        let domains = DBDocument::find_domains(conn, project_id)?;
+        Ok(ProjectApi::new(*project_id, domains))
+    }
+}
+
+fn record_error(err: RecordError) -> FieldError {
+    match err {
+        RecordError::Invalid(violations) => {
+            let violations = violations
+                .iter()
+                .map(|v| {
+                    let mut violation = juniper::Object::with_capacity(2);
+                    violation.add_field(
+                        "property",
+                        v.property.as_ref().map_or(juniper::Value::null(), |p| {
+                            juniper::Value::scalar(p.clone())
+                        }),
+                    );
+                    violation.add_field("message", juniper::Value::scalar(v.message.clone()));
+                    juniper::Value::object(violation)
+                })
+                .collect();
+            let mut extensions = juniper::Object::with_capacity(1);
+            extensions.add_field("violations", juniper::Value::list(violations));
+            FieldError::new("invalid record", juniper::Value::object(extensions))
+        }
+        RecordError::Referenced(id, referrers) => {
+            let referrers = referrers
+                .iter()
+                .map(|r| juniper::Value::scalar(r.to_string()))
+                .collect();
+            let mut extensions = juniper::Object::with_capacity(1);
+            extensions.add_field("referrers", juniper::Value::list(referrers));
+            FieldError::new(
+                format!("record {} is referred to by other records", id),
+                juniper::Value::object(extensions),
+            )
+        }
+        err => FieldError::new(err, juniper::Value::null()),
+    }
+}
+
+fn parse_id(id: Option<ID>) -> FieldResult<Uuid> {
+    let id = id.ok_or_else(|| FieldError::from("an id is required"))?;
+    Ok(Uuid::parse_str(&id)?)
+}
+
+fn attribute_field<'r>(
+    registry: &mut Registry<'r, DefaultScalarValue>,
+    field: &AttributeField,
+) -> Field<'r, DefaultScalarValue> {
+    match (field.kind, field.list) {
+        (ScalarKind::Int, false) => registry.field::<Option<i32>>(&field.name, &()),
+        (ScalarKind::Float, false) => registry.field::<Option<f64>>(&field.name, &()),
+        (ScalarKind::Boolean, false) => registry.field::<Option<bool>>(&field.name, &()),
+        (ScalarKind::String, false) | (ScalarKind::Json, _) => {
+            registry.field::<Option<String>>(&field.name, &())
+        }
+        (ScalarKind::Int, true) => registry.field::<Option<Vec<Option<i32>>>>(&field.name, &()),
+        (ScalarKind::Float, true) => registry.field::<Option<Vec<Option<f64>>>>(&field.name, &()),
+        (ScalarKind::Boolean, true) => {
+            registry.field::<Option<Vec<Option<bool>>>>(&field.name, &())
+        }
+        (ScalarKind::String, true) => {
+            registry.field::<Option<Vec<Option<String>>>>(&field.name, &())
+        }
+    }
+}
+
+fn attribute_argument<'r>(
+    registry: &mut Registry<'r, DefaultScalarValue>,
+    field: &AttributeField,
+) -> juniper::meta::Argument<'r, DefaultScalarValue> {
+    match (field.kind, field.list) {
+        (ScalarKind::Int, false) => registry.arg::<Option<i32>>(&field.name, &()),
+        (ScalarKind::Float, false) => registry.arg::<Option<f64>>(&field.name, &()),
+        (ScalarKind::Boolean, false) => registry.arg::<Option<bool>>(&field.name, &()),
+        (ScalarKind::String, false) | (ScalarKind::Json, _) => {
+            registry.arg::<Option<String>>(&field.name, &())
+        }
+        (ScalarKind::Int, true) => registry.arg::<Option<Vec<i32>>>(&field.name, &()),
+        (ScalarKind::Float, true) => registry.arg::<Option<Vec<f64>>>(&field.name, &()),
+        (ScalarKind::Boolean, true) => registry.arg::<Option<Vec<bool>>>(&field.name, &()),
+        (ScalarKind::String, true) => registry.arg::<Option<Vec<String>>>(&field.name, &()),
+    }
+}
+
+/// The value of an attribute argument as it is kept in a record, `None`
+/// when the argument is left out
+fn attribute_argument_value(
+    field: &AttributeField,
+    args: &Arguments,
+) -> FieldResult<Option<Value>> {
+    let value = match (field.kind, field.list) {
+        (ScalarKind::Int, false) => args.get::<i32>(&field.name).map(|v| json!(v)),
+        (ScalarKind::Float, false) => args.get::<f64>(&field.name).map(|v| json!(v)),
+        (ScalarKind::Boolean, false) => args.get::<bool>(&field.name).map(|v| json!(v)),
+        (ScalarKind::String, false) => args.get::<String>(&field.name).map(|v| json!(v)),
+        (ScalarKind::Json, _) => match args.get::<String>(&field.name) {
+            Some(text) => Some(serde_json::from_str(&text)?),
+            None => None,
+        },
+        (ScalarKind::Int, true) => args.get::<Vec<i32>>(&field.name).map(|v| json!(v)),
+        (ScalarKind::Float, true) => args.get::<Vec<f64>>(&field.name).map(|v| json!(v)),
+        (ScalarKind::Boolean, true) => args.get::<Vec<bool>>(&field.name).map(|v| json!(v)),
+        (ScalarKind::String, true) => args.get::<Vec<String>>(&field.name).map(|v| json!(v)),
+    };
+    Ok(value)
+}
+
+fn resolve_attribute(
+    field: &AttributeField,
+    value: Option<&Value>,
+    executor: &Executor<Context>,
+) -> ExecutionResult {
+    let value = value.filter(|v| !v.is_null());
+    let items = || {
+        value
+            .and_then(Value::as_array)
+            .map(|items| items.iter().collect::<Vec<_>>())
+    };
+    match (field.kind, field.list) {
+        (ScalarKind::Int, false) => {
+            executor.resolve_with_ctx(&(), &value.and_then(Value::as_i64).map(|v| v as i32))
+        }
+        (ScalarKind::Float, false) => {
+            executor.resolve_with_ctx(&(), &value.and_then(Value::as_f64))
+        }
+        (ScalarKind::Boolean, false) => {
+            executor.resolve_with_ctx(&(), &value.and_then(Value::as_bool))
+        }
+        (ScalarKind::String, false) => {
+            executor.resolve_with_ctx(&(), &value.and_then(Value::as_str).map(str::to_owned))
+        }
+        (ScalarKind::Json, _) => executor.resolve_with_ctx(&(), &value.map(|v| v.to_string())),
+        (ScalarKind::Int, true) => executor.resolve_with_ctx(
+            &(),
+            &items().map(|items| {
+                items
+                    .iter()
+                    .map(|v| v.as_i64().map(|v| v as i32))
+                    .collect::<Vec<_>>()
+            }),
+        ),
+        (ScalarKind::Float, true) => executor.resolve_with_ctx(
+            &(),
+            &items().map(|items| items.iter().map(|v| v.as_f64()).collect::<Vec<_>>()),
+        ),
+        (ScalarKind::Boolean, true) => executor.resolve_with_ctx(
+            &(),
+            &items().map(|items| items.iter().map(|v| v.as_bool()).collect::<Vec<_>>()),
+        ),
+        (ScalarKind::String, true) => executor.resolve_with_ctx(
+            &(),
+            &items().map(|items| {
+                items
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_owned))
+                    .collect::<Vec<_>>()
+            }),
+        ),
+    }
+}
+
+/// The type info of a record type, the API of the project and the index
+/// of the entity in it
+pub struct RecordType {
+    pub api: Arc<ProjectApi>,
+    pub entity: usize,
+}
+
+impl RecordType {
+    fn entity(&self) -> &EntityApi {
+        &self.api.entities[self.entity]
+    }
+
+    fn of(&self, entity: usize) -> RecordType {
+        RecordType {
+            api: self.api.clone(),
+            entity,
+        }
+    }
+}
+
+/// A record, as an object of the type of its entity
+pub struct RecordObject(pub Record);
+
+impl RecordObject {
+    /// The records of `entity` referenced through `field`
+    fn referenced(
+        &self,
+        conn: &PgConnection,
+        other: &EntityApi,
+        field: &ReferenceField,
+    ) -> Result<Vec<RecordObject>, RecordError> {
+        let record = &self.0;
+        let records = match (record.body.get(&field.property), &field.inverse) {
+            (Some(Value::Array(ids)), _) => {
+                let ids: Vec<Uuid> = ids
+                    .iter()
+                    .filter_map(|id| id.as_str().and_then(|id| Uuid::parse_str(id).ok()))
+                    .collect();
+                Record::by_ids(conn, &other.domain, &ids)?
+            }
+            (Some(Value::String(id)), _) => match Uuid::parse_str(id) {
+                Ok(id) => Record::by_ids(conn, &other.domain, &[id])?,
+                Err(_) => vec![],
+            },
+            (_, Some((property, many))) => {
+                let id = json!(record.id.to_string());
+                let mut pattern = Map::new();
+                pattern.insert(property.clone(), if *many { json!([id]) } else { id });
+                Record::find_containing(
+                    conn,
+                    &other.domain,
+                    other.entity_id,
+                    &Value::Object(pattern),
+                )?
+            }
+            _ => vec![],
+        };
+        Ok(records
+            .into_iter()
+            .filter(|r| r.entity_id == other.entity_id)
+            .map(RecordObject)
+            .collect())
+    }
+}
+
+impl GraphQLType for RecordObject {
+    type Context = Context;
+    type TypeInfo = RecordType;
+
+    fn name(info: &RecordType) -> Option<&str> {
+        Some(&info.entity().type_name)
+    }
+
+    fn meta<'r>(info: &RecordType, registry: &mut Registry<'r>) -> MetaType<'r>
+    where
+        DefaultScalarValue: 'r,
+    {
+        let entity = info.entity();
+        let mut fields = vec![registry.field::<ID>("id", &())];
+        for attribute in &entity.attributes {
+            fields.push(attribute_field(registry, attribute));
+        }
+        for reference in &entity.references {
+            let other = info.of(reference.other);
+            if reference.many {
+                fields.push(registry.field::<Vec<RecordObject>>(&reference.name, &other));
+                fields.push(registry.field::<Vec<ID>>(&reference.ids_name, &()));
+            } else {
+                fields.push(registry.field::<Option<RecordObject>>(&reference.name, &other));
+                fields.push(registry.field::<Option<ID>>(&reference.ids_name, &()));
+            }
+        }
+        registry
+            .build_object_type::<RecordObject>(info, &fields)
+            .into_meta()
+    }
+
+    fn resolve_field(
+        &self,
+        info: &RecordType,
+        field_name: &str,
+        _args: &Arguments,
+        executor: &Executor<Context>,
+    ) -> ExecutionResult {
+        let entity = info.entity();
+        let body = &self.0.body;
+        if field_name == "id" {
+            return executor.resolve_with_ctx(&(), &ID::from(self.0.id.to_string()));
+        }
+        if let Some(field) = entity.attributes.iter().find(|a| a.name == field_name) {
+            return resolve_attribute(field, body.get(&field.property), executor);
+        }
+        if let Some(field) = entity.references.iter().find(|r| r.ids_name == field_name) {
+            let ids: Vec<ID> = match body.get(&field.property) {
+                Some(Value::Array(ids)) => ids
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|id| ID::from(id.to_owned()))
+                    .collect(),
+                Some(Value::String(id)) => vec![ID::from(id.clone())],
+                _ => vec![],
+            };
+            return if field.many {
+                executor.resolve_with_ctx(&(), &ids)
+            } else {
+                executor.resolve_with_ctx(&(), &ids.into_iter().next())
+            };
+        }
+        if let Some(field) = entity.references.iter().find(|r| r.name == field_name) {
+            let conn = executor.context().dbpool.get()?;
+            let other = info.of(field.other);
+            let records = self
+                .referenced(&conn, other.entity(), field)
+                .map_err(record_error)?;
+            return if field.many {
+                executor.resolve(&other, &records)
+            } else {
+                executor.resolve(&other, &records.into_iter().next())
+            };
+        }
+        Err(FieldError::from(format!(
+            "{} has no field {}",
+            entity.type_name, field_name
+        )))
+    }
+}
+
+/// The queries of the records API
+pub struct RecordQuery;
+
+impl GraphQLType for RecordQuery {
+    type Context = Context;
+    type TypeInfo = Arc<ProjectApi>;
+
+    fn name(_info: &Arc<ProjectApi>) -> Option<&str> {
+        Some("Query")
+    }
+
+    fn meta<'r>(info: &Arc<ProjectApi>, registry: &mut Registry<'r>) -> MetaType<'r>
+    where
+        DefaultScalarValue: 'r,
+    {
+        let mut fields = vec![registry.field::<ID>("projectId", &())];
+        for (index, entity) in info.entities.iter().enumerate() {
+            let record_type = RecordType {
+                api: info.clone(),
+                entity: index,
+            };
+            let id = registry.arg::<ID>("id", &());
+            fields.push(
+                registry
+                    .field::<Option<RecordObject>>(&entity.get_field(), &record_type)
+                    .argument(id),
+            );
+            let limit = registry.arg::<Option<i32>>("limit", &());
+            let offset = registry.arg::<Option<i32>>("offset", &());
+            fields.push(
+                registry
+                    .field::<Vec<RecordObject>>(&entity.list_field(), &record_type)
+                    .argument(limit)
+                    .argument(offset),
+            );
+        }
+        registry
+            .build_object_type::<RecordQuery>(info, &fields)
+            .into_meta()
+    }
+
+    fn resolve_field(
+        &self,
+        info: &Arc<ProjectApi>,
+        field_name: &str,
+        args: &Arguments,
+        executor: &Executor<Context>,
+    ) -> ExecutionResult {
+        if field_name == "projectId" {
+            return executor.resolve_with_ctx(&(), &ID::from(info.project_id.to_string()));
+        }
+        let conn = executor.context().dbpool.get()?;
+        for (index, entity) in info.entities.iter().enumerate() {
+            let record_type = RecordType {
+                api: info.clone(),
+                entity: index,
+            };
+            if field_name == entity.get_field() {
+                let id = parse_id(args.get::<ID>("id"))?;
+                let record = match Record::by_id(&conn, &id) {
+                    Ok(record) => Some(record),
+                    Err(DieselError::NotFound) => None,
+                    Err(err) => return Err(err.into()),
+                }
+                .filter(|r| r.domain_id == entity.domain.id && r.entity_id == entity.entity_id);
+                let record = match record {
+                    Some(record) => Some(RecordObject(
+                        Record::read(&conn, &entity.domain, record).map_err(record_error)?,
+                    )),
+                    None => None,
+                };
+                return executor.resolve(&record_type, &record);
+            }
+            if field_name == entity.list_field() {
+                let paging = crate::messages::QueryPage {
+                    limit: args.get::<i32>("limit"),
+                    offset: args.get::<i32>("offset"),
+                };
+                let records: Vec<RecordObject> =
+                    Record::find(&conn, &entity.domain, entity.entity_id, Some(paging))
+                        .map_err(record_error)?
+                        .into_iter()
+                        .map(RecordObject)
+                        .collect();
+                return executor.resolve(&record_type, &records);
+            }
+        }
+        Err(FieldError::from(format!(
+            "Query has no field {}",
+            field_name
+        )))
+    }
+}
+
+/// The mutations of the records API
+pub struct RecordMutation;
+
+impl RecordMutation {
+    /// The record body for the arguments of a create or update mutation.
+    /// Arguments that are left out or null are left out.
+    fn body(entity: &EntityApi, args: &Arguments) -> FieldResult<Value> {
+        let mut body = Map::new();
+        for field in entity.attributes.iter().filter(|f| !f.read_only) {
+            if let Some(value) = attribute_argument_value(field, args)? {
+                body.insert(field.property.clone(), value);
+            }
+        }
+        for field in &entity.references {
+            let ids = if field.many {
+                args.get::<Vec<ID>>(&field.ids_name)
+                    .map(|ids| json!(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()))
+            } else {
+                args.get::<ID>(&field.ids_name)
+                    .map(|id| json!(id.to_string()))
+            };
+            if let Some(ids) = ids {
+                body.insert(field.property.clone(), ids);
+            }
+        }
+        Ok(Value::Object(body))
+    }
+}
+
+impl GraphQLType for RecordMutation {
+    type Context = Context;
+    type TypeInfo = Arc<ProjectApi>;
+
+    fn name(_info: &Arc<ProjectApi>) -> Option<&str> {
+        Some("Mutation")
+    }
+
+    fn meta<'r>(info: &Arc<ProjectApi>, registry: &mut Registry<'r>) -> MetaType<'r>
+    where
+        DefaultScalarValue: 'r,
+    {
+        let mut fields = Vec::<Field<'r, DefaultScalarValue>>::new();
+        for (index, entity) in info.entities.iter().enumerate() {
+            let record_type = RecordType {
+                api: info.clone(),
+                entity: index,
+            };
+            let mut arguments = Vec::new();
+            for field in entity.attributes.iter().filter(|f| !f.read_only) {
+                arguments.push(attribute_argument(registry, field));
+            }
+            for field in &entity.references {
+                arguments.push(if field.many {
+                    registry.arg::<Option<Vec<ID>>>(&field.ids_name, &())
+                } else {
+                    registry.arg::<Option<ID>>(&field.ids_name, &())
+                });
+            }
+
+            let create = registry.field::<RecordObject>(&entity.create_field(), &record_type);
+            fields.push(arguments.iter().cloned().fold(create, Field::argument));
+
+            let update = registry
+                .field::<RecordObject>(&entity.update_field(), &record_type)
+                .argument(registry.arg::<ID>("id", &()));
+            fields.push(arguments.into_iter().fold(update, Field::argument));
+
+            fields.push(
+                registry
+                    .field::<bool>(&entity.delete_field(), &())
+                    .argument(registry.arg::<ID>("id", &())),
+            );
+        }
+        registry
+            .build_object_type::<RecordMutation>(info, &fields)
+            .into_meta()
+    }
+
+    fn resolve_field(
+        &self,
+        info: &Arc<ProjectApi>,
+        field_name: &str,
+        args: &Arguments,
+        executor: &Executor<Context>,
+    ) -> ExecutionResult {
+        let conn = executor.context().dbpool.get()?;
+        for (index, entity) in info.entities.iter().enumerate() {
+            let record_type = RecordType {
+                api: info.clone(),
+                entity: index,
+            };
+            if field_name == entity.create_field() {
+                let body = RecordMutation::body(entity, args)?;
+                let record = Record::create(&conn, &entity.domain, entity.entity_id, body)
+                    .map_err(record_error)?;
+                return executor.resolve(&record_type, &RecordObject(record));
+            }
+            if field_name == entity.update_field() {
+                let id = parse_id(args.get::<ID>("id"))?;
+                let record = Record::by_id(&conn, &id)?;
+                if record.entity_id != entity.entity_id {
+                    return Err(FieldError::from(format!(
+                        "{} is not a {}",
+                        id, entity.type_name
+                    )));
+                }
+                let changes = RecordMutation::body(entity, args)?;
+                let record =
+                    Record::update(&conn, &entity.domain, &id, changes).map_err(record_error)?;
+                return executor.resolve(&record_type, &RecordObject(record));
+            }
+            if field_name == entity.delete_field() {
+                let id = parse_id(args.get::<ID>("id"))?;
+                let deleted = match Record::by_id(&conn, &id) {
+                    Ok(record)
+                        if record.domain_id == entity.domain.id
+                            && record.entity_id == entity.entity_id =>
+                    {
+                        Record::delete(&conn, &entity.domain, &id).map_err(record_error)? > 0
+                    }
+                    Ok(_) | Err(DieselError::NotFound) => false,
+                    Err(err) => return Err(err.into()),
+                };
+                return executor.resolve_with_ctx(&(), &deleted);
+            }
+        }
+        Err(FieldError::from(format!(
+            "Mutation has no field {}",
+            field_name
+        )))
+    }
+}
+
+pub type RecordSchema = RootNode<'static, RecordQuery, RecordMutation>;
+
+/// The schema of the records API of a project
+pub fn create_record_schema(api: ProjectApi) -> RecordSchema {
+    let api = Arc::new(api);
+    RecordSchema::new_with_info(RecordQuery, RecordMutation, api.clone(), api)
+}
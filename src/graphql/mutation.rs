@@ -3,9 +3,14 @@ use juniper;
 use super::schema::Context;
 use uuid::Uuid;
 use crate::db::models::{Document as DocumentDAO, Project as ProjectDAO};
+use crate::engine::flox::check::{check_parameters, nodes_using};
 use crate::messages::*;
-use crate::structure::domain::{Attribute, DomainDocument, Entity};
-use crate::structure::xflow::{XFlowDocument};
+use crate::structure::domain::{Attribute, DomainDocument, Entity, EnumDefinition, Reference};
+use crate::structure::data::VariableDefinition;
+use crate::structure::xflow::{
+    XFlow, XFlowBranch, XFlowDiagnostic, XFlowDiagnosticCode, XFlowDocument, XFlowEdge, XFlowError,
+    XFlowNode, XFlowNodeParameters, XFlowVariableEdit,
+};
 use crate::structure::fngroup::{FngroupDocument, FnDefinition};
 use crate::structure::modelx::ModelxDocument;
 use juniper::FieldResult;
@@ -81,6 +86,14 @@ impl MutationRoot {
         Ok(entity)
     }
 
+    fn domain_add_enum(context: &Context, input: DomainAddEnumInput) -> FieldResult<EnumDefinition> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let def = doc.body.add_enum(&input.name, &input.values)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(def)
+    }
+
     /*
     fn fngroup_add_fn(context: &Context, input: FnGroupAPIAddFn) -> FieldResult<FnDefinition> {
         let mut conn = context.dbpool.get()?;
@@ -104,20 +117,312 @@ impl MutationRoot {
         Ok(attribute)
     }
 
-    /*
-    fn remove_entity(
+    fn domain_add_attribute(
         context: &Context,
-        input: DocumentElementId,
+        input: DomainAddAttributeInput,
     ) -> FieldResult<Attribute> {
         let mut conn = context.dbpool.get()?;
-        let mut doc = DocumentDAO::by_id(&conn, &input.document_id)?.as_domain()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let vtype = input.vtype.to_vtype()?;
         let attribute = doc
             .body
-            .remove_entity(input.element_id)?;
+            .add_attribute(input.entity_id, &input.name, vtype)?;
         let _ = DocumentDAO::save(&conn, &doc.as_raw());
         Ok(attribute)
     }
-    */
+
+    fn domain_update_attribute(
+        context: &Context,
+        input: DomainUpdateAttributeInput,
+    ) -> FieldResult<Attribute> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let vtype = match &input.vtype {
+            Some(vtype) => Some(vtype.to_vtype()?),
+            None => None,
+        };
+        let attribute = doc.body.update_attribute(
+            input.entity_id,
+            input.attribute_id,
+            input.name.as_deref(),
+            vtype,
+        )?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(attribute)
+    }
+
+    fn domain_set_attribute_constraints(
+        context: &Context,
+        input: DomainSetAttributeConstraintsInput,
+    ) -> FieldResult<Attribute> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let attribute = doc.body.set_attribute_constraints(
+            input.entity_id,
+            input.attribute_id,
+            input.constraints.to_constraints(),
+        )?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(attribute)
+    }
+
+    fn domain_set_attribute_storage(
+        context: &Context,
+        input: DomainSetAttributeStorageInput,
+    ) -> FieldResult<Attribute> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let storage = input.to_storage()?;
+        let attribute =
+            doc.body
+                .set_attribute_storage(input.entity_id, input.attribute_id, storage)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(attribute)
+    }
+
+    fn domain_remove_attribute(
+        context: &Context,
+        input: DomainRemoveAttributeInput,
+    ) -> FieldResult<Attribute> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let attribute = doc
+            .body
+            .remove_attribute(input.entity_id, input.attribute_id)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(attribute)
+    }
+
+    #[graphql(description = "Add a reference, returns the new reference followed by its inverse if requested")]
+    fn domain_add_reference(
+        context: &Context,
+        input: DomainAddReferenceInput,
+    ) -> FieldResult<Vec<Reference>> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let references = if input.inverse.unwrap_or(false) {
+            let (reference, inverse) = doc.body.add_reference_with_inverse(
+                input.entity_id,
+                &input.name,
+                input.reftype,
+                &input.other,
+                input.through.as_deref(),
+                input.inverse_name.as_deref(),
+            )?;
+            vec![reference, inverse]
+        } else {
+            vec![doc.body.add_reference_through(
+                input.entity_id,
+                &input.name,
+                input.reftype,
+                &input.other,
+                input.through.as_deref(),
+            )?]
+        };
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(references)
+    }
+
+    fn domain_update_reference(
+        context: &Context,
+        input: DomainUpdateReferenceInput,
+    ) -> FieldResult<Reference> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let reference = doc.body.update_reference(
+            input.entity_id,
+            input.reference_id,
+            input.name.as_deref(),
+            input.reftype,
+            input.other.as_deref(),
+        )?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(reference)
+    }
+
+    fn domain_remove_reference(
+        context: &Context,
+        input: DomainRemoveReferenceInput,
+    ) -> FieldResult<Reference> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let reference = doc
+            .body
+            .remove_reference(input.entity_id, input.reference_id)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(reference)
+    }
+
+    #[graphql(description = "Rename an entity, references to it are renamed along with it")]
+    fn domain_rename_entity(context: &Context, input: DomainRenameEntityInput) -> FieldResult<Entity> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let entity = doc.body.rename_entity(input.entity_id, &input.name)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(entity)
+    }
+
+    #[graphql(description = "Remove an entity, refused while it is referenced unless cascade is set")]
+    fn domain_remove_entity(context: &Context, input: DomainRemoveEntityInput) -> FieldResult<Entity> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let entity = doc.body.get_entity(input.entity_id)?.clone();
+        if input.cascade.unwrap_or(false) {
+            doc.body.remove_entity_cascade(input.entity_id)?;
+        } else {
+            doc.body.remove_entity(input.entity_id)?;
+        }
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(entity)
+    }
+
+    fn xflow_add_node(context: &Context, input: XFlowAddNodeInput) -> FieldResult<XFlowNode> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let parameters = match &input.flox {
+            Some(flox) => flox_parameters(&doc.body, flox)?,
+            None => XFlowNodeParameters::for_type(&input.nodetype)
+                .ok_or("A flox node requires the 'flox' field")?,
+        };
+        let node = doc.body.add_node(
+            input.nodetype.clone(),
+            &input.action,
+            &input.label,
+            input.position.to_position(),
+            parameters,
+        )?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(node)
+    }
+
+    fn xflow_update_node(context: &Context, input: XFlowUpdateNodeInput) -> FieldResult<XFlowNode> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let parameters = match &input.flox {
+            Some(flox) => Some(flox_parameters(&doc.body, flox)?),
+            None => None,
+        };
+        let node = doc.body.update_node(
+            input.node_id,
+            input.action.as_deref(),
+            input.label.as_deref(),
+            parameters,
+        )?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(node)
+    }
+
+    fn xflow_move_node(context: &Context, input: XFlowMoveNodeInput) -> FieldResult<XFlowNode> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let node = doc
+            .body
+            .move_node(input.node_id, input.position.to_position())?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(node)
+    }
+
+    #[graphql(description = "Remove a node, along with its edges and their branches")]
+    fn xflow_remove_node(context: &Context, input: XFlowRemoveNodeInput) -> FieldResult<XFlowNode> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let node = doc.body.remove_node(input.node_id)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(node)
+    }
+
+    fn xflow_connect(context: &Context, input: XFlowEdgeInput) -> FieldResult<XFlowEdge> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let edge = doc.body.connect(input.source, input.target)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(edge)
+    }
+
+    #[graphql(description = "Remove an edge, along with its branches")]
+    fn xflow_disconnect(context: &Context, input: XFlowEdgeInput) -> FieldResult<XFlowEdge> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let edge = doc.body.disconnect(input.source, input.target)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(edge)
+    }
+
+    fn xflow_add_branch(context: &Context, input: XFlowAddBranchInput) -> FieldResult<XFlowBranch> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let vtype = input.vtype.to_vtype()?;
+        let branch = doc
+            .body
+            .add_branch(input.source, input.target, &input.variable, vtype)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(branch)
+    }
+
+    fn xflow_remove_branch(
+        context: &Context,
+        input: XFlowRemoveBranchInput,
+    ) -> FieldResult<XFlowBranch> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let branch = doc
+            .body
+            .remove_branch(input.source, input.target, &input.variable)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(branch)
+    }
+
+    fn xflow_add_variable(
+        context: &Context,
+        input: XFlowAddVariableInput,
+    ) -> FieldResult<VariableDefinition> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let vtype = input.vtype.to_vtype()?;
+        let xvar = doc.body.add_variable(input.scope, &input.name, vtype)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(xvar)
+    }
+
+    fn xflow_rename_variable(
+        context: &Context,
+        input: XFlowRenameVariableInput,
+    ) -> FieldResult<XFlowVariableEdit> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let xvar = doc.body.rename_variable(&input.name, &input.new_name)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(XFlowVariableEdit {
+            variable: xvar,
+            warnings: variable_warnings(&doc.body, &input.name),
+        })
+    }
+
+    fn xflow_retype_variable(
+        context: &Context,
+        input: XFlowRetypeVariableInput,
+    ) -> FieldResult<VariableDefinition> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let vtype = input.vtype.to_vtype()?;
+        let xvar = doc.body.retype_variable(&input.name, vtype)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(xvar)
+    }
+
+    fn xflow_remove_variable(
+        context: &Context,
+        input: XFlowRemoveVariableInput,
+    ) -> FieldResult<XFlowVariableEdit> {
+        let mut conn = context.dbpool.get()?;
+        let mut doc = DocumentDAO::by_id(&conn, &input.xflow_id)?.as_xflow()?;
+        let xvar = doc.body.remove_variable(&input.name)?;
+        let _ = DocumentDAO::save(&conn, &doc.as_raw());
+        Ok(XFlowVariableEdit {
+            variable: xvar,
+            warnings: variable_warnings(&doc.body, &input.name),
+        })
+    }
 
     fn fngroup_add_fn(context: &Context, doc: DocumentIdentifier, input: FnGroupFnNew) -> FieldResult<FnDefinition> {
         let mut conn = context.dbpool.get()?;
@@ -142,3 +447,33 @@ impl MutationRoot {
     }
 
 }
+
+/// Warnings about the branches and flox nodes of `xflow` still using the
+/// variable `name` after it was renamed or removed
+fn variable_warnings(xflow: &XFlow, name: &str) -> Vec<XFlowDiagnostic> {
+    let branches = xflow.branches.iter().filter(|branch| branch.xvar.name == name);
+    let mut warnings: Vec<XFlowDiagnostic> = branches
+        .map(|branch| {
+            let err = XFlowError::BranchVariableNotFound(branch.edge.clone(), name.to_owned());
+            XFlowDiagnostic::from(&err)
+        })
+        .collect();
+    for id in nodes_using(xflow, name) {
+        warnings.push(XFlowDiagnostic {
+            code: XFlowDiagnosticCode::VariableNotFound,
+            message: format!("node {}: variable {} does not exist", id, name),
+            node_ids: vec![id],
+        });
+    }
+    warnings
+}
+
+/// The parameters of a flox node, refused when the expression does not
+/// check against the variables of `xflow`
+fn flox_parameters(xflow: &XFlow, input: &FloxParametersInput) -> FieldResult<XFlowNodeParameters> {
+    let parameters = xflow.flox_parameters(&input.expression, &input.returns)?;
+    if let XFlowNodeParameters::Flox(flox) = &parameters {
+        check_parameters(flox, &xflow.variables)?;
+    }
+    Ok(parameters)
+}
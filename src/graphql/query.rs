@@ -1,11 +1,17 @@
 use super::schema::Context;
 use crate::db::models::{Document as DBDocument, Project as DBProject};
+use crate::db::records::Record;
+use crate::generator::erd::{document_to_erd, ErdFormat};
+use crate::generator::json_schema::document_to_json_schema;
+use crate::generator::sql::document_to_ddl;
 use crate::messages::*;
-use crate::structure::domain::{DomainDocument};
-use crate::structure::xflow::{XFlowDocument};
+use crate::structure::domain::{DomainDiagnostic, DomainDocument};
+use crate::structure::record::RecordViolation;
+use crate::structure::xflow::{XFlowDiagnostic, XFlowDocument};
 use crate::structure::fngroup::{FngroupDocument};
 use juniper;
 use juniper::FieldResult;
+use std::collections::HashSet;
 
 pub struct QueryRoot;
 
@@ -38,6 +44,56 @@ impl QueryRoot {
         Ok(doc)
     }
 
+    #[graphql(description = "Check a domain document for consistency")]
+    fn validate_domain(context: &Context, input: DocumentId) -> FieldResult<Vec<DomainDiagnostic>> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.document_id)?.as_domain()?;
+        let xflows: HashSet<_> = DBDocument::find_xflows(&conn, &doc.project_id)?
+            .iter()
+            .map(|xflow| xflow.id)
+            .collect();
+        Ok(doc.body.validate_with_xflows(&xflows))
+    }
+
+    #[graphql(description = "Generate the PostgreSQL DDL for a domain document")]
+    fn domain_ddl(context: &Context, input: DocumentId) -> FieldResult<String> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.document_id)?.as_domain()?;
+        Ok(document_to_ddl(&doc)?)
+    }
+
+    #[graphql(description = "Generate the JSON Schema for a domain document")]
+    fn domain_json_schema(context: &Context, input: DocumentId) -> FieldResult<String> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.document_id)?.as_domain()?;
+        Ok(document_to_json_schema(&doc)?.to_string())
+    }
+
+    #[graphql(description = "Draw an entity relationship diagram of a domain document")]
+    fn domain_erd(context: &Context, input: DocumentId, format: ErdFormat) -> FieldResult<String> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.document_id)?.as_domain()?;
+        Ok(document_to_erd(&doc, format))
+    }
+
+    #[graphql(description = "Check a record of an entity without storing it")]
+    fn validate_record(
+        context: &Context,
+        input: ValidateRecordInput,
+    ) -> FieldResult<Vec<RecordViolation>> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.domain_id)?.as_domain()?;
+        let entity = Record::entity(&doc, input.entity_id)?;
+        let body: serde_json::Value = serde_json::from_str(&input.record)?;
+        Ok(Record::violations(
+            &conn,
+            &doc,
+            entity,
+            input.record_id.as_ref(),
+            &body,
+        )?)
+    }
+
     #[graphql(description = "List of all xflow documents")]
     fn xflows(context: &Context, input: ProjectId) -> FieldResult<Vec<XFlowDocument>> {
         let mut conn = context.dbpool.get()?;
@@ -52,6 +108,13 @@ impl QueryRoot {
         Ok(doc)
     }
 
+    #[graphql(description = "Check the structure of a xflow document")]
+    fn validate_xflow(context: &Context, input: DocumentId) -> FieldResult<Vec<XFlowDiagnostic>> {
+        let mut conn = context.dbpool.get()?;
+        let doc = DBDocument::by_id(&conn, &input.document_id)?.as_xflow()?;
+        Ok(doc.body.validate().iter().map(XFlowDiagnostic::from).collect())
+    }
+
     #[graphql(description = "List of all fngroup documents")]
     fn fngroups(context: &Context, input: ProjectId) -> FieldResult<Vec<FngroupDocument>> {
         let mut conn = context.dbpool.get()?;
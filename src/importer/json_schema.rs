@@ -0,0 +1,432 @@
+//! Import a `Domain` from JSON Schema
+//!
+//! Object definitions under `$defs` (or `definitions`) become entities and
+//! string enums become enums of the type catalog. A schema without
+//! definitions is read as a single entity. Properties become attributes,
+//! except for `$ref`s to other entities, and arrays of them, which become
+//! `belongs_to` and `has_many` references.
+
+use super::{Import, ImportError};
+use crate::structure::data::*;
+use crate::structure::domain::{AttributeConstraints, ReferenceType};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+use uuid::Uuid;
+
+/// Keywords of a property that end up in the domain
+const PROPERTY_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "default",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "minItems",
+    "maxItems",
+    "pattern",
+    "items",
+    "enum",
+    "$ref",
+];
+
+/// Keywords of an object definition that end up in the domain, entities
+/// have no additional properties
+const OBJECT_KEYWORDS: &[&str] = &[
+    "title",
+    "type",
+    "properties",
+    "required",
+    "additionalProperties",
+];
+
+/// Keywords of an enum definition that end up in the domain
+const ENUM_KEYWORDS: &[&str] = &["title", "type", "enum"];
+
+type Object = Map<String, Value>;
+
+/// The name of the definition a local `$ref` points at
+fn ref_name(reference: &str) -> Option<String> {
+    let name = reference
+        .strip_prefix("#/$defs/")
+        .or_else(|| reference.strip_prefix("#/definitions/"))?;
+    Some(name.replace("~1", "/").replace("~0", "~"))
+}
+
+fn is_string_enum(def: &Object) -> bool {
+    let strings = match def.get("enum") {
+        Some(Value::Array(values)) => values.iter().all(Value::is_string),
+        _ => false,
+    };
+    let string_type = match def.get("type") {
+        None => true,
+        Some(t) => t == "string",
+    };
+    strings && string_type
+}
+
+fn is_object(def: &Object) -> bool {
+    def.get("type") == Some(&Value::from("object")) || def.contains_key("properties")
+}
+
+fn string_values(values: &Value) -> Vec<String> {
+    values
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The single type of a property, `null` is left out since attributes are
+/// optional unless required
+fn single_type(prop: &Object) -> Result<Option<String>, String> {
+    match prop.get("type") {
+        None => Ok(None),
+        Some(Value::String(t)) => Ok(Some(t.clone())),
+        Some(Value::Array(types)) => {
+            let types: Vec<&str> = types
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|t| *t != "null")
+                .collect();
+            match types.as_slice() {
+                [t] => Ok(Some((*t).to_string())),
+                _ => Err(format!("union of {} is imported as json", types.join(", "))),
+            }
+        }
+        Some(other) => Err(format!("type {} is imported as json", other)),
+    }
+}
+
+fn parse_datetime(s: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|d| d.naive_utc())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
+}
+
+struct Importer {
+    import: Import,
+    entities: Vec<String>,
+}
+
+impl Importer {
+    fn is_entity(&self, name: &str) -> bool {
+        self.entities.iter().any(|e| e == name)
+    }
+
+    fn is_enum(&self, name: &str) -> bool {
+        self.import.domain.types.get_enum(name).is_some()
+    }
+
+    /// Report the keywords of `def` that are not in `known`
+    fn unsupported_keywords(&mut self, location: &str, def: &Object, known: &[&str]) {
+        for key in def.keys().filter(|k| !known.contains(&k.as_str())) {
+            self.import
+                .issue(location, &format!("keyword {} is not supported", key));
+        }
+    }
+
+    fn int(&mut self, location: &str, prop: &Object, key: &str) -> Option<i32> {
+        let value = prop.get(key)?;
+        let int = value.as_i64().and_then(|v| i32::try_from(v).ok());
+        if int.is_none() {
+            self.import.issue(
+                location,
+                &format!("{} {} is not a 32 bit integer", key, value),
+            );
+        }
+        int
+    }
+
+    fn float(&mut self, location: &str, prop: &Object, key: &str) -> Option<f64> {
+        let value = prop.get(key)?;
+        let float = value.as_f64();
+        if float.is_none() {
+            self.import
+                .issue(location, &format!("{} {} is not a number", key, value));
+        }
+        float
+    }
+
+    /// The default of a property, parsed by `parse`
+    fn default<T, F>(&mut self, location: &str, prop: &Object, parse: F) -> Option<T>
+    where
+        F: Fn(&Value) -> Option<T>,
+    {
+        let value = prop.get("default")?;
+        let default = parse(value);
+        if default.is_none() {
+            self.import
+                .issue(location, &format!("default {} is not a valid value", value));
+        }
+        default
+    }
+
+    fn string_default<T, F>(&mut self, location: &str, prop: &Object, parse: F) -> Option<T>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        self.default(location, prop, |v| v.as_str().and_then(&parse))
+    }
+
+    fn string_type(
+        &mut self,
+        location: &str,
+        enum_name: &str,
+        prop: &Object,
+    ) -> Result<VType, ImportError> {
+        if let Some(values) = prop.get("enum") {
+            if self.is_enum(enum_name) || self.is_entity(enum_name) {
+                self.import.issue(
+                    location,
+                    &format!("enum {} already exists, imported as string", enum_name),
+                );
+            } else {
+                self.import
+                    .domain
+                    .add_enum(enum_name, &string_values(values))?;
+                return Ok(VType::VTypeEnum(VTypeEnum {
+                    name: enum_name.to_string(),
+                    default: self.string_default(location, prop, |s| Some(s.to_string())),
+                }));
+            }
+        }
+
+        let vtype = match prop.get("format").and_then(Value::as_str) {
+            Some("date") => VType::VTypeDate(VTypeDate {
+                default: self.string_default(location, prop, |s| {
+                    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+                }),
+            }),
+            Some("date-time") => VType::VTypeDateTime(VTypeDateTime {
+                default: self.string_default(location, prop, parse_datetime),
+            }),
+            Some("uuid") => VType::VTypeUuid(VTypeUuid {
+                default: self.string_default(location, prop, |s| Uuid::parse_str(s).ok()),
+            }),
+            format => {
+                if let Some(format) = format {
+                    self.import.issue(
+                        location,
+                        &format!("format {} is imported as a plain string", format),
+                    );
+                }
+                VType::VTypeString(VTypeString {
+                    default: self.string_default(location, prop, |s| Some(s.to_string())),
+                })
+            }
+        };
+        Ok(vtype)
+    }
+
+    /// The value type of a property, `enum_name` is the name an inline enum
+    /// is added to the type catalog under
+    fn value_type(
+        &mut self,
+        location: &str,
+        enum_name: &str,
+        prop: &Object,
+    ) -> Result<VType, ImportError> {
+        if let Some(reference) = prop.get("$ref").and_then(Value::as_str) {
+            return match ref_name(reference) {
+                Some(name) if self.is_enum(&name) => Ok(VType::VTypeEnum(VTypeEnum {
+                    default: self.string_default(location, prop, |s| Some(s.to_string())),
+                    name,
+                })),
+                _ => {
+                    self.import
+                        .issue(location, &format!("$ref {} is imported as json", reference));
+                    Ok(VType::VTypeJson(VTypeJson { default: None }))
+                }
+            };
+        }
+
+        let vtype_name = match single_type(prop) {
+            Ok(vtype_name) => vtype_name,
+            Err(msg) => {
+                self.import.issue(location, &msg);
+                None
+            }
+        };
+        let vtype = match vtype_name.as_deref() {
+            Some("string") => self.string_type(location, enum_name, prop)?,
+            Some("boolean") => VType::VTypeBoolean(VTypeBoolean {
+                default: self.default(location, prop, Value::as_bool),
+            }),
+            Some("integer") => VType::VTypeInteger(VTypeInteger {
+                default: self.default(location, prop, |v| {
+                    v.as_i64().and_then(|v| i32::try_from(v).ok())
+                }),
+                min: self.int(location, prop, "minimum"),
+                max: self.int(location, prop, "maximum"),
+            }),
+            Some("number") => VType::VTypeFloat(VTypeFloat {
+                default: self.default(location, prop, Value::as_f64),
+                min: self.float(location, prop, "minimum"),
+                max: self.float(location, prop, "maximum"),
+            }),
+            Some("array") => {
+                let item = match prop.get("items") {
+                    Some(Value::Object(items)) => {
+                        self.unsupported_keywords(location, items, PROPERTY_KEYWORDS);
+                        self.value_type(location, enum_name, items)?
+                    }
+                    _ => {
+                        self.import
+                            .issue(location, "array without items is imported as json");
+                        VType::VTypeJson(VTypeJson { default: None })
+                    }
+                };
+                if prop.contains_key("default") {
+                    self.import
+                        .issue(location, "defaults of lists are not supported");
+                }
+                VType::VTypeList(VTypeList {
+                    item: Box::new(item),
+                })
+            }
+            _ => VType::VTypeJson(VTypeJson {
+                default: prop.get("default").map(Value::to_string),
+            }),
+        };
+        Ok(vtype)
+    }
+
+    fn property(
+        &mut self,
+        entity: &str,
+        name: &str,
+        prop: &Object,
+        required: bool,
+    ) -> Result<(), ImportError> {
+        let location = format!("{}.{}", entity, name);
+        self.unsupported_keywords(&location, prop, PROPERTY_KEYWORDS);
+
+        let target = |prop: &Object| {
+            prop.get("$ref")
+                .and_then(Value::as_str)
+                .and_then(ref_name)
+                .filter(|name| self.is_entity(name))
+        };
+        if let Some(other) = target(prop) {
+            return self
+                .import
+                .add_reference(entity, name, ReferenceType::BelongsTo, &other);
+        }
+        if let Some(Value::Object(items)) = prop.get("items") {
+            if let Some(other) = target(items) {
+                return self
+                    .import
+                    .add_reference(entity, name, ReferenceType::HasMany, &other);
+            }
+        }
+
+        let vtype = self.value_type(&location, &format!("{}_{}", entity, name), prop)?;
+        let (min_key, max_key) = match vtype {
+            VType::VTypeList(_) => ("minItems", "maxItems"),
+            _ => ("minLength", "maxLength"),
+        };
+        let constraints = AttributeConstraints {
+            required,
+            min_length: self.int(&location, prop, min_key),
+            max_length: self.int(&location, prop, max_key),
+            pattern: prop
+                .get("pattern")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            ..AttributeConstraints::default()
+        };
+        self.import.add_attribute(entity, name, vtype, constraints)
+    }
+
+    fn object(&mut self, entity: &str, def: &Object) -> Result<(), ImportError> {
+        self.unsupported_keywords(entity, def, OBJECT_KEYWORDS);
+        if matches!(def.get("additionalProperties"), Some(a) if a != false) {
+            self.import
+                .issue(entity, "additional properties are not supported");
+        }
+
+        let required = def.get("required").map(string_values).unwrap_or_default();
+        let properties = match def.get("properties") {
+            Some(Value::Object(properties)) => properties,
+            _ => return Ok(()),
+        };
+        for (name, prop) in properties {
+            let prop = match prop {
+                Value::Object(prop) => prop,
+                _ => {
+                    self.import
+                        .issue(&format!("{}.{}", entity, name), "property is not a schema");
+                    continue;
+                }
+            };
+            // Every entity has an id of its own
+            if name == "id" && prop.get("format") == Some(&Value::from("uuid")) {
+                continue;
+            }
+            self.property(entity, name, prop, required.contains(name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Import the definitions of a JSON Schema
+pub fn import_json_schema(schema: &Value) -> Result<Import, ImportError> {
+    let root = schema
+        .as_object()
+        .ok_or_else(|| ImportError::Syntax("a schema is a JSON object".to_string()))?;
+
+    let mut defs = Vec::<(String, &Object)>::new();
+    for key in &["$defs", "definitions"] {
+        if let Some(Value::Object(entries)) = root.get(*key) {
+            for (name, def) in entries {
+                match def {
+                    Value::Object(def) => defs.push((name.clone(), def)),
+                    _ => return Err(ImportError::Syntax(format!("{} is not a schema", name))),
+                }
+            }
+        }
+    }
+
+    let mut importer = Importer {
+        import: Import::default(),
+        entities: Vec::new(),
+    };
+    if defs.is_empty() && is_object(root) {
+        let name = root
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or("entity");
+        defs.push((name.to_string(), root));
+    } else {
+        let known = ["$schema", "$id", "title", "$defs", "definitions"];
+        importer.unsupported_keywords("schema", root, &known);
+    }
+
+    for (name, def) in &defs {
+        if is_string_enum(def) {
+            importer.unsupported_keywords(name, def, ENUM_KEYWORDS);
+            let values = string_values(&def["enum"]);
+            importer.import.domain.add_enum(name, &values)?;
+        } else if is_object(def) {
+            importer.import.domain.add_entity(name)?;
+            importer.entities.push(name.clone());
+        } else {
+            importer
+                .import
+                .issue(name, "only objects and string enums are imported");
+        }
+    }
+    for (name, def) in &defs {
+        if importer.is_entity(name) {
+            importer.object(name, def)?;
+        }
+    }
+    Ok(importer.import)
+}
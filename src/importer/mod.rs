@@ -0,0 +1,127 @@
+//! Build a `Domain` from data models kept elsewhere
+//!
+//! Importers go through the `Domain` API, so ids are handed out by
+//! `Domain::next_id` and the result is consistent. Whatever an importer can
+//! not represent is listed as an `ImportIssue` next to the domain.
+
+use crate::structure::data::VType;
+use crate::structure::domain::{AttributeConstraints, Domain, DomainError, ReferenceType};
+use std::error;
+use std::fmt;
+
+pub mod json_schema;
+pub mod sql;
+
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    /// The input could not be read at all
+    Syntax(String),
+    Domain(DomainError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            ImportError::Domain(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl From<DomainError> for ImportError {
+    fn from(err: DomainError) -> Self {
+        ImportError::Domain(err)
+    }
+}
+
+/// A construct of the input that did not make it into the domain as it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportIssue {
+    /// Where in the input, e.g. `book.title`
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Import {
+    pub domain: Domain,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl Import {
+    pub fn issue(&mut self, location: &str, message: &str) {
+        self.issues.push(ImportIssue {
+            location: location.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    fn entity_id(&self, entity: &str) -> Result<i32, ImportError> {
+        self.domain
+            .entities
+            .iter()
+            .find(|e| e.name == entity)
+            .map(|e| e.id)
+            .ok_or_else(|| ImportError::Domain(DomainError::EntityNameDoesNotExist(entity.into())))
+    }
+
+    /// Add an attribute, reporting instead of failing when the domain
+    /// refuses it
+    pub fn add_attribute(
+        &mut self,
+        entity: &str,
+        name: &str,
+        vtype: VType,
+        constraints: AttributeConstraints,
+    ) -> Result<(), ImportError> {
+        let location = format!("{}.{}", entity, name);
+        let entity_id = self.entity_id(entity)?;
+        let attribute = match self.domain.add_attribute(entity_id, name, vtype) {
+            Ok(attribute) => attribute,
+            Err(err) => {
+                self.issue(&location, &format!("attribute skipped, {}", err));
+                return Ok(());
+            }
+        };
+        if constraints != AttributeConstraints::default() {
+            if let Err(err) =
+                self.domain
+                    .set_attribute_constraints(entity_id, attribute.id, constraints)
+            {
+                self.issue(&location, &format!("constraints skipped, {}", err));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a reference, reporting instead of failing when the domain
+    /// refuses it
+    pub fn add_reference(
+        &mut self,
+        entity: &str,
+        name: &str,
+        reftype: ReferenceType,
+        other: &str,
+    ) -> Result<(), ImportError> {
+        let entity_id = self.entity_id(entity)?;
+        if let Err(err) = self.domain.add_reference(entity_id, name, reftype, other) {
+            self.issue(
+                &format!("{}.{}", entity, name),
+                &format!("reference skipped, {}", err),
+            );
+        }
+        Ok(())
+    }
+}
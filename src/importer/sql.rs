@@ -0,0 +1,1104 @@
+//! Import a `Domain` from PostgreSQL DDL
+//!
+//! Understands the statements a schema is usually made of, `CREATE TYPE ...
+//! AS ENUM`, `CREATE TABLE`, `CREATE INDEX` and foreign keys added with
+//! `ALTER TABLE`. Tables become entities and columns attributes. A foreign
+//! key column becomes a `belongs_to` reference named after the column,
+//! without its `_id` suffix. The `id` primary key is implied by every entity.
+//!
+//! Checks are imported when they are ranges, lengths or patterns of a
+//! single column, as `generator::sql` writes them.
+
+use super::{Import, ImportError};
+use crate::structure::data::*;
+use crate::structure::domain::{AttributeConstraints, ReferenceType};
+use chrono::{NaiveDate, NaiveDateTime};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An identifier or keyword, unquoted ones are folded to lower case
+    Ident(String, bool),
+    Str(String),
+    Num(String),
+    Punct(char),
+    Op(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ImportError> {
+    let mut tokens = Vec::<Token>::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    let unterminated = |what: &str| ImportError::Syntax(format!("unterminated {}", what));
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let end = (i + 2..chars.len().saturating_sub(1))
+                .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+                .ok_or_else(|| unterminated("comment"))?;
+            i = end + 2;
+        } else if c == '\'' || c == '"' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(unterminated("quoted text")),
+                    Some(&q) if q == c && chars.get(i + 1) == Some(&c) => {
+                        text.push(c);
+                        i += 2;
+                    }
+                    Some(&q) if q == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&other) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(if c == '\'' {
+                Token::Str(text)
+            } else {
+                Token::Ident(text, true)
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            tokens.push(Token::Ident(word.to_lowercase(), false));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Num(chars[start..i].iter().collect()));
+        } else if "(),;.[]".contains(c) {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        } else {
+            let start = i;
+            while i < chars.len() && "<>=!~:+-*/|&^%@#".contains(chars[i]) {
+                i += 1;
+            }
+            if i == start {
+                return Err(ImportError::Syntax(format!("unexpected character {}", c)));
+            }
+            tokens.push(Token::Op(chars[start..i].iter().collect()));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Column constraint keywords, they end a type or a default
+const COLUMN_KEYWORDS: &[&str] = &[
+    "constraint",
+    "not",
+    "null",
+    "primary",
+    "unique",
+    "default",
+    "references",
+    "check",
+    "collate",
+    "generated",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Str(String),
+    Num(String),
+    Bool(bool),
+}
+
+/// A single column condition of a `CHECK`
+#[derive(Debug, Clone, PartialEq)]
+enum Check {
+    Min(String),
+    Max(String),
+    MinLength(i32),
+    MaxLength(i32),
+    Pattern(String),
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    vtype: VType,
+    constraints: AttributeConstraints,
+    checks: Vec<Check>,
+    primary_key: bool,
+}
+
+#[derive(Debug)]
+struct Table {
+    name: String,
+    columns: Vec<Column>,
+}
+
+#[derive(Debug)]
+struct ForeignKey {
+    table: String,
+    column: String,
+    target: String,
+}
+
+#[derive(Default)]
+struct Schema {
+    enums: Vec<(String, Vec<String>)>,
+    tables: Vec<Table>,
+    foreign_keys: Vec<ForeignKey>,
+    /// Table, column and uniqueness of single column indexes
+    indexes: Vec<(String, String, bool)>,
+}
+
+/// A cursor over the tokens of a single statement
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(word, false)) => word == keyword,
+            _ => false,
+        }
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn keywords(&mut self, keywords: &[&str]) -> bool {
+        let start = self.pos;
+        if keywords.iter().all(|k| self.keyword(k)) {
+            true
+        } else {
+            self.pos = start;
+            false
+        }
+    }
+
+    fn punct(&mut self, c: char) -> bool {
+        let found = self.is_punct(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), ImportError> {
+        if self.punct(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", c)))
+        }
+    }
+
+    fn error(&self, msg: &str) -> ImportError {
+        ImportError::Syntax(format!(
+            "{} near {}",
+            msg,
+            self.text(self.pos, self.pos + 3)
+        ))
+    }
+
+    /// The tokens from `from` up to `to`, roughly as they were written
+    fn text(&self, from: usize, to: usize) -> String {
+        let to = to.min(self.tokens.len());
+        let words: Vec<String> = self.tokens[from.min(to)..to]
+            .iter()
+            .map(|t| match t {
+                Token::Ident(word, true) => format!("\"{}\"", word),
+                Token::Ident(word, false) => word.to_uppercase(),
+                Token::Str(text) => format!("'{}'", text),
+                Token::Num(num) => num.clone(),
+                Token::Punct(c) => c.to_string(),
+                Token::Op(op) => op.clone(),
+            })
+            .collect();
+        if words.is_empty() {
+            "the end".to_string()
+        } else {
+            words.join(" ")
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ImportError> {
+        match self.peek() {
+            Some(Token::Ident(word, _)) => {
+                self.pos += 1;
+                Ok(word.clone())
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    /// A possibly schema qualified name, the schema is reported unless it is
+    /// `public`
+    fn qualified_name(&mut self, import: &mut Import) -> Result<String, ImportError> {
+        let mut name = self.ident()?;
+        while self.punct('.') {
+            let schema = name;
+            name = self.ident()?;
+            if schema != "public" {
+                import.issue(&name, &format!("schema {} is left out", schema));
+            }
+        }
+        Ok(name)
+    }
+
+    /// Skip a parenthesised group, returning the position just inside it
+    fn skip_group(&mut self) -> Result<usize, ImportError> {
+        self.expect_punct('(')?;
+        let start = self.pos;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct('(')) => depth += 1,
+                Some(Token::Punct(')')) => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("unbalanced parentheses")),
+            }
+        }
+        Ok(start)
+    }
+
+    /// Skip to the next `,` or `)` outside parentheses, or to a column
+    /// keyword when `keywords` is set
+    fn skip_element(&mut self, keywords: bool) -> Result<(), ImportError> {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct(',') | Token::Punct(')') => break,
+                Token::Punct('(') => {
+                    self.skip_group()?;
+                }
+                Token::Ident(word, false)
+                    if keywords && COLUMN_KEYWORDS.contains(&word.as_str()) =>
+                {
+                    break
+                }
+                _ => self.pos += 1,
+            }
+        }
+        Ok(())
+    }
+
+    fn name_list(&mut self) -> Result<Vec<String>, ImportError> {
+        self.expect_punct('(')?;
+        let mut names = vec![self.ident()?];
+        while self.punct(',') {
+            names.push(self.ident()?);
+        }
+        self.expect_punct(')')?;
+        Ok(names)
+    }
+
+    fn literal(&mut self) -> Option<Literal> {
+        let start = self.pos;
+        let negative = match self.peek() {
+            Some(Token::Op(op)) if op == "-" => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        };
+        let literal = match (self.next(), negative) {
+            (Some(Token::Str(text)), false) => Some(Literal::Str(text.clone())),
+            (Some(Token::Num(num)), false) => Some(Literal::Num(num.clone())),
+            (Some(Token::Num(num)), true) => Some(Literal::Num(format!("-{}", num))),
+            (Some(Token::Ident(word, false)), false) if word == "true" => Some(Literal::Bool(true)),
+            (Some(Token::Ident(word, false)), false) if word == "false" => {
+                Some(Literal::Bool(false))
+            }
+            _ => None,
+        };
+        if literal.is_none() {
+            self.pos = start;
+        }
+        literal
+    }
+}
+
+/// The value type of a column type, with the maximum length of `VARCHAR(n)`
+fn column_type(
+    schema: &Schema,
+    name: &str,
+    args: &[String],
+    dimensions: usize,
+) -> Result<(VType, Option<i32>), String> {
+    let int_arg = |i: usize| args.get(i).and_then(|a| a.parse::<i32>().ok());
+    let (vtype, max_length) = match name {
+        "text" => (VType::VTypeString(VTypeString { default: None }), None),
+        "varchar" | "character varying" | "char" | "character" | "bpchar" => (
+            VType::VTypeString(VTypeString { default: None }),
+            int_arg(0),
+        ),
+        "boolean" | "bool" => (VType::VTypeBoolean(VTypeBoolean { default: None }), None),
+        "integer" | "int" | "int4" | "smallint" | "int2" => (
+            VType::VTypeInteger(VTypeInteger {
+                default: None,
+                min: None,
+                max: None,
+            }),
+            None,
+        ),
+        "double precision" | "float8" | "real" | "float4" | "float" => (
+            VType::VTypeFloat(VTypeFloat {
+                default: None,
+                min: None,
+                max: None,
+            }),
+            None,
+        ),
+        "numeric" | "decimal" => (
+            VType::VTypeDecimal(VTypeDecimal {
+                default: None,
+                precision: int_arg(0),
+                scale: int_arg(1),
+            }),
+            None,
+        ),
+        "date" => (VType::VTypeDate(VTypeDate { default: None }), None),
+        "timestamp" | "timestamp without time zone" => {
+            (VType::VTypeDateTime(VTypeDateTime { default: None }), None)
+        }
+        "uuid" => (VType::VTypeUuid(VTypeUuid { default: None }), None),
+        "json" | "jsonb" => (VType::VTypeJson(VTypeJson { default: None }), None),
+        other if schema.enums.iter().any(|(e, _)| e == other) => (
+            VType::VTypeEnum(VTypeEnum {
+                name: other.to_string(),
+                default: None,
+            }),
+            None,
+        ),
+        other => return Err(format!("type {} is not supported", other.to_uppercase())),
+    };
+    if dimensions > 1 {
+        return Err("multidimensional arrays are not supported".to_string());
+    }
+    if dimensions == 1 {
+        Ok((
+            VType::VTypeList(VTypeList {
+                item: Box::new(vtype),
+            }),
+            None,
+        ))
+    } else {
+        Ok((vtype, max_length))
+    }
+}
+
+/// Set the default of `vtype` from a literal
+fn with_default(vtype: VType, literal: &Literal) -> Option<VType> {
+    let text = match literal {
+        Literal::Str(text) | Literal::Num(text) => text.clone(),
+        Literal::Bool(value) => value.to_string(),
+    };
+    let vtype = match vtype {
+        VType::VTypeString(_) => VType::VTypeString(VTypeString {
+            default: Some(text),
+        }),
+        VType::VTypeBoolean(_) => VType::VTypeBoolean(VTypeBoolean {
+            default: Some(text.parse().ok()?),
+        }),
+        VType::VTypeInteger(t) => VType::VTypeInteger(VTypeInteger {
+            default: Some(text.parse().ok()?),
+            ..t
+        }),
+        VType::VTypeFloat(t) => VType::VTypeFloat(VTypeFloat {
+            default: Some(text.parse().ok()?),
+            ..t
+        }),
+        VType::VTypeDecimal(t) => VType::VTypeDecimal(VTypeDecimal {
+            default: Some(text),
+            ..t
+        }),
+        VType::VTypeDate(_) => VType::VTypeDate(VTypeDate {
+            default: Some(NaiveDate::parse_from_str(&text, "%Y-%m-%d").ok()?),
+        }),
+        VType::VTypeDateTime(_) => VType::VTypeDateTime(VTypeDateTime {
+            default: Some(
+                NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S")
+                    .or_else(|_| NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S"))
+                    .ok()?,
+            ),
+        }),
+        VType::VTypeUuid(_) => VType::VTypeUuid(VTypeUuid {
+            default: Some(Uuid::parse_str(&text).ok()?),
+        }),
+        VType::VTypeJson(_) => VType::VTypeJson(VTypeJson {
+            default: Some(text),
+        }),
+        VType::VTypeEnum(t) => VType::VTypeEnum(VTypeEnum {
+            default: Some(text),
+            ..t
+        }),
+        VType::VTypeList(_) => return None,
+    };
+    Some(vtype)
+}
+
+/// Parse one condition of a `CHECK`, `column op literal` or
+/// `length_fn(column) op number`
+fn parse_check(tokens: &[Token]) -> Option<(String, Check)> {
+    let number = |t: &Token| match t {
+        Token::Num(num) => Some(num.clone()),
+        _ => None,
+    };
+    let int = |t: &Token| number(t).and_then(|n| n.parse::<i32>().ok());
+    match tokens {
+        [Token::Ident(f, false), Token::Punct('('), Token::Ident(column, _), Token::Punct(')'), Token::Op(op), value]
+            if f == "char_length" || f == "length" || f == "cardinality" =>
+        {
+            let check = match op.as_str() {
+                ">=" => Check::MinLength(int(value)?),
+                "<=" => Check::MaxLength(int(value)?),
+                _ => return None,
+            };
+            Some((column.clone(), check))
+        }
+        [Token::Ident(column, _), Token::Op(op), Token::Op(minus), value] if minus == "-" => {
+            let value = format!("-{}", number(value)?);
+            match op.as_str() {
+                ">=" => Some((column.clone(), Check::Min(value))),
+                "<=" => Some((column.clone(), Check::Max(value))),
+                _ => None,
+            }
+        }
+        [Token::Ident(column, _), Token::Op(op), value] => match (op.as_str(), value) {
+            (">=", value) => Some((column.clone(), Check::Min(number(value)?))),
+            ("<=", value) => Some((column.clone(), Check::Max(number(value)?))),
+            ("~", Token::Str(pattern)) => Some((column.clone(), Check::Pattern(pattern.clone()))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Split the inside of a `CHECK (...)` into its conditions, joined by `AND`
+fn parse_checks(parser: &Parser, from: usize, to: usize) -> (Vec<(String, Check)>, Vec<String>) {
+    let tokens = &parser.tokens[from..to];
+    let mut parts = Vec::<&[Token]>::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => depth -= 1,
+            Token::Ident(word, false) if depth == 0 && word == "and" => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+
+    let mut checks = Vec::new();
+    let mut unsupported = Vec::new();
+    for part in parts {
+        // Conditions may be wrapped in parentheses of their own
+        let inner = match part {
+            [Token::Punct('('), inner @ .., Token::Punct(')')] => inner,
+            part => part,
+        };
+        match parse_check(inner) {
+            Some(check) => checks.push(check),
+            None => {
+                let sub = Parser {
+                    tokens: part,
+                    pos: 0,
+                };
+                unsupported.push(sub.text(0, part.len()));
+            }
+        }
+    }
+    (checks, unsupported)
+}
+
+struct Reader<'a> {
+    import: &'a mut Import,
+    schema: Schema,
+}
+
+impl<'a> Reader<'a> {
+    fn create_type(&mut self, p: &mut Parser) -> Result<(), ImportError> {
+        let name = p.qualified_name(self.import)?;
+        if !p.keywords(&["as", "enum"]) {
+            self.import
+                .issue(&name, "only enum types are supported, type skipped");
+            return Ok(());
+        }
+        p.expect_punct('(')?;
+        let mut values = Vec::new();
+        while let Some(Token::Str(value)) = p.peek() {
+            values.push(value.clone());
+            p.pos += 1;
+            if !p.punct(',') {
+                break;
+            }
+        }
+        p.expect_punct(')')?;
+        self.schema.enums.push((name, values));
+        Ok(())
+    }
+
+    /// The type of a column, its words, arguments and array dimensions
+    fn type_name(&mut self, p: &mut Parser) -> Result<(String, Vec<String>, usize), ImportError> {
+        let mut words = vec![p.ident()?];
+        let mut args = Vec::new();
+        loop {
+            match p.peek() {
+                Some(Token::Ident(word, false)) if !COLUMN_KEYWORDS.contains(&word.as_str()) => {
+                    words.push(word.clone());
+                    p.pos += 1;
+                }
+                Some(Token::Punct('(')) if args.is_empty() => {
+                    p.pos += 1;
+                    while let Some(Token::Num(num)) = p.peek() {
+                        args.push(num.clone());
+                        p.pos += 1;
+                        if !p.punct(',') {
+                            break;
+                        }
+                    }
+                    p.expect_punct(')')?;
+                }
+                _ => break,
+            }
+        }
+        let mut dimensions = 0;
+        while p.punct('[') {
+            while let Some(Token::Num(_)) = p.peek() {
+                p.pos += 1;
+            }
+            p.expect_punct(']')?;
+            dimensions += 1;
+        }
+        // `timestamp(3) without time zone` puts the arguments in between
+        while let Some(Token::Ident(word, false)) = p.peek() {
+            if COLUMN_KEYWORDS.contains(&word.as_str()) {
+                break;
+            }
+            words.push(word.clone());
+            p.pos += 1;
+        }
+        Ok((words.join(" "), args, dimensions))
+    }
+
+    fn check(
+        &mut self,
+        p: &mut Parser,
+        location: &str,
+        column: Option<&str>,
+    ) -> Result<Vec<(String, Check)>, ImportError> {
+        let start = p.skip_group()?;
+        let (checks, unsupported) = parse_checks(p, start, p.pos - 1);
+        for condition in unsupported {
+            self.import
+                .issue(location, &format!("check {} is not supported", condition));
+        }
+        let (checks, others): (Vec<_>, Vec<_>) = checks
+            .into_iter()
+            .partition(|(c, _)| column.is_none() || column == Some(c.as_str()));
+        for (c, _) in others {
+            self.import.issue(
+                location,
+                &format!("check on another column {} is not supported", c),
+            );
+        }
+        Ok(checks)
+    }
+
+    fn references(&mut self, p: &mut Parser, table: &str, column: &str) -> Result<(), ImportError> {
+        let target = p.qualified_name(self.import)?;
+        if p.is_punct('(') {
+            let columns = p.name_list()?;
+            if columns != ["id"] {
+                self.import.issue(
+                    &format!("{}.{}", table, column),
+                    &format!(
+                        "foreign key to {}.{} is not supported",
+                        target,
+                        columns.join(", ")
+                    ),
+                );
+                return Ok(());
+            }
+        }
+        let start = p.pos;
+        while p.keyword("on") {
+            p.ident()?;
+            // NO ACTION, SET NULL and SET DEFAULT take two words
+            if !p.keyword("no") {
+                p.keyword("set");
+            }
+            p.ident()?;
+        }
+        if p.pos > start {
+            self.import.issue(
+                &format!("{}.{}", table, column),
+                &format!("{} is not supported", p.text(start, p.pos)),
+            );
+        }
+        self.schema.foreign_keys.push(ForeignKey {
+            table: table.to_string(),
+            column: column.to_string(),
+            target,
+        });
+        Ok(())
+    }
+
+    fn column(&mut self, p: &mut Parser, table: &str) -> Result<Option<Column>, ImportError> {
+        let name = p.ident()?;
+        let location = format!("{}.{}", table, name);
+        let (type_name, args, dimensions) = self.type_name(p)?;
+        let (type_name, note) = match type_name.as_str() {
+            "bigint" | "int8" => ("integer", Some("64 bit integers are imported as integers")),
+            "serial" | "serial4" | "smallserial" | "serial2" | "bigserial" | "serial8" => (
+                "integer",
+                Some("the sequence of a serial column is left out"),
+            ),
+            "timestamptz" | "timestamp with time zone" => {
+                ("timestamp", Some("the time zone of timestamps is left out"))
+            }
+            other => (other, None),
+        };
+        if let Some(note) = note {
+            self.import.issue(&location, note);
+        }
+        let (vtype, max_length) = match column_type(&self.schema, type_name, &args, dimensions) {
+            Ok(vtype) => vtype,
+            Err(msg) => {
+                self.import
+                    .issue(&location, &format!("{}, column skipped", msg));
+                p.skip_element(false)?;
+                return Ok(None);
+            }
+        };
+        let mut column = Column {
+            name: name.clone(),
+            vtype,
+            constraints: AttributeConstraints {
+                max_length,
+                ..AttributeConstraints::default()
+            },
+            checks: Vec::new(),
+            primary_key: false,
+        };
+
+        while !p.at_end() && !p.is_punct(',') && !p.is_punct(')') {
+            if p.keyword("constraint") {
+                p.ident()?;
+            } else if p.keywords(&["not", "null"]) {
+                column.constraints.required = true;
+            } else if p.keyword("null") {
+                // Columns are nullable unless they are required
+            } else if p.keywords(&["primary", "key"]) {
+                column.primary_key = true;
+            } else if p.keyword("unique") {
+                column.constraints.unique = true;
+            } else if p.keyword("default") {
+                let start = p.pos;
+                let literal = p.literal();
+                // A cast of a literal, as in 'open'::status
+                if literal.is_some() {
+                    if let Some(Token::Op(op)) = p.peek() {
+                        if op == "::" {
+                            p.pos += 1;
+                            self.type_name(p)?;
+                        }
+                    }
+                }
+                let at_keyword = p.at_end()
+                    || p.is_punct(',')
+                    || p.is_punct(')')
+                    || matches!(p.peek(), Some(Token::Ident(w, false)) if COLUMN_KEYWORDS.contains(&w.as_str()));
+                match literal.filter(|_| at_keyword) {
+                    Some(literal) => match with_default(column.vtype.clone(), &literal) {
+                        Some(vtype) => column.vtype = vtype,
+                        None => self.import.issue(
+                            &location,
+                            &format!("default {:?} does not fit the column", literal),
+                        ),
+                    },
+                    None => {
+                        p.skip_element(true)?;
+                        self.import.issue(
+                            &location,
+                            &format!("default {} is not supported", p.text(start, p.pos)),
+                        );
+                    }
+                }
+            } else if p.keyword("references") {
+                self.references(p, table, &name)?;
+            } else if p.keyword("check") {
+                let checks = self.check(p, &location, Some(&name))?;
+                column.checks.extend(checks.into_iter().map(|(_, c)| c));
+            } else {
+                let start = p.pos;
+                p.pos += 1;
+                p.skip_element(true)?;
+                self.import.issue(
+                    &location,
+                    &format!("{} is not supported", p.text(start, p.pos)),
+                );
+            }
+        }
+        Ok(Some(column))
+    }
+
+    /// A table constraint, applied to the columns read so far
+    fn table_constraint(&mut self, p: &mut Parser, table: &mut Table) -> Result<(), ImportError> {
+        let location = table.name.clone();
+        if p.keyword("constraint") {
+            p.ident()?;
+        }
+        if p.keywords(&["primary", "key"]) {
+            let columns = p.name_list()?;
+            if columns != ["id"] {
+                self.import.issue(
+                    &location,
+                    &format!("primary key {} is not supported", columns.join(", ")),
+                );
+            }
+        } else if p.keyword("unique") {
+            let columns = p.name_list()?;
+            match table
+                .columns
+                .iter_mut()
+                .find(|c| columns == [c.name.clone()])
+            {
+                Some(column) => column.constraints.unique = true,
+                None => self.import.issue(
+                    &location,
+                    &format!("unique {} is not supported", columns.join(", ")),
+                ),
+            }
+        } else if p.keywords(&["foreign", "key"]) {
+            let columns = p.name_list()?;
+            if !p.keyword("references") || columns.len() != 1 {
+                return Err(p.error("expected a single column foreign key"));
+            }
+            self.references(p, &table.name, &columns[0])?;
+        } else if p.keyword("check") {
+            for (name, check) in self.check(p, &location, None)? {
+                match table.columns.iter_mut().find(|c| c.name == name) {
+                    Some(column) => column.checks.push(check),
+                    None => self
+                        .import
+                        .issue(&location, &format!("check on unknown column {}", name)),
+                }
+            }
+        } else {
+            let start = p.pos;
+            p.skip_element(false)?;
+            self.import.issue(
+                &location,
+                &format!("{} is not supported", p.text(start, p.pos)),
+            );
+        }
+        Ok(())
+    }
+
+    fn create_table(&mut self, p: &mut Parser) -> Result<(), ImportError> {
+        p.keywords(&["if", "not", "exists"]);
+        let name = p.qualified_name(self.import)?;
+        let mut table = Table {
+            name,
+            columns: Vec::new(),
+        };
+        p.expect_punct('(')?;
+        loop {
+            let constraint = [
+                "constraint",
+                "primary",
+                "unique",
+                "foreign",
+                "check",
+                "exclude",
+            ]
+            .iter()
+            .any(|k| p.is_keyword(k));
+            if constraint {
+                self.table_constraint(p, &mut table)?;
+            } else if let Some(column) = self.column(p, &table.name.clone())? {
+                table.columns.push(column);
+            }
+            if !p.punct(',') {
+                break;
+            }
+        }
+        p.expect_punct(')')?;
+        if !p.at_end() {
+            self.import.issue(
+                &table.name,
+                &format!("{} is not supported", p.text(p.pos, p.tokens.len())),
+            );
+        }
+        self.schema.tables.push(table);
+        Ok(())
+    }
+
+    fn create_index(&mut self, p: &mut Parser, unique: bool) -> Result<(), ImportError> {
+        p.keyword("concurrently");
+        p.keywords(&["if", "not", "exists"]);
+        if !p.is_keyword("on") {
+            p.ident()?;
+        }
+        if !p.keyword("on") {
+            return Err(p.error("expected ON"));
+        }
+        p.keyword("only");
+        let table = p.qualified_name(self.import)?;
+        let mut plain = true;
+        if p.keyword("using") {
+            plain = p.ident()? == "btree";
+        }
+        let start = p.pos;
+        let columns = p.name_list();
+        match columns {
+            Ok(columns) if plain && columns.len() == 1 && p.at_end() => {
+                self.schema
+                    .indexes
+                    .push((table, columns[0].clone(), unique));
+            }
+            _ => self.import.issue(
+                &table,
+                &format!("index {} is not supported", p.text(start, p.tokens.len())),
+            ),
+        }
+        Ok(())
+    }
+
+    fn alter_table(&mut self, p: &mut Parser, statement: &str) -> Result<(), ImportError> {
+        p.keywords(&["if", "exists"]);
+        p.keyword("only");
+        let name = p.qualified_name(self.import)?;
+        if !p.keyword("add") {
+            self.import
+                .issue(&name, &format!("{} is not supported", statement));
+            return Ok(());
+        }
+        let index = self.schema.tables.iter().position(|t| t.name == name);
+        let mut table = match index {
+            Some(index) => self.schema.tables.remove(index),
+            None => {
+                self.import
+                    .issue(&name, &format!("{} alters an unknown table", statement));
+                return Ok(());
+            }
+        };
+        let result = self.alter_table_add(p, &mut table);
+        self.schema.tables.push(table);
+        result
+    }
+
+    fn alter_table_add(&mut self, p: &mut Parser, table: &mut Table) -> Result<(), ImportError> {
+        loop {
+            let constraint = [
+                "constraint",
+                "primary",
+                "unique",
+                "foreign",
+                "check",
+                "exclude",
+            ]
+            .iter()
+            .any(|k| p.is_keyword(k));
+            if constraint {
+                self.table_constraint(p, table)?;
+            } else {
+                p.keyword("column");
+                if let Some(column) = self.column(p, &table.name.clone())? {
+                    table.columns.push(column);
+                }
+            }
+            if !p.punct(',') || !p.keyword("add") {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, tokens: &[Token]) -> Result<(), ImportError> {
+        let mut p = Parser { tokens, pos: 0 };
+        let statement = p.text(0, 3);
+        if p.keyword("create") {
+            if p.keyword("type") {
+                return self.create_type(&mut p);
+            }
+            p.keyword("unlogged");
+            if p.keyword("table") {
+                return self.create_table(&mut p);
+            }
+            let unique = p.keyword("unique");
+            if p.keyword("index") {
+                return self.create_index(&mut p, unique);
+            }
+        } else if p.keywords(&["alter", "table"]) {
+            return self.alter_table(&mut p, &statement);
+        }
+        self.import
+            .issue("schema", &format!("statement {} ... is skipped", statement));
+        Ok(())
+    }
+}
+
+/// Add the tables read from the DDL to the domain of `import`
+fn build(import: &mut Import, schema: Schema) -> Result<(), ImportError> {
+    for (name, values) in &schema.enums {
+        import.domain.add_enum(name, values)?;
+    }
+    for table in &schema.tables {
+        import.domain.add_entity(&table.name)?;
+    }
+
+    for table in &schema.tables {
+        for column in &table.columns {
+            let location = format!("{}.{}", table.name, column.name);
+            let foreign_key = schema
+                .foreign_keys
+                .iter()
+                .find(|fk| fk.table == table.name && fk.column == column.name);
+            if let Some(fk) = foreign_key {
+                if schema.tables.iter().any(|t| t.name == fk.target) {
+                    let name = column
+                        .name
+                        .strip_suffix("_id")
+                        .filter(|n| !n.is_empty())
+                        .unwrap_or(&column.name);
+                    import.add_reference(
+                        &table.name,
+                        name,
+                        ReferenceType::BelongsTo,
+                        &fk.target,
+                    )?;
+                    continue;
+                }
+                import.issue(
+                    &location,
+                    &format!("foreign key to unknown table {} is left out", fk.target),
+                );
+            }
+            if column.primary_key || column.name == "id" {
+                let implied = column.name == "id"
+                    && column.vtype == VType::VTypeUuid(VTypeUuid { default: None });
+                if !implied {
+                    import.issue(
+                        &location,
+                        "only a UUID primary key named id is supported, column skipped",
+                    );
+                }
+                continue;
+            }
+
+            let mut vtype = column.vtype.clone();
+            let mut constraints = column.constraints.clone();
+            for index in &schema.indexes {
+                if index.0 == table.name && index.1 == column.name {
+                    constraints.indexed = true;
+                    constraints.unique |= index.2;
+                }
+            }
+            for check in &column.checks {
+                let applied = match (check, &mut vtype) {
+                    (Check::Min(min), VType::VTypeInteger(t)) => {
+                        min.parse().map(|m| t.min = Some(m)).is_ok()
+                    }
+                    (Check::Max(max), VType::VTypeInteger(t)) => {
+                        max.parse().map(|m| t.max = Some(m)).is_ok()
+                    }
+                    (Check::Min(min), VType::VTypeFloat(t)) => {
+                        min.parse().map(|m| t.min = Some(m)).is_ok()
+                    }
+                    (Check::Max(max), VType::VTypeFloat(t)) => {
+                        max.parse().map(|m| t.max = Some(m)).is_ok()
+                    }
+                    (Check::MinLength(min), _) => {
+                        constraints.min_length = Some(*min);
+                        true
+                    }
+                    (Check::MaxLength(max), _) => {
+                        constraints.max_length = Some(*max);
+                        true
+                    }
+                    (Check::Pattern(pattern), _) => {
+                        constraints.pattern = Some(pattern.clone());
+                        true
+                    }
+                    _ => false,
+                };
+                if !applied {
+                    import.issue(&location, &format!("check {:?} is not supported", check));
+                }
+            }
+            import.add_attribute(&table.name, &column.name, vtype, constraints)?;
+        }
+    }
+
+    for fk in &schema.foreign_keys {
+        let known = schema
+            .tables
+            .iter()
+            .any(|t| t.name == fk.table && t.columns.iter().any(|c| c.name == fk.column));
+        if !known {
+            import.issue(
+                &format!("{}.{}", fk.table, fk.column),
+                "foreign key on an unknown column is left out",
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Import the tables of a PostgreSQL schema
+pub fn import_sql(ddl: &str) -> Result<Import, ImportError> {
+    let tokens = tokenize(ddl)?;
+    let mut import = Import::default();
+    let mut reader = Reader {
+        import: &mut import,
+        schema: Schema::default(),
+    };
+    for statement in tokens.split(|t| *t == Token::Punct(';')) {
+        if !statement.is_empty() {
+            reader.statement(statement)?;
+        }
+    }
+    let schema = reader.schema;
+    build(&mut import, schema)?;
+    Ok(import)
+}
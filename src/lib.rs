@@ -17,7 +17,10 @@ extern crate dotenv;
 extern crate log;
 
 pub mod db;
+pub mod engine;
+pub mod generator;
 pub mod graphql;
+pub mod importer;
 pub mod messages;
 pub mod structure;
 pub mod util;
@@ -18,7 +18,10 @@ extern crate dotenv;
 extern crate log;
 
 mod db;
+mod engine;
+mod generator;
 mod graphql;
+mod importer;
 mod messages;
 mod structure;
 mod util;
@@ -33,12 +36,181 @@ fn create_graphql_filter() -> warp::filters::BoxedFilter<(impl Reply,)> {
     graphql_filter.boxed()
 }
 
+/// The records API of a project, with a schema built from its domains for
+/// every request
+fn create_records_filter() -> warp::filters::BoxedFilter<(impl Reply,)> {
+    let endpoint = warp::path("projects")
+        .and(warp::path::param::<uuid::Uuid>())
+        .and(warp::path("graphql"))
+        .and(warp::path::end());
+    let graphiql = warp::get2().and(endpoint).map(|project_id: uuid::Uuid| {
+        warp::reply::html(juniper::http::graphiql::graphiql_source(&format!(
+            "/projects/{}/graphql",
+            project_id
+        )))
+    });
+    let graphql = warp::post2().and(endpoint).and(warp::body::json()).map(
+        |project_id: uuid::Uuid, request: juniper::http::GraphQLRequest| {
+            let context = graphql::schema::Context::new();
+            let api = context
+                .dbpool
+                .get()
+                .map_err(|err| err.to_string())
+                .and_then(|conn| {
+                    graphql::dynamic::ProjectApi::load(&conn, &project_id)
+                        .map_err(|err| err.to_string())
+                });
+            let (ok, body) = match api {
+                Ok(api) => {
+                    let schema = graphql::dynamic::create_record_schema(api);
+                    let response = request.execute(&schema, &context);
+                    (response.is_ok(), serde_json::to_string(&response))
+                }
+                Err(err) => {
+                    let err: juniper::FieldError = err.into();
+                    let response = juniper::http::GraphQLResponse::error(err);
+                    (false, serde_json::to_string(&response))
+                }
+            };
+            let status = if ok {
+                warp::http::StatusCode::OK
+            } else {
+                warp::http::StatusCode::BAD_REQUEST
+            };
+            warp::http::Response::builder()
+                .status(status)
+                .header("content-type", "application/json")
+                .body(body.unwrap_or_default())
+        },
+    );
+    graphiql.or(graphql).boxed()
+}
+
+fn read_domain(path: &str) -> Result<structure::domain::DomainDocument, String> {
+    let json = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    structure::domain::DomainDocument::from_json(&json)
+        .map_err(|err| format!("{}: {:?}", path, err))
+}
+
+fn run_migration(matches: &clap::ArgMatches) -> Result<(), String> {
+    let from = read_domain(matches.value_of("FROM").unwrap())?;
+    let to = read_domain(matches.value_of("TO").unwrap())?;
+    let migration = generator::migration::domain_migration(&from.body, &to.body)
+        .map_err(|err| err.to_string())?;
+    if migration.is_empty() {
+        return Err("The domain documents are structurally the same".to_owned());
+    }
+    let dir = std::path::Path::new(matches.value_of("dir").unwrap());
+    let now = chrono::Utc::now().naive_utc();
+    let path = migration
+        .write(dir, matches.value_of("name").unwrap(), &now)
+        .map_err(|err| format!("{}: {}", dir.display(), err))?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Write generated output to the `output` argument, or to stdout
+fn write_output(matches: &clap::ArgMatches, generated: &str) -> Result<(), String> {
+    match matches.value_of("output") {
+        Some(output) => {
+            std::fs::write(output, generated).map_err(|err| format!("{}: {}", output, err))
+        }
+        None => {
+            print!("{}", generated);
+            Ok(())
+        }
+    }
+}
+
+fn run_ddl(matches: &clap::ArgMatches) -> Result<(), String> {
+    let doc = read_domain(matches.value_of("INPUT").unwrap())?;
+    let ddl = generator::sql::document_to_ddl(&doc).map_err(|err| err.to_string())?;
+    write_output(matches, &ddl)
+}
+
+fn run_json_schema(matches: &clap::ArgMatches) -> Result<(), String> {
+    let doc = read_domain(matches.value_of("INPUT").unwrap())?;
+    let schema =
+        generator::json_schema::document_to_json_schema(&doc).map_err(|err| err.to_string())?;
+    write_output(matches, &format!("{:#}\n", schema))
+}
+
+fn run_erd(matches: &clap::ArgMatches) -> Result<(), String> {
+    let doc = read_domain(matches.value_of("INPUT").unwrap())?;
+    let format = matches.value_of("format").unwrap().parse()?;
+    write_output(matches, &generator::erd::document_to_erd(&doc, format))
+}
+
+fn run_models(matches: &clap::ArgMatches) -> Result<(), String> {
+    let doc = read_domain(matches.value_of("INPUT").unwrap())?;
+    let style = match matches.value_of("references") {
+        Some("nested") => generator::model::ReferenceStyle::Nested,
+        _ => generator::model::ReferenceStyle::Ids,
+    };
+    let models = generator::model::document_models(&doc, style).map_err(|err| err.to_string())?;
+    let dir = std::path::Path::new(matches.value_of("dir").unwrap());
+    let paths = models
+        .write(dir)
+        .map_err(|err| format!("{}: {}", dir.display(), err))?;
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn run_import(matches: &clap::ArgMatches) -> Result<(), String> {
+    let input = matches.value_of("INPUT").unwrap();
+    let text = std::fs::read_to_string(input).map_err(|err| format!("{}: {}", input, err))?;
+    let import = match matches.value_of("from") {
+        Some("json-schema") => {
+            let schema = serde_json::from_str(&text).map_err(|err| format!("{}: {}", input, err))?;
+            importer::json_schema::import_json_schema(&schema)
+        }
+        _ => importer::sql::import_sql(&text),
+    }
+    .map_err(|err| format!("{}: {}", input, err))?;
+
+    for issue in &import.issues {
+        eprintln!("{}: {}", input, issue);
+    }
+    let name = matches.value_of("name").map(str::to_string).unwrap_or_else(|| {
+        std::path::Path::new(input)
+            .file_stem()
+            .map_or("imported".to_string(), |stem| stem.to_string_lossy().to_string())
+    });
+    let doc = structure::domain::DomainDocument {
+        name,
+        body: import.domain,
+        ..structure::domain::DomainDocument::default()
+    };
+    write_output(matches, &format!("{}\n", doc.to_json()))
+}
+
 fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
 
     let yaml = load_yaml!("cli.yml");
-    let _matches = clap::App::from(yaml).get_matches();
+    let matches = clap::App::from(yaml).get_matches();
+
+    let result = match matches.subcommand() {
+        ("ddl", Some(matches)) => Some(run_ddl(matches)),
+        ("migration", Some(matches)) => Some(run_migration(matches)),
+        ("json-schema", Some(matches)) => Some(run_json_schema(matches)),
+        ("erd", Some(matches)) => Some(run_erd(matches)),
+        ("models", Some(matches)) => Some(run_models(matches)),
+        ("import", Some(matches)) => Some(run_import(matches)),
+        _ => None,
+    };
+    if let Some(result) = result {
+        std::process::exit(match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        });
+    }
 
     let graphql_filter = create_graphql_filter();
     let log = warp::log("warp_server");
@@ -53,7 +225,8 @@ fn main() {
     let graphql_routes = warp::get2()
         .and(warp::path("graphiql"))
         .and(juniper_warp::graphiql_filter("/graphql"))
-        .or(graphql_filter);
+        .or(graphql_filter)
+        .or(create_records_filter());
 
     warp::serve(graphql_routes.with(log)).run(([127, 0, 0, 1], 8080));
 }
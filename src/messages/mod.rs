@@ -1,3 +1,9 @@
+use crate::structure::data::*;
+use crate::structure::domain::{
+    AttributeConstraints, AttributeStorage, AttributeStorageMode, ReferenceType,
+};
+use crate::structure::xflow::XFlowNodeType;
+use chrono::{NaiveDate, NaiveDateTime};
 use uuid::Uuid;
 
 #[derive(juniper::GraphQLInputObject)]
@@ -71,6 +77,31 @@ pub struct DomainAddEntityInput {
     pub domain_id: Uuid,
 }
 
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainRenameEntityInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub name: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainRemoveEntityInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    #[graphql(description = "Also remove the references that point at the entity")]
+    pub cascade: Option<bool>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainAddEnumInput {
+    pub name: String,
+    pub values: Vec<String>,
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+}
+
 #[derive(juniper::GraphQLInputObject)]
 pub struct DocumentProperties {
     pub name: Option<String>,
@@ -99,6 +130,160 @@ pub struct AttributeInputBoolean {
     pub default: Option<bool>,
 }
 
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputFloat {
+    pub default: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputDecimal {
+    pub default: Option<String>,
+    pub precision: Option<i32>,
+    pub scale: Option<i32>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputDate {
+    pub default: Option<NaiveDate>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputDateTime {
+    pub default: Option<NaiveDateTime>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputUuid {
+    pub default: Option<Uuid>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputJson {
+    pub default: Option<String>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputEnum {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeInputList {
+    pub item: Box<VTypeInput>,
+}
+
+#[derive(juniper::GraphQLEnum, Debug, Clone, Copy, PartialEq)]
+pub enum VTypeKind {
+    String,
+    Boolean,
+    Integer,
+    Float,
+    Decimal,
+    Date,
+    DateTime,
+    Uuid,
+    Json,
+    Enum,
+    List,
+}
+
+/// A `VType` as GraphQL input, `kind` selects which of the optional
+/// type specific fields is read. Leaving that field out uses its defaults.
+#[derive(juniper::GraphQLInputObject)]
+pub struct VTypeInput {
+    pub kind: VTypeKind,
+    pub string: Option<AttributeInputString>,
+    pub boolean: Option<AttributeInputBoolean>,
+    pub integer: Option<AttributeInputInteger>,
+    pub float: Option<AttributeInputFloat>,
+    pub decimal: Option<AttributeInputDecimal>,
+    pub date: Option<AttributeInputDate>,
+    pub datetime: Option<AttributeInputDateTime>,
+    pub uuid: Option<AttributeInputUuid>,
+    pub json: Option<AttributeInputJson>,
+    #[graphql(name = "enum")]
+    pub enumeration: Option<AttributeInputEnum>,
+    pub list: Option<AttributeInputList>,
+}
+
+impl VTypeInput {
+    pub fn to_vtype(&self) -> Result<VType, String> {
+        let vtype = match self.kind {
+            VTypeKind::String => VType::VTypeString(VTypeString {
+                default: self.string.as_ref().and_then(|i| i.default.clone()),
+            }),
+            VTypeKind::Boolean => VType::VTypeBoolean(VTypeBoolean {
+                default: self.boolean.as_ref().and_then(|i| i.default),
+            }),
+            VTypeKind::Integer => VType::VTypeInteger(match &self.integer {
+                Some(i) => VTypeInteger {
+                    default: i.default,
+                    min: i.min,
+                    max: i.max,
+                },
+                None => VTypeInteger {
+                    default: None,
+                    min: None,
+                    max: None,
+                },
+            }),
+            VTypeKind::Float => VType::VTypeFloat(match &self.float {
+                Some(i) => VTypeFloat {
+                    default: i.default,
+                    min: i.min,
+                    max: i.max,
+                },
+                None => VTypeFloat {
+                    default: None,
+                    min: None,
+                    max: None,
+                },
+            }),
+            VTypeKind::Decimal => VType::VTypeDecimal(match &self.decimal {
+                Some(i) => VTypeDecimal {
+                    default: i.default.clone(),
+                    precision: i.precision,
+                    scale: i.scale,
+                },
+                None => VTypeDecimal {
+                    default: None,
+                    precision: None,
+                    scale: None,
+                },
+            }),
+            VTypeKind::Date => VType::VTypeDate(VTypeDate {
+                default: self.date.as_ref().and_then(|i| i.default),
+            }),
+            VTypeKind::DateTime => VType::VTypeDateTime(VTypeDateTime {
+                default: self.datetime.as_ref().and_then(|i| i.default),
+            }),
+            VTypeKind::Uuid => VType::VTypeUuid(VTypeUuid {
+                default: self.uuid.as_ref().and_then(|i| i.default),
+            }),
+            VTypeKind::Json => VType::VTypeJson(VTypeJson {
+                default: self.json.as_ref().and_then(|i| i.default.clone()),
+            }),
+            VTypeKind::Enum => match &self.enumeration {
+                Some(i) => VType::VTypeEnum(VTypeEnum {
+                    name: i.name.clone(),
+                    default: i.default.clone(),
+                }),
+                None => return Err("An enum type requires the 'enum' field".to_owned()),
+            },
+            VTypeKind::List => match &self.list {
+                Some(i) => VType::VTypeList(VTypeList {
+                    item: Box::new(i.item.to_vtype()?),
+                }),
+                None => return Err("A list type requires the 'list' field".to_owned()),
+            },
+        };
+        Ok(vtype)
+    }
+}
+
 #[derive(juniper::GraphQLInputObject)]
 pub struct AddStringAttributeToEntity {
     pub project_id: Uuid,
@@ -108,6 +293,254 @@ pub struct AddStringAttributeToEntity {
     pub default: Option<String>,
 }
 
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainAddAttributeInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub name: String,
+    pub vtype: VTypeInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainUpdateAttributeInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub attribute_id: i32,
+    pub name: Option<String>,
+    pub vtype: Option<VTypeInput>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct AttributeConstraintsInput {
+    pub required: Option<bool>,
+    pub unique: Option<bool>,
+    pub indexed: Option<bool>,
+    pub min_length: Option<i32>,
+    pub max_length: Option<i32>,
+    pub pattern: Option<String>,
+}
+
+impl AttributeConstraintsInput {
+    pub fn to_constraints(&self) -> AttributeConstraints {
+        AttributeConstraints {
+            required: self.required.unwrap_or(false),
+            unique: self.unique.unwrap_or(false),
+            indexed: self.indexed.unwrap_or(false),
+            min_length: self.min_length,
+            max_length: self.max_length,
+            pattern: self.pattern.clone(),
+        }
+    }
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainSetAttributeConstraintsInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub attribute_id: i32,
+    pub constraints: AttributeConstraintsInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainSetAttributeStorageInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub attribute_id: i32,
+    pub mode: AttributeStorageMode,
+    #[graphql(description = "The expression a computed attribute is derived from")]
+    pub expression: Option<String>,
+}
+
+impl DomainSetAttributeStorageInput {
+    pub fn to_storage(&self) -> Result<AttributeStorage, String> {
+        match self.mode {
+            AttributeStorageMode::Persistent => Ok(AttributeStorage::Persistent),
+            AttributeStorageMode::Transient => Ok(AttributeStorage::Transient),
+            AttributeStorageMode::Computed => match &self.expression {
+                Some(expression) => Ok(AttributeStorage::Computed {
+                    expression: expression.clone(),
+                }),
+                None => Err("A computed attribute requires an expression".to_owned()),
+            },
+        }
+    }
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainRemoveAttributeInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub attribute_id: i32,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainAddReferenceInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub name: String,
+    pub reftype: ReferenceType,
+    pub other: String,
+    #[graphql(description = "Join entity for a many to many reference, created when left out")]
+    pub through: Option<String>,
+    #[graphql(description = "Also add the inverse reference on the other entity")]
+    pub inverse: Option<bool>,
+    #[graphql(description = "Name of the inverse reference, defaults to the entity name")]
+    pub inverse_name: Option<String>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainUpdateReferenceInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub reference_id: i32,
+    pub name: Option<String>,
+    pub reftype: Option<ReferenceType>,
+    pub other: Option<String>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct DomainRemoveReferenceInput {
+    pub project_id: Uuid,
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    pub reference_id: i32,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct ValidateRecordInput {
+    pub domain_id: Uuid,
+    pub entity_id: i32,
+    #[graphql(description = "The id of the record, when it is stored already")]
+    pub record_id: Option<Uuid>,
+    #[graphql(description = "The body of the record, as JSON")]
+    pub record: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct PositionInput {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl PositionInput {
+    pub fn to_position(&self) -> Position {
+        Position {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct FloxParametersInput {
+    pub expression: String,
+    #[graphql(description = "Name of the variable the node sets")]
+    pub returns: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowAddNodeInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub nodetype: XFlowNodeType,
+    pub action: String,
+    pub label: String,
+    pub position: PositionInput,
+    #[graphql(description = "The parameters of a flox node, required for those")]
+    pub flox: Option<FloxParametersInput>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowUpdateNodeInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub node_id: i32,
+    pub action: Option<String>,
+    pub label: Option<String>,
+    pub flox: Option<FloxParametersInput>,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowMoveNodeInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub node_id: i32,
+    pub position: PositionInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowRemoveNodeInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub node_id: i32,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowEdgeInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub source: i32,
+    pub target: i32,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowAddBranchInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub source: i32,
+    pub target: i32,
+    pub variable: String,
+    #[graphql(description = "The type of the variable, its default is the value taking the edge")]
+    pub vtype: VTypeInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowRemoveBranchInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub source: i32,
+    pub target: i32,
+    pub variable: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowAddVariableInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub scope: VariableScope,
+    pub name: String,
+    pub vtype: VTypeInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowRenameVariableInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub name: String,
+    pub new_name: String,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowRetypeVariableInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub name: String,
+    pub vtype: VTypeInput,
+}
+
+#[derive(juniper::GraphQLInputObject)]
+pub struct XFlowRemoveVariableInput {
+    pub project_id: Uuid,
+    pub xflow_id: Uuid,
+    pub name: String,
+}
 
 // FnGroup
 #[derive(juniper::GraphQLInputObject)]
@@ -70,7 +70,7 @@ pub enum ModelLoadError {
 
 impl<T> Document<T>
 where
-    T: serde::Serialize + serde::de::DeserializeOwned + Eq + Default,
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + Default,
 {
     pub fn new(project_id: &Uuid, doctype: String) -> Self {
         Self {
@@ -1,5 +1,7 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use std::collections::{HashMap, HashSet};
 use serde_tuple::*;
+use uuid::Uuid;
 
 #[derive(GraphQLObject, Serialize_tuple, Deserialize_tuple, Debug, Clone, Eq, PartialEq)]
 pub struct Position {
@@ -7,14 +9,14 @@ pub struct Position {
     pub y: i32,
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct VariableDefinition {
     pub id: i32,
     pub name: String,
     pub vtype: VType,
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(tag = "type")]
 pub enum VType {
     #[serde(rename = "string")]
@@ -23,6 +25,22 @@ pub enum VType {
     VTypeBoolean(VTypeBoolean),
     #[serde(rename = "integer")]
     VTypeInteger(VTypeInteger),
+    #[serde(rename = "float")]
+    VTypeFloat(VTypeFloat),
+    #[serde(rename = "decimal")]
+    VTypeDecimal(VTypeDecimal),
+    #[serde(rename = "date")]
+    VTypeDate(VTypeDate),
+    #[serde(rename = "datetime")]
+    VTypeDateTime(VTypeDateTime),
+    #[serde(rename = "uuid")]
+    VTypeUuid(VTypeUuid),
+    #[serde(rename = "json")]
+    VTypeJson(VTypeJson),
+    #[serde(rename = "enum")]
+    VTypeEnum(VTypeEnum),
+    #[serde(rename = "list")]
+    VTypeList(VTypeList),
 }
 
 graphql_union!(VType: () where Scalar = <S> |&self| {
@@ -30,14 +48,30 @@ graphql_union!(VType: () where Scalar = <S> |&self| {
         &VTypeString => match *self { VType::VTypeString(ref h) => Some(h), _ => None },
         &VTypeBoolean => match *self { VType::VTypeBoolean(ref h) => Some(h), _ => None },
         &VTypeInteger => match *self { VType::VTypeInteger(ref h) => Some(h), _ => None },
+        &VTypeFloat => match *self { VType::VTypeFloat(ref h) => Some(h), _ => None },
+        &VTypeDecimal => match *self { VType::VTypeDecimal(ref h) => Some(h), _ => None },
+        &VTypeDate => match *self { VType::VTypeDate(ref h) => Some(h), _ => None },
+        &VTypeDateTime => match *self { VType::VTypeDateTime(ref h) => Some(h), _ => None },
+        &VTypeUuid => match *self { VType::VTypeUuid(ref h) => Some(h), _ => None },
+        &VTypeJson => match *self { VType::VTypeJson(ref h) => Some(h), _ => None },
+        &VTypeEnum => match *self { VType::VTypeEnum(ref h) => Some(h), _ => None },
+        &VTypeList => match *self { VType::VTypeList(ref h) => Some(h), _ => None },
     }
 });
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum VTypeContainer {
     VTypeStringContainer(VTypeStringContainer),
     VTypeBooleanContainer(VTypeBooleanContainer),
     VTypeIntegerContainer(VTypeIntegerContainer),
+    VTypeFloatContainer(VTypeFloatContainer),
+    VTypeDecimalContainer(VTypeDecimalContainer),
+    VTypeDateContainer(VTypeDateContainer),
+    VTypeDateTimeContainer(VTypeDateTimeContainer),
+    VTypeUuidContainer(VTypeUuidContainer),
+    VTypeJsonContainer(VTypeJsonContainer),
+    VTypeEnumContainer(VTypeEnumContainer),
+    VTypeListContainer(VTypeListContainer),
 }
 
 graphql_union!(VTypeContainer: () where Scalar = <S> |&self| {
@@ -45,6 +79,14 @@ graphql_union!(VTypeContainer: () where Scalar = <S> |&self| {
         &VTypeStringContainer => match *self { VTypeContainer::VTypeStringContainer(ref h) => Some(h), _ => None },
         &VTypeBooleanContainer => match *self { VTypeContainer::VTypeBooleanContainer(ref h) => Some(h), _ => None },
         &VTypeIntegerContainer => match *self { VTypeContainer::VTypeIntegerContainer(ref h) => Some(h), _ => None },
+        &VTypeFloatContainer => match *self { VTypeContainer::VTypeFloatContainer(ref h) => Some(h), _ => None },
+        &VTypeDecimalContainer => match *self { VTypeContainer::VTypeDecimalContainer(ref h) => Some(h), _ => None },
+        &VTypeDateContainer => match *self { VTypeContainer::VTypeDateContainer(ref h) => Some(h), _ => None },
+        &VTypeDateTimeContainer => match *self { VTypeContainer::VTypeDateTimeContainer(ref h) => Some(h), _ => None },
+        &VTypeUuidContainer => match *self { VTypeContainer::VTypeUuidContainer(ref h) => Some(h), _ => None },
+        &VTypeJsonContainer => match *self { VTypeContainer::VTypeJsonContainer(ref h) => Some(h), _ => None },
+        &VTypeEnumContainer => match *self { VTypeContainer::VTypeEnumContainer(ref h) => Some(h), _ => None },
+        &VTypeListContainer => match *self { VTypeContainer::VTypeListContainer(ref h) => Some(h), _ => None },
     }
 });
 
@@ -81,6 +123,96 @@ pub struct VTypeIntegerContainer {
     pub value: i32,
 }
 
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VTypeFloat {
+    pub default: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VTypeFloatContainer {
+    pub value: f64,
+}
+
+/// A fixed-point number, kept as its decimal string representation so no
+/// precision is lost on the way through JSON or GraphQL
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDecimal {
+    pub default: Option<String>,
+    pub precision: Option<i32>,
+    pub scale: Option<i32>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDecimalContainer {
+    pub value: String,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDate {
+    pub default: Option<NaiveDate>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDateContainer {
+    pub value: NaiveDate,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDateTime {
+    pub default: Option<NaiveDateTime>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeDateTimeContainer {
+    pub value: NaiveDateTime,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeUuid {
+    pub default: Option<Uuid>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeUuidContainer {
+    pub value: Uuid,
+}
+
+/// An arbitrary JSON value, the default is held as JSON text
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeJson {
+    pub default: Option<String>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeJsonContainer {
+    pub value: String,
+}
+
+/// A value from an enum in the `Domain` type catalog, referenced by name
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeEnum {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct VTypeEnumContainer {
+    pub value: String,
+}
+
+/// A list of values that all share the `item` type
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VTypeList {
+    pub item: Box<VType>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VTypeListContainer {
+    pub value: Vec<VTypeContainer>,
+}
+
 pub trait VTypeVariableDefinition {
     type Container;
     fn new_instance(&self) -> Self::Container;
@@ -134,9 +266,334 @@ impl VTypeVariableDefinition for VTypeInteger {
     }
 }
 
+impl VTypeVariableDefinition for VTypeFloat {
+    type Container = VTypeFloatContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.unwrap_or(0.0),
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        let x = self.default.unwrap_or(0.0);
+        if !x.is_finite() {
+            return false;
+        }
+        if let Some(min) = self.min {
+            if min > x {
+                return false;
+            }
+        }
+        if let Some(max) = self.max {
+            if max < x {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Split a decimal string into the number of integer and fraction digits,
+/// `None` if it is not a plain decimal number
+fn decimal_digits(s: &str) -> Option<(usize, usize)> {
+    let s = s.trim_start_matches(&['-', '+'][..]);
+    let mut parts = s.splitn(2, '.');
+    let int_part = parts.next().unwrap_or("");
+    let frac_part = parts.next().unwrap_or("");
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    if !int_part.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let int_digits = int_part.trim_start_matches('0').len();
+    Some((int_digits, frac_part.len()))
+}
+
+impl VTypeVariableDefinition for VTypeDecimal {
+    type Container = VTypeDecimalContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.as_ref().unwrap_or(&"0".to_owned()).clone(),
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        let precision = self.precision.unwrap_or(i32::MAX);
+        let scale = self.scale.unwrap_or(0);
+        if precision < 1 || scale < 0 || scale > precision {
+            return false;
+        }
+        if let Some(default) = &self.default {
+            match decimal_digits(default) {
+                Some((int_digits, frac_digits)) => {
+                    if int_digits > (precision - scale) as usize || frac_digits > scale as usize {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+impl VTypeVariableDefinition for VTypeDate {
+    type Container = VTypeDateContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.unwrap_or_else(|| NaiveDate::from_ymd(1970, 1, 1)),
+        }
+    }
+}
+
+impl VTypeVariableDefinition for VTypeDateTime {
+    type Container = VTypeDateTimeContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.unwrap_or_else(|| NaiveDateTime::from_timestamp(0, 0)),
+        }
+    }
+}
+
+impl VTypeVariableDefinition for VTypeUuid {
+    type Container = VTypeUuidContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.unwrap_or_else(Uuid::nil),
+        }
+    }
+}
+
+impl VTypeVariableDefinition for VTypeJson {
+    type Container = VTypeJsonContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.as_ref().unwrap_or(&"null".to_owned()).clone(),
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        match &self.default {
+            Some(default) => serde_json::from_str::<serde_json::Value>(default).is_ok(),
+            None => true,
+        }
+    }
+}
+
+impl VTypeVariableDefinition for VTypeEnum {
+    type Container = VTypeEnumContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container {
+            value: self.default.as_ref().unwrap_or(&format!("")).clone(),
+        }
+    }
+
+    fn is_consistent(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+impl VTypeVariableDefinition for VTypeList {
+    type Container = VTypeListContainer;
+
+    fn new_instance(&self) -> Self::Container {
+        Self::Container { value: Vec::new() }
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.item.is_consistent()
+    }
+}
+
+impl VType {
+    /// Create a value container holding the default for this `VType`
+    pub fn new_instance(&self) -> VTypeContainer {
+        match self {
+            VType::VTypeString(t) => VTypeContainer::VTypeStringContainer(t.new_instance()),
+            VType::VTypeBoolean(t) => VTypeContainer::VTypeBooleanContainer(t.new_instance()),
+            VType::VTypeInteger(t) => VTypeContainer::VTypeIntegerContainer(t.new_instance()),
+            VType::VTypeFloat(t) => VTypeContainer::VTypeFloatContainer(t.new_instance()),
+            VType::VTypeDecimal(t) => VTypeContainer::VTypeDecimalContainer(t.new_instance()),
+            VType::VTypeDate(t) => VTypeContainer::VTypeDateContainer(t.new_instance()),
+            VType::VTypeDateTime(t) => VTypeContainer::VTypeDateTimeContainer(t.new_instance()),
+            VType::VTypeUuid(t) => VTypeContainer::VTypeUuidContainer(t.new_instance()),
+            VType::VTypeJson(t) => VTypeContainer::VTypeJsonContainer(t.new_instance()),
+            VType::VTypeEnum(t) => VTypeContainer::VTypeEnumContainer(t.new_instance()),
+            VType::VTypeList(t) => VTypeContainer::VTypeListContainer(t.new_instance()),
+        }
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        match self {
+            VType::VTypeString(t) => t.is_consistent(),
+            VType::VTypeBoolean(t) => t.is_consistent(),
+            VType::VTypeInteger(t) => t.is_consistent(),
+            VType::VTypeFloat(t) => t.is_consistent(),
+            VType::VTypeDecimal(t) => t.is_consistent(),
+            VType::VTypeDate(t) => t.is_consistent(),
+            VType::VTypeDateTime(t) => t.is_consistent(),
+            VType::VTypeUuid(t) => t.is_consistent(),
+            VType::VTypeJson(t) => t.is_consistent(),
+            VType::VTypeEnum(t) => t.is_consistent(),
+            VType::VTypeList(t) => t.is_consistent(),
+        }
+    }
+
+    /// The serialized name of this `VType`, as used in the `type` tag
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            VType::VTypeString(_) => "string",
+            VType::VTypeBoolean(_) => "boolean",
+            VType::VTypeInteger(_) => "integer",
+            VType::VTypeFloat(_) => "float",
+            VType::VTypeDecimal(_) => "decimal",
+            VType::VTypeDate(_) => "date",
+            VType::VTypeDateTime(_) => "datetime",
+            VType::VTypeUuid(_) => "uuid",
+            VType::VTypeJson(_) => "json",
+            VType::VTypeEnum(_) => "enum",
+            VType::VTypeList(_) => "list",
+        }
+    }
+
+    /// Read a value of this `VType` from its JSON representation, the one
+    /// `VTypeContainer` values are stored in. Values out of the bounds of
+    /// the `VType` are refused, enum values are not checked against their
+    /// enum.
+    pub fn container_from_json(
+        &self,
+        value: &serde_json::Value,
+    ) -> Result<VTypeContainer, String> {
+        use serde_json::Value;
+
+        let expected = || format!("expected a value of type {}", self.type_name());
+        let container = match (self, value) {
+            (VType::VTypeString(_), Value::String(s)) => {
+                VTypeContainer::VTypeStringContainer(VTypeStringContainer { value: s.clone() })
+            }
+            (VType::VTypeBoolean(_), Value::Bool(b)) => {
+                VTypeContainer::VTypeBooleanContainer(VTypeBooleanContainer { value: *b })
+            }
+            (VType::VTypeInteger(t), Value::Number(n)) => {
+                let x = n
+                    .as_i64()
+                    .filter(|x| *x >= i32::MIN as i64 && *x <= i32::MAX as i64)
+                    .ok_or_else(expected)? as i32;
+                if matches!(t.min, Some(min) if x < min) || matches!(t.max, Some(max) if x > max) {
+                    return Err(format!("{} is out of range", x));
+                }
+                VTypeContainer::VTypeIntegerContainer(VTypeIntegerContainer { value: x })
+            }
+            (VType::VTypeFloat(t), Value::Number(n)) => {
+                let x = n.as_f64().ok_or_else(expected)?;
+                if matches!(t.min, Some(min) if x < min) || matches!(t.max, Some(max) if x > max) {
+                    return Err(format!("{} is out of range", x));
+                }
+                VTypeContainer::VTypeFloatContainer(VTypeFloatContainer { value: x })
+            }
+            (VType::VTypeDecimal(t), Value::String(s)) => {
+                let (int_digits, frac_digits) = decimal_digits(s).ok_or_else(expected)?;
+                let scale = t.scale.unwrap_or(0);
+                let int_max = t.precision.map(|p| (p - scale) as usize);
+                if matches!(int_max, Some(max) if int_digits > max)
+                    || (t.scale.is_some() && frac_digits > scale as usize)
+                {
+                    return Err(format!("{} does not fit the precision", s));
+                }
+                VTypeContainer::VTypeDecimalContainer(VTypeDecimalContainer { value: s.clone() })
+            }
+            (VType::VTypeDate(_), Value::String(s)) => {
+                let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| expected())?;
+                VTypeContainer::VTypeDateContainer(VTypeDateContainer { value: date })
+            }
+            (VType::VTypeDateTime(_), Value::String(s)) => {
+                let datetime = chrono::DateTime::parse_from_rfc3339(s)
+                    .map(|dt| dt.naive_utc())
+                    .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+                    .map_err(|_| expected())?;
+                VTypeContainer::VTypeDateTimeContainer(VTypeDateTimeContainer { value: datetime })
+            }
+            (VType::VTypeUuid(_), Value::String(s)) => {
+                let uuid = Uuid::parse_str(s).map_err(|_| expected())?;
+                VTypeContainer::VTypeUuidContainer(VTypeUuidContainer { value: uuid })
+            }
+            (VType::VTypeJson(_), value) => {
+                VTypeContainer::VTypeJsonContainer(VTypeJsonContainer {
+                    value: value.to_string(),
+                })
+            }
+            (VType::VTypeEnum(_), Value::String(s)) => {
+                VTypeContainer::VTypeEnumContainer(VTypeEnumContainer { value: s.clone() })
+            }
+            (VType::VTypeList(t), Value::Array(items)) => {
+                let value = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        t.item
+                            .container_from_json(item)
+                            .map_err(|err| format!("item {}: {}", i, err))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                VTypeContainer::VTypeListContainer(VTypeListContainer { value })
+            }
+            _ => return Err(expected()),
+        };
+        Ok(container)
+    }
+}
+
+impl VTypeContainer {
+    /// The JSON representation of the value, the one
+    /// `VType::container_from_json` reads. JSON values that do not parse
+    /// are kept as strings.
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value;
+
+        match self {
+            VTypeContainer::VTypeStringContainer(c) => Value::from(c.value.clone()),
+            VTypeContainer::VTypeBooleanContainer(c) => Value::from(c.value),
+            VTypeContainer::VTypeIntegerContainer(c) => Value::from(c.value),
+            VTypeContainer::VTypeFloatContainer(c) => Value::from(c.value),
+            VTypeContainer::VTypeDecimalContainer(c) => Value::from(c.value.clone()),
+            VTypeContainer::VTypeDateContainer(c) => {
+                Value::from(c.value.format("%Y-%m-%d").to_string())
+            }
+            VTypeContainer::VTypeDateTimeContainer(c) => {
+                Value::from(c.value.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            }
+            VTypeContainer::VTypeUuidContainer(c) => Value::from(c.value.to_string()),
+            VTypeContainer::VTypeJsonContainer(c) => {
+                serde_json::from_str(&c.value).unwrap_or_else(|_| Value::from(c.value.clone()))
+            }
+            VTypeContainer::VTypeEnumContainer(c) => Value::from(c.value.clone()),
+            VTypeContainer::VTypeListContainer(c) => {
+                Value::Array(c.value.iter().map(VTypeContainer::to_json).collect())
+            }
+        }
+    }
+}
+
 pub type VariableDefinitions = Vec<VariableDefinition>;
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+/// Where a variable of a flow lives: given to the flow, kept while it
+/// runs or given back by it
+#[derive(GraphQLEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableScope {
+    Input,
+    Local,
+    Output,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct DocumentVariables {
     pub input: VariableDefinitions,
     pub local: VariableDefinitions,
@@ -154,45 +611,57 @@ impl Default for DocumentVariables {
 }
 
 impl DocumentVariables {
+    /// Get the variable `name`, looked up among the input, local and output
+    /// variables in that order
+    pub fn get_variable(&self, name: &str) -> Option<&VariableDefinition> {
+        self.iter().find(|xvar| xvar.name == name)
+    }
+
+    /// Get a `HashSet` of all variable ids in input, local and output
     pub fn get_all_ids(&self) -> HashSet<i32> {
-        let mut ids = HashSet::<i32>::new();
-
-        let _ = self.input.iter().map(|p| {
-            ids.insert(p.id)
-        });
-
-        unimplemented!();
-
-        ids
-    }
-
-//    /// Get a `HashSet` of all variable names in input, local and output
-//    ///
-//    /// # Example
-//    /// ```
-//    /// use gears::structure::xflow::{XFlow};
-//    /// let xfs = XFlow::default();
-//    /// let names = xfs.get_all_variable_names();
-//    /// assert_eq!(names.len(), 0);
-//    /// ```
-//    pub fn all_variable_names(&self) -> HashSet<String> {
-//        let mut vars = HashSet::<String>::new();
-//
-//        for xvar in &self.variables.input {
-//            vars.insert(xvar.name.clone());
-//        }
-//
-//        for xvar in &self.variables.local {
-//            vars.insert(xvar.name.clone());
-//        }
-//
-//        for xvar in &self.variables.output {
-//            vars.insert(xvar.name.clone());
-//        }
-//
-//        vars
-//    }
+        self.iter().map(|xvar| xvar.id).collect()
+    }
 
+    /// Get a `HashSet` of all variable names in input, local and output
+    pub fn all_variable_names(&self) -> HashSet<String> {
+        self.iter().map(|xvar| xvar.name.clone()).collect()
+    }
+
+    /// The next free variable id
+    pub fn next_id(&self) -> i32 {
+        self.iter().map(|xvar| xvar.id).max().unwrap_or(0) + 1
+    }
+
+    pub fn scope_mut(&mut self, scope: VariableScope) -> &mut VariableDefinitions {
+        match scope {
+            VariableScope::Input => &mut self.input,
+            VariableScope::Local => &mut self.local,
+            VariableScope::Output => &mut self.output,
+        }
+    }
+
+    pub fn get_variable_mut(&mut self, name: &str) -> Option<&mut VariableDefinition> {
+        self.input
+            .iter_mut()
+            .chain(&mut self.local)
+            .chain(&mut self.output)
+            .find(|xvar| xvar.name == name)
+    }
+
+    /// Remove the variable `name` from whichever scope it is in
+    pub fn remove_variable(&mut self, name: &str) -> Option<VariableDefinition> {
+        for scope in &mut [&mut self.input, &mut self.local, &mut self.output] {
+            if let Some(position) = scope.iter().position(|xvar| xvar.name == name) {
+                return Some(scope.remove(position));
+            }
+        }
+        None
+    }
+
+    /// All variables, input first, then local and output
+    pub fn iter(&self) -> impl Iterator<Item = &VariableDefinition> {
+        self.input.iter().chain(&self.local).chain(&self.output)
+    }
 }
 
 mod test {
@@ -230,4 +699,148 @@ mod test {
         let vval = vtype.new_instance();
         assert_eq!(vval.value, 200);
     }
+
+    #[test]
+    fn test_vtype_float() {
+        use super::VTypeVariableDefinition;
+        let vtype = super::VTypeFloat {
+            default: Some(1.5),
+            min: Some(0.0),
+            max: Some(10.0),
+        };
+
+        assert_eq!(vtype.new_instance().value, 1.5);
+        assert!(vtype.is_consistent());
+
+        let vtype = super::VTypeFloat {
+            default: Some(11.0),
+            min: None,
+            max: Some(10.0),
+        };
+        assert!(!vtype.is_consistent());
+    }
+
+    #[test]
+    fn test_vtype_decimal() {
+        use super::VTypeVariableDefinition;
+        let vtype = super::VTypeDecimal {
+            default: Some("123.45".to_string()),
+            precision: Some(5),
+            scale: Some(2),
+        };
+
+        assert_eq!(vtype.new_instance().value, "123.45");
+        assert!(vtype.is_consistent());
+
+        let too_wide = super::VTypeDecimal {
+            default: Some("1234.5".to_string()),
+            precision: Some(5),
+            scale: Some(2),
+        };
+        assert!(!too_wide.is_consistent());
+
+        let bad_scale = super::VTypeDecimal {
+            default: None,
+            precision: Some(2),
+            scale: Some(3),
+        };
+        assert!(!bad_scale.is_consistent());
+
+        let not_a_number = super::VTypeDecimal {
+            default: Some("12a".to_string()),
+            precision: None,
+            scale: None,
+        };
+        assert!(!not_a_number.is_consistent());
+    }
+
+    #[test]
+    fn test_vtype_json() {
+        use super::VTypeVariableDefinition;
+        let vtype = super::VTypeJson {
+            default: Some("{\"a\": [1, 2]}".to_string()),
+        };
+        assert!(vtype.is_consistent());
+
+        let vtype = super::VTypeJson {
+            default: Some("{".to_string()),
+        };
+        assert!(!vtype.is_consistent());
+    }
+
+    #[test]
+    fn test_vtype_serialization() {
+        let json = r#"{"type": "datetime", "default": "2020-01-24T10:45:47"}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        assert_eq!(vtype.type_name(), "datetime");
+        match vtype.new_instance() {
+            super::VTypeContainer::VTypeDateTimeContainer(c) => {
+                assert_eq!(c.value.to_string(), "2020-01-24 10:45:47")
+            }
+            _ => panic!("Expected a datetime container"),
+        }
+    }
+
+    #[test]
+    fn test_vtype_list_serialization() {
+        let json = r#"{"type": "list", "item": {"type": "enum", "name": "status"}}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        match &vtype {
+            super::VType::VTypeList(list) => assert_eq!(list.item.type_name(), "enum"),
+            _ => panic!("Expected a list type"),
+        }
+        assert!(vtype.is_consistent());
+        assert_eq!(
+            vtype.new_instance(),
+            super::VTypeContainer::VTypeListContainer(super::VTypeListContainer { value: vec![] })
+        );
+    }
+
+    #[test]
+    fn test_vtype_container_from_json() {
+        let json = r#"{"type": "list", "item": {"type": "integer", "min": 1, "default": 1}}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        let container = vtype.container_from_json(&serde_json::json!([1, 2])).unwrap();
+        match container {
+            super::VTypeContainer::VTypeListContainer(c) => assert_eq!(c.value.len(), 2),
+            _ => panic!("Expected a list container"),
+        }
+        assert_eq!(
+            vtype.container_from_json(&serde_json::json!([1, 0])),
+            Err("item 1: 0 is out of range".to_string())
+        );
+        assert_eq!(
+            vtype.container_from_json(&serde_json::json!([1, "2"])),
+            Err("item 1: expected a value of type integer".to_string())
+        );
+
+        let json = r#"{"type": "datetime"}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        match vtype.container_from_json(&serde_json::json!("2020-01-24T10:45:47+01:00")) {
+            Ok(super::VTypeContainer::VTypeDateTimeContainer(c)) => {
+                assert_eq!(c.value.to_string(), "2020-01-24 09:45:47")
+            }
+            _ => panic!("Expected a datetime container"),
+        }
+
+        let json = r#"{"type": "decimal", "precision": 4, "scale": 2}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        assert!(vtype.container_from_json(&serde_json::json!("12.34")).is_ok());
+        assert!(vtype.container_from_json(&serde_json::json!("123.4")).is_err());
+    }
+
+    #[test]
+    fn test_vtype_container_to_json() {
+        let json = r#"{"type": "list", "item": {"type": "date"}}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        let value = serde_json::json!(["2020-02-29", "2020-03-01"]);
+        let container = vtype.container_from_json(&value).unwrap();
+        assert_eq!(container.to_json(), value);
+
+        let json = r#"{"type": "json"}"#;
+        let vtype: super::VType = serde_json::from_str(json).unwrap();
+        let value = serde_json::json!({"a": [1, null]});
+        let container = vtype.container_from_json(&value).unwrap();
+        assert_eq!(container.to_json(), value);
+    }
 }
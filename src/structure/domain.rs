@@ -2,7 +2,11 @@
 use crate as root;
 use super::common::{Document, DocumentReference};
 use super::data::*;
+use super::domain_index::{DomainIndex, DomainIndexCache, ElementPosition};
+use crate::engine::computed;
 use crate::messages::AddStringAttributeToEntity;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt;
 // use uuid::Uuid;
@@ -13,22 +17,43 @@ root::gears_doc!(Domain, DomainDocument, domain);
 #[derive(Debug, PartialEq)]
 pub enum DomainError {
     EntityDoesNotExist(i32),
+    EntityNameDoesNotExist(String),
     AttributeDoesNotExist(i32, i32),
     ReferenceDoesNotExist(i32, i32),
     EntityAlreadyExists(String),
+    EntityIsReferenced(i32, Vec<i32>),
     AttributeAlreadyExists(i32, String),
     ReferenceAlreadyExists(i32, String),
+    InvalidReference(i32, String),
+    TypeDoesNotExist(String),
+    TypeAlreadyExists(String),
+    TypeInUse(String),
+    InconsistentVType(String),
+    InvalidConstraint(i32, String),
+    InvalidStorage(i32, String),
 }
 
 impl fmt::Display for DomainError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             DomainError::EntityDoesNotExist(e) => write!(f, "id:{}", e),
+            DomainError::EntityNameDoesNotExist(e) => write!(f, "{}", e),
             DomainError::AttributeDoesNotExist(e, a) => write!(f, "id:{}/id:{}", e, a),
             DomainError::ReferenceDoesNotExist(e, r) => write!(f, "id:{}/id:{}", e, r),
             DomainError::EntityAlreadyExists(e) => write!(f, "{}", e),
+            DomainError::EntityIsReferenced(e, refs) => {
+                let refs: Vec<String> = refs.iter().map(|r| format!("id:{}", r)).collect();
+                write!(f, "id:{} is referenced by {}", e, refs.join(", "))
+            }
             DomainError::AttributeAlreadyExists(e, a) => write!(f, "id:{}/{}", e, a),
             DomainError::ReferenceAlreadyExists(e, r) => write!(f, "id:{}/{}", e, r),
+            DomainError::InvalidReference(e, r) => write!(f, "id:{}/{} is invalid", e, r),
+            DomainError::TypeDoesNotExist(t) => write!(f, "type:{}", t),
+            DomainError::TypeAlreadyExists(t) => write!(f, "type:{}", t),
+            DomainError::TypeInUse(t) => write!(f, "type:{} is in use", t),
+            DomainError::InconsistentVType(t) => write!(f, "inconsistent vtype:{}", t),
+            DomainError::InvalidConstraint(a, c) => write!(f, "id:{} invalid constraint:{}", a, c),
+            DomainError::InvalidStorage(a, s) => write!(f, "id:{} invalid storage:{}", a, s),
         }
     }
 }
@@ -40,6 +65,56 @@ impl error::Error for DomainError {
     }
 }
 
+#[derive(GraphQLEnum, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+}
+
+#[derive(GraphQLEnum, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum DomainDiagnosticCode {
+    DuplicateEntityName,
+    DuplicateAttributeName,
+    DuplicateReferenceName,
+    DuplicateId,
+    MissingReferenceTarget,
+    MissingJoinEntity,
+    InconsistentVType,
+    MissingType,
+    InvalidDocumentReference,
+    MissingXFlow,
+    InvalidConstraint,
+    InvalidStorage,
+}
+
+/// A single problem found by `Domain::validate`, `entity_id` and
+/// `element_id` point at the offending entity and attribute or reference
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct DomainDiagnostic {
+    pub level: DiagnosticLevel,
+    pub code: DomainDiagnosticCode,
+    pub message: String,
+    pub entity_id: Option<i32>,
+    pub element_id: Option<i32>,
+}
+
+impl DomainDiagnostic {
+    fn error(
+        code: DomainDiagnosticCode,
+        message: String,
+        entity_id: Option<i32>,
+        element_id: Option<i32>,
+    ) -> Self {
+        DomainDiagnostic {
+            level: DiagnosticLevel::Error,
+            code,
+            message,
+            entity_id,
+            element_id,
+        }
+    }
+}
+
 #[juniper::object]
 impl DomainDocument {
     fn id(&self) -> &Uuid {
@@ -66,10 +141,28 @@ impl DomainDocument {
     }
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Domain {
     pub events: Events,
+    #[serde(default)]
+    pub types: TypeCatalog,
     pub entities: Entities,
+    #[serde(skip)]
+    #[graphql(skip)]
+    index: DomainIndexCache,
+}
+
+/// Reusable type definitions that attributes can refer to by name
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct TypeCatalog {
+    pub enums: Vec<EnumDefinition>,
+}
+
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct EnumDefinition {
+    pub id: i32,
+    pub name: String,
+    pub values: Vec<String>,
 }
 
 #[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
@@ -92,20 +185,232 @@ pub struct Validation {
     pub xflow: DocumentReference,
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Attribute {
     pub id: i32,
     pub name: String,
     pub vtype: VType,
+    #[serde(default)]
+    pub storage: AttributeStorage,
+    #[serde(default)]
+    pub constraints: AttributeConstraints,
     pub validations: Vec<Validation>,
 }
 
+/// How the value of an `Attribute` comes about. Only persistent attributes
+/// are stored, transient ones live for the duration of an operation and
+/// computed ones are derived from `expression` whenever a record is read.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum AttributeStorage {
+    #[serde(rename = "persistent")]
+    Persistent,
+    #[serde(rename = "transient")]
+    Transient,
+    #[serde(rename = "computed")]
+    Computed { expression: String },
+}
+
+#[derive(GraphQLEnum, Debug, Eq, PartialEq, Clone, Copy)]
+pub enum AttributeStorageMode {
+    Persistent,
+    Transient,
+    Computed,
+}
+
+impl Default for AttributeStorage {
+    fn default() -> Self {
+        AttributeStorage::Persistent
+    }
+}
+
+#[juniper::object(Scalar = S)]
+impl<S> AttributeStorage
+where
+    S: juniper::ScalarValue,
+{
+    fn mode(&self) -> AttributeStorageMode {
+        match self {
+            AttributeStorage::Persistent => AttributeStorageMode::Persistent,
+            AttributeStorage::Transient => AttributeStorageMode::Transient,
+            AttributeStorage::Computed { .. } => AttributeStorageMode::Computed,
+        }
+    }
+
+    fn expression(&self) -> Option<&str> {
+        match self {
+            AttributeStorage::Computed { expression } => Some(expression),
+            _ => None,
+        }
+    }
+}
+
+impl AttributeStorage {
+    pub fn is_stored(&self) -> bool {
+        *self == AttributeStorage::Persistent
+    }
+
+    pub fn is_computed(&self) -> bool {
+        matches!(self, AttributeStorage::Computed { .. })
+    }
+
+    /// Check that the storage mode can be combined with `constraints`
+    pub fn is_consistent_with(&self, constraints: &AttributeConstraints) -> Result<(), String> {
+        match self {
+            AttributeStorage::Computed { expression } if expression.trim().is_empty() => {
+                Err("a computed attribute needs an expression".to_owned())
+            }
+            AttributeStorage::Persistent => Ok(()),
+            _ if constraints.unique || constraints.indexed => {
+                Err("only persistent attributes can be unique or indexed".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Declarative constraints on the values of an `Attribute`. Lengths apply to
+/// the characters of a string or the items of a list, `pattern` is a regular
+/// expression a string value has to match.
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct AttributeConstraints {
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub unique: bool,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConstraintViolation {
+    Required,
+    TooShort(i32),
+    TooLong(i32),
+    PatternMismatch(String),
+    InvalidPattern(String),
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstraintViolation::Required => write!(f, "a value is required"),
+            ConstraintViolation::TooShort(l) => write!(f, "shorter than {}", l),
+            ConstraintViolation::TooLong(l) => write!(f, "longer than {}", l),
+            ConstraintViolation::PatternMismatch(p) => write!(f, "does not match {}", p),
+            ConstraintViolation::InvalidPattern(p) => write!(f, "invalid pattern {}", p),
+        }
+    }
+}
+
+impl error::Error for ConstraintViolation {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl AttributeConstraints {
+    /// Check a value against the constraints. `unique` and `indexed` are
+    /// properties of the stored collection and are not checked here.
+    pub fn check(&self, value: Option<&VTypeContainer>) -> Result<(), ConstraintViolation> {
+        let value = match value {
+            Some(value) => value,
+            None if self.required => return Err(ConstraintViolation::Required),
+            None => return Ok(()),
+        };
+
+        let length = match value {
+            VTypeContainer::VTypeStringContainer(c) => Some(c.value.chars().count()),
+            VTypeContainer::VTypeListContainer(c) => Some(c.value.len()),
+            _ => None,
+        };
+        if let Some(length) = length {
+            if let Some(min) = self.min_length {
+                if (length as i64) < min as i64 {
+                    return Err(ConstraintViolation::TooShort(min));
+                }
+            }
+            if let Some(max) = self.max_length {
+                if (length as i64) > max as i64 {
+                    return Err(ConstraintViolation::TooLong(max));
+                }
+            }
+        }
+
+        if let (Some(pattern), VTypeContainer::VTypeStringContainer(c)) = (&self.pattern, value) {
+            let re = Regex::new(pattern)
+                .map_err(|_| ConstraintViolation::InvalidPattern(pattern.clone()))?;
+            if !re.is_match(&c.value) {
+                return Err(ConstraintViolation::PatternMismatch(pattern.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the constraints make sense for values of `vtype`
+    pub fn is_consistent_with(&self, vtype: &VType) -> Result<(), String> {
+        let has_length = matches!(vtype, VType::VTypeString(_) | VType::VTypeList(_));
+        if (self.min_length.is_some() || self.max_length.is_some()) && !has_length {
+            return Err(format!("a {} has no length", vtype.type_name()));
+        }
+        if self.min_length.unwrap_or(0) < 0 || self.max_length.unwrap_or(0) < 0 {
+            return Err("lengths can not be negative".to_owned());
+        }
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length) {
+            if min > max {
+                return Err(format!("min_length {} is above max_length {}", min, max));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            match vtype {
+                VType::VTypeString(_) => {}
+                _ => return Err(format!("a {} can not match a pattern", vtype.type_name())),
+            }
+            if Regex::new(pattern).is_err() {
+                return Err(format!("invalid pattern {}", pattern));
+            }
+        }
+        if let VType::VTypeString(t) = vtype {
+            if let Some(default) = &t.default {
+                let value = VTypeContainer::VTypeStringContainer(VTypeStringContainer {
+                    value: default.clone(),
+                });
+                if let Err(err) = self.check(Some(&value)) {
+                    return Err(format!("default value {}", err));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(GraphQLEnum, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 pub enum ReferenceType {
+    #[serde(rename = "has_one")]
+    HasOne,
     #[serde(rename = "has_many")]
     HasMany,
     #[serde(rename = "belongs_to")]
     BelongsTo,
+    #[serde(rename = "many_to_many")]
+    ManyToMany,
+}
+
+impl ReferenceType {
+    /// The `ReferenceType` seen from the other side of a reference
+    pub fn inverse(&self) -> ReferenceType {
+        match self {
+            ReferenceType::HasOne => ReferenceType::BelongsTo,
+            ReferenceType::HasMany => ReferenceType::BelongsTo,
+            ReferenceType::BelongsTo => ReferenceType::HasMany,
+            ReferenceType::ManyToMany => ReferenceType::ManyToMany,
+        }
+    }
 }
 
 #[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
@@ -114,9 +419,12 @@ pub struct Reference {
     pub name: String,
     pub reftype: ReferenceType,
     pub other: String,
+    /// The join entity of a `many_to_many` reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub through: Option<String>,
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Entity {
     pub id: i32,
     pub name: String,
@@ -136,66 +444,176 @@ impl Default for Events {
     }
 }
 
+impl TypeCatalog {
+    pub fn get_enum(&self, name: &str) -> Option<&EnumDefinition> {
+        self.enums.iter().find(|e| e.name.eq(name))
+    }
+}
+
 impl Attribute {
     pub fn new(id: i32, name: &str, vtype: VType) -> Self {
         Attribute {
             id: id,
             name: name.to_string(),
             vtype: vtype,
+            storage: AttributeStorage::default(),
+            constraints: AttributeConstraints::default(),
             validations: Validations::new(),
         }
     }
+
+    /// Check a value for this attribute against its `constraints`
+    pub fn check_value(&self, value: Option<&VTypeContainer>) -> Result<(), ConstraintViolation> {
+        self.constraints.check(value)
+    }
 }
 
 impl Domain {
-    pub fn next_id(&self) -> i32 {
-        let mut ids = Vec::<i32>::new();
-        ids.append(&mut self.entities.iter().map(|e| e.id).collect());
-        for entity in &self.entities {
-            ids.append(&mut entity.attributes.iter().map(|e| e.id).collect());
-            ids.append(&mut entity.references.iter().map(|e| e.id).collect());
+    /// The index of this domain, built when it is missing or does not fit
+    /// and with its stale entities indexed again
+    fn index(&self) -> std::sync::MutexGuard<'_, Option<DomainIndex>> {
+        let mut index = self.index.lock();
+        match index.as_mut() {
+            Some(found) if found.fits(self) => found.refresh(self),
+            _ => *index = Some(DomainIndex::build(self)),
         }
-        let id = ids.iter().max().unwrap_or(&0).to_owned() + 1;
-        id
+        index
     }
 
-    pub fn has_entity_name(&mut self, name: &str) -> bool {
-        self.get_entity_name(name).is_ok()
+    /// Drop the index of this domain, to be rebuilt on the next lookup.
+    /// Needed after renaming entities or changing ids through the fields of
+    /// the domain.
+    pub fn reindex(&self) {
+        *self.index.lock() = None;
     }
 
-    pub fn has_entity(&mut self, id: i32) -> bool {
-        let res: Vec<&Entity> = self.entities.iter().filter(|&e| e.id.eq(&id)).collect();
-        res.len() == 1
+    /// Bring the index, if there is one, up to date with a change
+    fn update_index(&self, update: impl FnOnce(&mut DomainIndex)) {
+        if let Some(index) = self.index.lock().as_mut() {
+            update(index);
+        }
     }
 
-    pub fn get_entity(&mut self, id: i32) -> Result<&Entity, DomainError> {
-        let res: Vec<&Entity> = self.entities.iter().filter({ |e| e.id.eq(&id) }).collect();
-        if res.len() == 1 {
-            Ok(&res[0])
-        } else {
-            Err(DomainError::EntityDoesNotExist(id))
+    /// Look up a position in the index, and check it against the domain.
+    /// A position that does not check out is looked up again in a rebuilt
+    /// index.
+    fn lookup<T>(
+        &self,
+        find: impl Fn(&DomainIndex) -> Option<T>,
+        check: impl Fn(&T) -> bool,
+    ) -> Option<T> {
+        let found = find(self.index().as_ref().unwrap());
+        if !matches!(&found, Some(found) if !check(found)) {
+            return found;
         }
+        self.reindex();
+        let index = self.index();
+        find(index.as_ref().unwrap()).filter(|found| check(found))
     }
 
-    pub fn get_entity_mut(&mut self, id: i32) -> Result<&mut Entity, DomainError> {
-        let index = self.entities.iter().position({ |e| e.id.eq(&id) });
+    fn entity_position(&self, id: i32) -> Option<usize> {
+        self.lookup(
+            |index| index.entities.get(&id).copied(),
+            |&position| self.entities.get(position).map(|e| e.id) == Some(id),
+        )
+    }
 
-        match index {
-            Some(idx) => Ok(self.entities.get_mut(idx).unwrap()),
+    fn entity_name_position(&self, name: &str) -> Option<usize> {
+        self.lookup(
+            |index| index.names.get(name).copied(),
+            |&position| self.entities.get(position).map(|e| e.name.as_str()) == Some(name),
+        )
+    }
+
+    fn element_position(&self, id: i32) -> Option<ElementPosition> {
+        self.lookup(
+            |index| index.elements.get(&id).copied(),
+            |position| {
+                let found = match *position {
+                    ElementPosition::Attribute(e, a) => self
+                        .entities
+                        .get(e)
+                        .and_then(|e| e.attributes.get(a))
+                        .map(|a| a.id),
+                    ElementPosition::Reference(e, r) => self
+                        .entities
+                        .get(e)
+                        .and_then(|e| e.references.get(r))
+                        .map(|r| r.id),
+                };
+                found == Some(id)
+            },
+        )
+    }
+
+    /// The next free id for an entity, enum, attribute or reference
+    pub fn next_id(&self) -> i32 {
+        self.index().as_ref().map_or(0, |i| i.max_id) + 1
+    }
+
+    pub fn has_entity_name(&self, name: &str) -> bool {
+        self.entity_name_position(name).is_some()
+    }
+
+    pub fn has_entity(&self, id: i32) -> bool {
+        self.entity_position(id).is_some()
+    }
+
+    pub fn get_entity(&self, id: i32) -> Result<&Entity, DomainError> {
+        match self.entity_position(id) {
+            Some(position) => Ok(&self.entities[position]),
             None => Err(DomainError::EntityDoesNotExist(id)),
         }
     }
 
-    pub fn get_entity_name(&mut self, name: &str) -> Result<&Entity, DomainError> {
-        let res: Vec<&Entity> = self
-            .entities
-            .iter()
-            .filter({ |e| e.name.eq(name) })
-            .collect();
-        if res.len() == 1 {
-            Ok(&res[0])
-        } else {
-            Err(DomainError::EntityDoesNotExist(0))
+    /// Like `get_entity_mut`, for changes that leave the index alone
+    fn entity_mut(&mut self, id: i32) -> Result<&mut Entity, DomainError> {
+        match self.entity_position(id) {
+            Some(position) => Ok(&mut self.entities[position]),
+            None => Err(DomainError::EntityDoesNotExist(id)),
+        }
+    }
+
+    /// Get an entity to change, it is indexed again on the next lookup
+    pub fn get_entity_mut(&mut self, id: i32) -> Result<&mut Entity, DomainError> {
+        let position = self
+            .entity_position(id)
+            .ok_or(DomainError::EntityDoesNotExist(id))?;
+        self.update_index(|index| index.stale.push(position));
+        Ok(&mut self.entities[position])
+    }
+
+    /// Index the entity at `position` again, after it changed
+    fn entity_changed(&self, position: usize) {
+        self.update_index(|index| index.refresh_entity(position, &self.entities[position]));
+    }
+
+    pub fn get_entity_name(&self, name: &str) -> Result<&Entity, DomainError> {
+        match self.entity_name_position(name) {
+            Some(position) => Ok(&self.entities[position]),
+            None => Err(DomainError::EntityDoesNotExist(0)),
+        }
+    }
+
+    /// The attribute `id`, and the entity it belongs to
+    pub fn find_attribute(&self, id: i32) -> Option<(&Entity, &Attribute)> {
+        match self.element_position(id) {
+            Some(ElementPosition::Attribute(e, a)) => {
+                let entity = &self.entities[e];
+                Some((entity, &entity.attributes[a]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The reference `id`, and the entity it belongs to
+    pub fn find_reference(&self, id: i32) -> Option<(&Entity, &Reference)> {
+        match self.element_position(id) {
+            Some(ElementPosition::Reference(e, r)) => {
+                let entity = &self.entities[e];
+                Some((entity, &entity.references[r]))
+            }
+            _ => None,
         }
     }
 
@@ -205,20 +623,159 @@ impl Domain {
         } else {
             let entity = Entity::new(self.next_id(), name);
             self.entities.push(entity.clone());
+            self.update_index(|index| index.add_entity(&entity));
             Ok(entity)
         }
     }
 
+    /// Rename an entity and rewrite every reference that targets it
+    pub fn rename_entity(&mut self, id: i32, name: &str) -> Result<Entity, DomainError> {
+        let old_name = self.get_entity(id)?.name.clone();
+        if old_name == name {
+            return Ok(self.get_entity(id)?.clone());
+        }
+        if self.has_entity_name(name) {
+            return Err(DomainError::EntityAlreadyExists(name.to_string()));
+        }
+
+        for entity in self.entities.iter_mut() {
+            for reference in entity.references.iter_mut() {
+                if reference.other == old_name {
+                    reference.other = name.to_string();
+                }
+                if reference.through.as_deref() == Some(old_name.as_str()) {
+                    reference.through = Some(name.to_string());
+                }
+            }
+        }
+
+        let position = self.entity_position(id).unwrap();
+        self.entities[position].name = name.to_string();
+        self.entity_changed(position);
+        Ok(self.entities[position].clone())
+    }
+
+    /// Ids of the references in other entities that target the entity `id`,
+    /// either directly or as the join entity of a `many_to_many` reference
+    pub fn references_to(&self, id: i32) -> Vec<i32> {
+        let name = match self.entities.iter().find(|e| e.id == id) {
+            Some(entity) => entity.name.as_str(),
+            None => return Vec::new(),
+        };
+        self.entities
+            .iter()
+            .filter(|e| e.id != id)
+            .flat_map(|e| e.references.iter())
+            .filter(|r| r.other == name || r.through.as_deref() == Some(name))
+            .map(|r| r.id)
+            .collect()
+    }
+
+    /// Remove an entity, this is refused with `EntityIsReferenced` while
+    /// other entities still refer to it
     pub fn remove_entity(&mut self, id: i32) -> Result<(), DomainError> {
-        let entities = self.entities.clone();
+        let referenced_by = self.references_to(id);
+        if !referenced_by.is_empty() {
+            return Err(DomainError::EntityIsReferenced(id, referenced_by));
+        }
+
+        match self.entity_position(id) {
+            Some(position) => {
+                self.entities.remove(position);
+                self.update_index(|index| index.remove_entity(position));
+                Ok(())
+            }
+            None => Err(DomainError::EntityDoesNotExist(id)),
+        }
+    }
+
+    /// Remove an entity together with all references that target it,
+    /// returning the references removed from other entities
+    pub fn remove_entity_cascade(&mut self, id: i32) -> Result<Vec<Reference>, DomainError> {
+        let referenced_by = self.references_to(id);
+        self.get_entity(id)?;
+
+        let mut removed = Vec::<Reference>::new();
+        let mut changed = Vec::<usize>::new();
+        for (position, entity) in self.entities.iter_mut().enumerate() {
+            let (dangling, kept): (Vec<Reference>, Vec<Reference>) = entity
+                .references
+                .drain(..)
+                .partition(|r| referenced_by.contains(&r.id));
+            entity.references = kept;
+            if !dangling.is_empty() {
+                changed.push(position);
+            }
+            removed.extend(dangling);
+        }
+        for position in changed {
+            self.entity_changed(position);
+        }
+
+        self.remove_entity(id)?;
+        Ok(removed)
+    }
 
-        let index = entities.into_iter().position({ |e| e.id.eq(&id) });
+    pub fn add_enum(&mut self, name: &str, values: &[String]) -> Result<EnumDefinition, DomainError> {
+        if self.types.get_enum(name).is_some() {
+            Err(DomainError::TypeAlreadyExists(name.to_string()))
+        } else {
+            let def = EnumDefinition {
+                id: self.next_id(),
+                name: name.to_string(),
+                values: values.to_vec(),
+            };
+            self.types.enums.push(def.clone());
+            self.update_index(|index| index.add_enum(def.id));
+            Ok(def)
+        }
+    }
+
+    pub fn remove_enum(&mut self, name: &str) -> Result<(), DomainError> {
+        let in_use = self.entities.iter().any(|e| {
+            e.attributes
+                .iter()
+                .any(|a| vtype_uses_type(&a.vtype, name))
+        });
+        if in_use {
+            return Err(DomainError::TypeInUse(name.to_string()));
+        }
+
+        let index = self.types.enums.iter().position(|e| e.name.eq(name));
         match index {
             Some(idx) => {
-                &mut self.entities.remove(idx);
+                self.types.enums.remove(idx);
+                self.update_index(|index| index.remove_enum());
                 Ok(())
             }
-            None => Err(DomainError::EntityDoesNotExist(id)),
+            None => Err(DomainError::TypeDoesNotExist(name.to_string())),
+        }
+    }
+
+    /// Check that a `VType` is consistent and that any catalog types it
+    /// refers to exist in this `Domain`
+    pub fn check_vtype(&self, vtype: &VType) -> Result<(), DomainError> {
+        match vtype {
+            VType::VTypeEnum(t) => {
+                let def = self
+                    .types
+                    .get_enum(&t.name)
+                    .ok_or_else(|| DomainError::TypeDoesNotExist(t.name.clone()))?;
+                if let Some(default) = &t.default {
+                    if !def.values.contains(default) {
+                        return Err(DomainError::InconsistentVType(t.name.clone()));
+                    }
+                }
+                Ok(())
+            }
+            VType::VTypeList(t) => self.check_vtype(&t.item),
+            _ => {
+                if vtype.is_consistent() {
+                    Ok(())
+                } else {
+                    Err(DomainError::InconsistentVType(vtype.type_name().to_string()))
+                }
+            }
         }
     }
 
@@ -227,21 +784,619 @@ impl Domain {
         entity_id: i32,
         message: &AddStringAttributeToEntity,
     ) -> Result<Attribute, DomainError> {
-        let id = self.next_id();
-        let entity = self.get_entity_mut(entity_id)?;
-
         let vtype = VTypeString {
             default: message.default.clone(),
         };
-        let attribute = Attribute {
-            id: id,
-            name: message.name.clone(),
-            vtype: VType::VTypeString(vtype),
-            validations: Validations::new(),
+        self.add_attribute(entity_id, &message.name, VType::VTypeString(vtype))
+    }
+
+    pub fn add_attribute(
+        &mut self,
+        entity_id: i32,
+        name: &str,
+        vtype: VType,
+    ) -> Result<Attribute, DomainError> {
+        self.check_vtype(&vtype)?;
+        let id = self.next_id();
+        let entity = self.entity_mut(entity_id)?;
+
+        let attribute = Attribute::new(id, name, vtype);
+        entity.add_attribute(attribute.clone())?;
+        let added = entity.attributes.len() - 1;
+        let position = self.entity_position(entity_id).unwrap();
+        self.update_index(|index| {
+            index.add_element(id, ElementPosition::Attribute(position, added))
+        });
+        Ok(attribute)
+    }
+
+    pub fn update_attribute(
+        &mut self,
+        entity_id: i32,
+        attribute_id: i32,
+        name: Option<&str>,
+        vtype: Option<VType>,
+    ) -> Result<Attribute, DomainError> {
+        if let Some(vtype) = &vtype {
+            self.check_vtype(vtype)?;
+        }
+        let entity = self.entity_mut(entity_id)?;
+
+        if let Some(name) = name {
+            if entity
+                .attributes
+                .iter()
+                .any(|a| a.name.eq(name) && a.id != attribute_id)
+            {
+                return Err(DomainError::AttributeAlreadyExists(entity_id, name.to_string()));
+            }
+        }
+
+        let attribute = entity.get_attribute_mut(attribute_id)?;
+        if let Some(vtype) = &vtype {
+            if let Err(err) = attribute.constraints.is_consistent_with(vtype) {
+                return Err(DomainError::InvalidConstraint(attribute_id, err));
+            }
+        }
+        if let Some(name) = name {
+            attribute.name = name.to_string();
+        }
+        if let Some(vtype) = vtype {
+            attribute.vtype = vtype;
+        }
+        Ok(attribute.clone())
+    }
+
+    pub fn set_attribute_constraints(
+        &mut self,
+        entity_id: i32,
+        attribute_id: i32,
+        constraints: AttributeConstraints,
+    ) -> Result<Attribute, DomainError> {
+        let attribute = self
+            .entity_mut(entity_id)?
+            .get_attribute_mut(attribute_id)?;
+        if let Err(err) = constraints.is_consistent_with(&attribute.vtype) {
+            return Err(DomainError::InvalidConstraint(attribute_id, err));
+        }
+        if let Err(err) = attribute.storage.is_consistent_with(&constraints) {
+            return Err(DomainError::InvalidConstraint(attribute_id, err));
+        }
+        attribute.constraints = constraints;
+        Ok(attribute.clone())
+    }
+
+    pub fn set_attribute_storage(
+        &mut self,
+        entity_id: i32,
+        attribute_id: i32,
+        storage: AttributeStorage,
+    ) -> Result<Attribute, DomainError> {
+        let entity = self.get_entity(entity_id)?;
+        if let Some(attribute) = entity.attributes.iter().find(|a| a.id == attribute_id) {
+            let changed = Attribute {
+                storage: storage.clone(),
+                ..attribute.clone()
+            };
+            if let Err(err) = check_storage(entity, &changed) {
+                return Err(DomainError::InvalidStorage(attribute_id, err));
+            }
+        }
+        let attribute = self
+            .entity_mut(entity_id)?
+            .get_attribute_mut(attribute_id)?;
+        attribute.storage = storage;
+        Ok(attribute.clone())
+    }
+
+    pub fn remove_attribute(
+        &mut self,
+        entity_id: i32,
+        attribute_id: i32,
+    ) -> Result<Attribute, DomainError> {
+        let position = self
+            .entity_position(entity_id)
+            .ok_or(DomainError::EntityDoesNotExist(entity_id))?;
+        let entity = &mut self.entities[position];
+        let index = entity.attributes.iter().position(|a| a.id.eq(&attribute_id));
+        match index {
+            Some(idx) => {
+                let removed = entity.attributes.remove(idx);
+                self.entity_changed(position);
+                Ok(removed)
+            }
+            None => Err(DomainError::AttributeDoesNotExist(entity_id, attribute_id)),
+        }
+    }
+
+    fn check_other_entity(&mut self, other: &str) -> Result<(), DomainError> {
+        if self.has_entity_name(other) {
+            Ok(())
+        } else {
+            Err(DomainError::EntityNameDoesNotExist(other.to_string()))
+        }
+    }
+
+    pub fn add_reference(
+        &mut self,
+        entity_id: i32,
+        name: &str,
+        reftype: ReferenceType,
+        other: &str,
+    ) -> Result<Reference, DomainError> {
+        self.add_reference_through(entity_id, name, reftype, other, None)
+    }
+
+    /// Add a reference, a `many_to_many` reference is joined through the
+    /// `through` entity, or through the default join entity for both sides
+    /// which is created when it does not exist yet
+    pub fn add_reference_through(
+        &mut self,
+        entity_id: i32,
+        name: &str,
+        reftype: ReferenceType,
+        other: &str,
+        through: Option<&str>,
+    ) -> Result<Reference, DomainError> {
+        self.check_other_entity(other)?;
+        let source_name = self.get_entity(entity_id)?.name.clone();
+        if self
+            .get_entity(entity_id)?
+            .references
+            .iter()
+            .any(|r| r.name.eq(name))
+        {
+            return Err(DomainError::ReferenceAlreadyExists(entity_id, name.to_string()));
+        }
+
+        let through = match (&reftype, through) {
+            (ReferenceType::ManyToMany, Some(through)) => {
+                self.check_other_entity(through)?;
+                Some(through.to_string())
+            }
+            (ReferenceType::ManyToMany, None) => {
+                Some(self.join_entity(&source_name, other)?.name)
+            }
+            (_, Some(_)) => return Err(DomainError::InvalidReference(entity_id, name.to_string())),
+            (_, None) => None,
+        };
+
+        let id = self.next_id();
+        let entity = self.entity_mut(entity_id)?;
+
+        let reference = Reference {
+            id,
+            name: name.to_string(),
+            reftype,
+            other: other.to_string(),
+            through,
+        };
+        entity.add_reference(reference.clone())?;
+        let added = entity.references.len() - 1;
+        let position = self.entity_position(entity_id).unwrap();
+        self.update_index(|index| {
+            index.add_element(id, ElementPosition::Reference(position, added))
+        });
+        Ok(reference)
+    }
+
+    /// The name of the default join entity between two entities, the same
+    /// for both sides of a `many_to_many` reference
+    pub fn join_entity_name(a: &str, b: &str) -> String {
+        if a <= b {
+            format!("{}_{}", a, b)
+        } else {
+            format!("{}_{}", b, a)
+        }
+    }
+
+    /// Get the default join entity between the entities named `a` and `b`,
+    /// creating it with a `belongs_to` reference to each side if needed
+    pub fn join_entity(&mut self, a: &str, b: &str) -> Result<Entity, DomainError> {
+        self.check_other_entity(a)?;
+        self.check_other_entity(b)?;
+
+        let name = Domain::join_entity_name(a, b);
+        if let Ok(entity) = self.get_entity_name(&name) {
+            return Ok(entity.clone());
+        }
+
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        let second_name = if first == second {
+            format!("{}_other", second)
+        } else {
+            second.to_string()
+        };
+
+        let entity = self.add_entity(&name)?;
+        self.add_reference(entity.id, first, ReferenceType::BelongsTo, first)?;
+        self.add_reference(entity.id, &second_name, ReferenceType::BelongsTo, second)?;
+        Ok(self.get_entity(entity.id)?.clone())
+    }
+
+    /// Add a reference from `entity_id` to `other`, and the matching inverse
+    /// reference from `other` back to `entity_id`. The inverse reference is
+    /// named after the source entity unless `inverse_name` is given.
+    ///
+    /// Nothing is changed if either of the references can not be added.
+    pub fn add_reference_with_inverse(
+        &mut self,
+        entity_id: i32,
+        name: &str,
+        reftype: ReferenceType,
+        other: &str,
+        through: Option<&str>,
+        inverse_name: Option<&str>,
+    ) -> Result<(Reference, Reference), DomainError> {
+        let source_name = self.get_entity(entity_id)?.name.clone();
+        let inverse_name = inverse_name.unwrap_or(&source_name).to_string();
+        let other_id = match self.get_entity_name(other) {
+            Ok(entity) => entity.id,
+            Err(_) => return Err(DomainError::EntityNameDoesNotExist(other.to_string())),
+        };
+
+        // Both references are added to a copy, so that the domain is left as
+        // it was when the second one fails
+        let mut domain = self.clone();
+        let inverse_reftype = reftype.inverse();
+        let reference = domain.add_reference_through(entity_id, name, reftype, other, through)?;
+        let inverse = domain.add_reference_through(
+            other_id,
+            &inverse_name,
+            inverse_reftype,
+            &source_name,
+            reference.through.as_deref(),
+        )?;
+        *self = domain;
+        Ok((reference, inverse))
+    }
+
+    pub fn update_reference(
+        &mut self,
+        entity_id: i32,
+        reference_id: i32,
+        name: Option<&str>,
+        reftype: Option<ReferenceType>,
+        other: Option<&str>,
+    ) -> Result<Reference, DomainError> {
+        if let Some(other) = other {
+            self.check_other_entity(other)?;
+        }
+        let entity = self.entity_mut(entity_id)?;
+
+        if let Some(name) = name {
+            if entity
+                .references
+                .iter()
+                .any(|r| r.name.eq(name) && r.id != reference_id)
+            {
+                return Err(DomainError::ReferenceAlreadyExists(entity_id, name.to_string()));
+            }
+        }
+
+        let current = entity.get_reference_mut(reference_id)?.clone();
+        let source_name = entity.name.clone();
+        let new_reftype = reftype.unwrap_or_else(|| current.reftype.clone());
+        let new_other = other.unwrap_or(&current.other).to_string();
+
+        let through = if new_reftype == ReferenceType::ManyToMany {
+            if current.through.is_some() && new_other == current.other {
+                current.through.clone()
+            } else {
+                Some(self.join_entity(&source_name, &new_other)?.name)
+            }
+        } else {
+            None
         };
-        let attr_clone = attribute.clone();
-        entity.attributes.push(attribute);
-        Ok(attr_clone)
+
+        let reference = self
+            .entity_mut(entity_id)?
+            .get_reference_mut(reference_id)?;
+        if let Some(name) = name {
+            reference.name = name.to_string();
+        }
+        reference.reftype = new_reftype;
+        reference.other = new_other;
+        reference.through = through;
+        Ok(reference.clone())
+    }
+
+    pub fn remove_reference(
+        &mut self,
+        entity_id: i32,
+        reference_id: i32,
+    ) -> Result<Reference, DomainError> {
+        let position = self
+            .entity_position(entity_id)
+            .ok_or(DomainError::EntityDoesNotExist(entity_id))?;
+        let entity = &mut self.entities[position];
+        let index = entity.references.iter().position(|r| r.id.eq(&reference_id));
+        match index {
+            Some(idx) => {
+                let removed = entity.references.remove(idx);
+                self.entity_changed(position);
+                Ok(removed)
+            }
+            None => Err(DomainError::ReferenceDoesNotExist(entity_id, reference_id)),
+        }
+    }
+}
+
+impl Domain {
+    /// Check the whole `Domain` for consistency, reporting every problem
+    /// found instead of stopping at the first one. Document references are
+    /// only checked for their doctype, use `validate_with_xflows` to also
+    /// check that the referenced xflow documents exist.
+    pub fn validate(&self) -> Vec<DomainDiagnostic> {
+        let mut diagnostics = Vec::<DomainDiagnostic>::new();
+        self.validate_names(&mut diagnostics);
+        self.validate_ids(&mut diagnostics);
+        self.validate_references(&mut diagnostics);
+        self.validate_vtypes(&mut diagnostics);
+        self.validate_constraints(&mut diagnostics);
+        self.validate_storage(&mut diagnostics);
+        for (entity_id, element_id, docref) in self.document_references() {
+            if docref.doctype != "xflow" {
+                diagnostics.push(DomainDiagnostic::error(
+                    DomainDiagnosticCode::InvalidDocumentReference,
+                    format!("Document {} is a {}, not an xflow", docref.id, docref.doctype),
+                    entity_id,
+                    element_id,
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    /// Validate the `Domain` as `validate` does, and check that every event
+    /// and validation xflow is one of `xflows`
+    pub fn validate_with_xflows(&self, xflows: &HashSet<Uuid>) -> Vec<DomainDiagnostic> {
+        let mut diagnostics = self.validate();
+        for (entity_id, element_id, docref) in self.document_references() {
+            if docref.doctype == "xflow" && !xflows.contains(&docref.id) {
+                diagnostics.push(DomainDiagnostic::error(
+                    DomainDiagnosticCode::MissingXFlow,
+                    format!("XFlow {} does not exist", docref.id),
+                    entity_id,
+                    element_id,
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn validate_names(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        let mut entity_names = HashSet::<&str>::new();
+        for entity in &self.entities {
+            if !entity_names.insert(&entity.name) {
+                diagnostics.push(DomainDiagnostic::error(
+                    DomainDiagnosticCode::DuplicateEntityName,
+                    format!("Entity name {} is used more than once", entity.name),
+                    Some(entity.id),
+                    None,
+                ));
+            }
+
+            let mut attribute_names = HashSet::<&str>::new();
+            for attribute in &entity.attributes {
+                if !attribute_names.insert(&attribute.name) {
+                    diagnostics.push(DomainDiagnostic::error(
+                        DomainDiagnosticCode::DuplicateAttributeName,
+                        format!(
+                            "Attribute name {} is used more than once in {}",
+                            attribute.name, entity.name
+                        ),
+                        Some(entity.id),
+                        Some(attribute.id),
+                    ));
+                }
+            }
+
+            let mut reference_names = HashSet::<&str>::new();
+            for reference in &entity.references {
+                if !reference_names.insert(&reference.name) {
+                    diagnostics.push(DomainDiagnostic::error(
+                        DomainDiagnosticCode::DuplicateReferenceName,
+                        format!(
+                            "Reference name {} is used more than once in {}",
+                            reference.name, entity.name
+                        ),
+                        Some(entity.id),
+                        Some(reference.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_ids(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        let mut ids = HashMap::<i32, usize>::new();
+        let mut add = |id: i32| *ids.entry(id).or_insert(0) += 1;
+        for def in &self.types.enums {
+            add(def.id);
+        }
+        for entity in &self.entities {
+            add(entity.id);
+            for attribute in &entity.attributes {
+                add(attribute.id);
+            }
+            for reference in &entity.references {
+                add(reference.id);
+            }
+        }
+
+        let mut duplicates: Vec<i32> = ids
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(id, _)| id)
+            .collect();
+        duplicates.sort();
+        for id in duplicates {
+            diagnostics.push(DomainDiagnostic::error(
+                DomainDiagnosticCode::DuplicateId,
+                format!("Id {} is used more than once", id),
+                None,
+                Some(id),
+            ));
+        }
+    }
+
+    fn validate_references(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        let entity_names: HashSet<&str> = self.entities.iter().map(|e| e.name.as_str()).collect();
+        for entity in &self.entities {
+            for reference in &entity.references {
+                if !entity_names.contains(reference.other.as_str()) {
+                    diagnostics.push(DomainDiagnostic::error(
+                        DomainDiagnosticCode::MissingReferenceTarget,
+                        format!(
+                            "Reference {}.{} points at missing entity {}",
+                            entity.name, reference.name, reference.other
+                        ),
+                        Some(entity.id),
+                        Some(reference.id),
+                    ));
+                }
+                match (&reference.reftype, &reference.through) {
+                    (ReferenceType::ManyToMany, Some(through))
+                        if !entity_names.contains(through.as_str()) =>
+                    {
+                        diagnostics.push(DomainDiagnostic::error(
+                            DomainDiagnosticCode::MissingJoinEntity,
+                            format!(
+                                "Reference {}.{} is joined through missing entity {}",
+                                entity.name, reference.name, through
+                            ),
+                            Some(entity.id),
+                            Some(reference.id),
+                        ));
+                    }
+                    (ReferenceType::ManyToMany, None) => {
+                        diagnostics.push(DomainDiagnostic::error(
+                            DomainDiagnosticCode::MissingJoinEntity,
+                            format!(
+                                "Reference {}.{} has no join entity",
+                                entity.name, reference.name
+                            ),
+                            Some(entity.id),
+                            Some(reference.id),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn validate_vtypes(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        for entity in &self.entities {
+            for attribute in &entity.attributes {
+                let (code, message) = match self.check_vtype(&attribute.vtype) {
+                    Ok(()) => continue,
+                    Err(DomainError::TypeDoesNotExist(name)) => (
+                        DomainDiagnosticCode::MissingType,
+                        format!(
+                            "Attribute {}.{} refers to missing type {}",
+                            entity.name, attribute.name, name
+                        ),
+                    ),
+                    Err(err) => (
+                        DomainDiagnosticCode::InconsistentVType,
+                        format!(
+                            "Attribute {}.{} has an inconsistent type ({})",
+                            entity.name, attribute.name, err
+                        ),
+                    ),
+                };
+                diagnostics.push(DomainDiagnostic::error(
+                    code,
+                    message,
+                    Some(entity.id),
+                    Some(attribute.id),
+                ));
+            }
+        }
+    }
+
+    fn validate_constraints(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        for entity in &self.entities {
+            for attribute in &entity.attributes {
+                if let Err(err) = attribute.constraints.is_consistent_with(&attribute.vtype) {
+                    diagnostics.push(DomainDiagnostic::error(
+                        DomainDiagnosticCode::InvalidConstraint,
+                        format!(
+                            "Attribute {}.{} has invalid constraints ({})",
+                            entity.name, attribute.name, err
+                        ),
+                        Some(entity.id),
+                        Some(attribute.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_storage(&self, diagnostics: &mut Vec<DomainDiagnostic>) {
+        for entity in &self.entities {
+            for attribute in &entity.attributes {
+                if let Err(err) = check_storage(entity, attribute) {
+                    diagnostics.push(DomainDiagnostic::error(
+                        DomainDiagnosticCode::InvalidStorage,
+                        format!(
+                            "Attribute {}.{} has an invalid storage mode ({})",
+                            entity.name, attribute.name, err
+                        ),
+                        Some(entity.id),
+                        Some(attribute.id),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// All `DocumentReference`s in the `Domain` with the entity and attribute
+    /// they belong to, events have neither
+    fn document_references(&self) -> Vec<(Option<i32>, Option<i32>, &DocumentReference)> {
+        let mut refs = Vec::<(Option<i32>, Option<i32>, &DocumentReference)>::new();
+        let events = &self.events;
+        for docref in events
+            .change
+            .iter()
+            .chain(events.update.iter())
+            .chain(events.read.iter())
+            .chain(events.delete.iter())
+            .chain(events.all.iter())
+        {
+            refs.push((None, None, docref));
+        }
+        for entity in &self.entities {
+            for attribute in &entity.attributes {
+                for validation in &attribute.validations {
+                    refs.push((Some(entity.id), Some(attribute.id), &validation.xflow));
+                }
+            }
+        }
+        refs
+    }
+}
+
+/// Check the storage mode of `attribute` of `entity`. The expression of a
+/// computed attribute can use the other persistent attributes of the entity.
+fn check_storage(entity: &Entity, attribute: &Attribute) -> Result<(), String> {
+    attribute.storage.is_consistent_with(&attribute.constraints)?;
+    if let AttributeStorage::Computed { expression } = &attribute.storage {
+        let mut variables = computed::variables(entity);
+        variables.input.retain(|v| v.id != attribute.id);
+        computed::check_expression(expression, &attribute.vtype, &variables)
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn vtype_uses_type(vtype: &VType, name: &str) -> bool {
+    match vtype {
+        VType::VTypeEnum(t) => t.name.eq(name),
+        VType::VTypeList(t) => vtype_uses_type(&t.item, name),
+        _ => false,
     }
 }
 
@@ -268,7 +1423,39 @@ impl Entity {
         }
     }
 
-    pub fn add_attribute(&mut self, attr: Attribute) -> Result<(), String> {
+    pub fn get_reference_mut(&mut self, id: i32) -> Result<&mut Reference, DomainError> {
+        let entity_id = self.id;
+        self.references
+            .iter_mut()
+            .find(|r| r.id.eq(&id))
+            .ok_or(DomainError::ReferenceDoesNotExist(entity_id, id))
+    }
+
+    pub fn add_reference(&mut self, reference: Reference) -> Result<(), DomainError> {
+        if self.references.iter().any(|r| r.name.eq(&reference.name)) {
+            return Err(DomainError::ReferenceAlreadyExists(self.id, reference.name));
+        }
+        self.references.push(reference);
+        Ok(())
+    }
+
+    /// The attributes whose values are stored with a record
+    pub fn stored_attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.attributes.iter().filter(|a| a.storage.is_stored())
+    }
+
+    pub fn get_attribute_mut(&mut self, id: i32) -> Result<&mut Attribute, DomainError> {
+        let entity_id = self.id;
+        self.attributes
+            .iter_mut()
+            .find(|a| a.id.eq(&id))
+            .ok_or(DomainError::AttributeDoesNotExist(entity_id, id))
+    }
+
+    pub fn add_attribute(&mut self, attr: Attribute) -> Result<(), DomainError> {
+        if self.attributes.iter().any(|a| a.name.eq(&attr.name)) {
+            return Err(DomainError::AttributeAlreadyExists(self.id, attr.name));
+        }
         self.attributes.push(attr);
         Ok(())
     }
@@ -278,7 +1465,9 @@ impl Default for Domain {
     fn default() -> Self {
         Domain {
             events: Events::default(),
+            types: TypeCatalog::default(),
             entities: Entities::new(),
+            index: DomainIndexCache::default(),
         }
     }
 }
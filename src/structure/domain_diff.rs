@@ -0,0 +1,212 @@
+//! Structural differences between two versions of a `Domain`
+//!
+//! Enums, entities, attributes and references are matched up by id, so a
+//! changed name shows up as a rename rather than as a removal and an
+//! addition.
+
+use super::domain::{Attribute, Domain, Entity, EnumDefinition, Reference};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DomainChange {
+    AddEnum(EnumDefinition),
+    RemoveEnum(EnumDefinition),
+    /// The name or the values of an enum changed
+    ChangeEnum {
+        from: EnumDefinition,
+        to: EnumDefinition,
+    },
+    AddEntity(Entity),
+    RemoveEntity(Entity),
+    RenameEntity {
+        from: Entity,
+        to: Entity,
+    },
+    /// Attribute and reference changes carry the name of their entity in
+    /// the newer `Domain`
+    AddAttribute {
+        entity: String,
+        attribute: Attribute,
+    },
+    RemoveAttribute {
+        entity: String,
+        attribute: Attribute,
+    },
+    RenameAttribute {
+        entity: String,
+        from: Attribute,
+        to: Attribute,
+    },
+    /// The type, storage or constraints of an attribute changed
+    RetypeAttribute {
+        entity: String,
+        from: Attribute,
+        to: Attribute,
+    },
+    AddReference {
+        entity: String,
+        reference: Reference,
+    },
+    RemoveReference {
+        entity: String,
+        reference: Reference,
+    },
+    RenameReference {
+        entity: String,
+        from: Reference,
+        to: Reference,
+    },
+    /// The kind, target or join entity of a reference changed
+    RetypeReference {
+        entity: String,
+        from: Reference,
+        to: Reference,
+    },
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DomainDiff {
+    pub changes: Vec<DomainChange>,
+}
+
+impl DomainDiff {
+    /// The changes that turn `from` into `to`
+    pub fn between(from: &Domain, to: &Domain) -> Self {
+        let mut changes = Vec::<DomainChange>::new();
+
+        for old in &from.types.enums {
+            match to.types.enums.iter().find(|e| e.id == old.id) {
+                Some(new) if new != old => changes.push(DomainChange::ChangeEnum {
+                    from: old.clone(),
+                    to: new.clone(),
+                }),
+                Some(_) => {}
+                None => changes.push(DomainChange::RemoveEnum(old.clone())),
+            }
+        }
+        for new in &to.types.enums {
+            if !from.types.enums.iter().any(|e| e.id == new.id) {
+                changes.push(DomainChange::AddEnum(new.clone()));
+            }
+        }
+
+        for old in &from.entities {
+            match to.entities.iter().find(|e| e.id == old.id) {
+                Some(new) => diff_entity(from, to, old, new, &mut changes),
+                None => changes.push(DomainChange::RemoveEntity(old.clone())),
+            }
+        }
+        for new in &to.entities {
+            if !from.entities.iter().any(|e| e.id == new.id) {
+                changes.push(DomainChange::AddEntity(new.clone()));
+            }
+        }
+
+        DomainDiff { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Whether entity name `a` in `from` and `b` in `to` are the same entity,
+/// which they are after a rename
+fn same_entity(from: &Domain, a: &str, to: &Domain, b: &str) -> bool {
+    let a_id = from.entities.iter().find(|e| e.name == a).map(|e| e.id);
+    let b_id = to.entities.iter().find(|e| e.name == b).map(|e| e.id);
+    match (a_id, b_id) {
+        (Some(a_id), Some(b_id)) => a_id == b_id,
+        _ => a == b,
+    }
+}
+
+fn diff_entity(
+    from_domain: &Domain,
+    to_domain: &Domain,
+    from: &Entity,
+    to: &Entity,
+    changes: &mut Vec<DomainChange>,
+) {
+    if from.name != to.name {
+        changes.push(DomainChange::RenameEntity {
+            from: from.clone(),
+            to: to.clone(),
+        });
+    }
+
+    for old in &from.attributes {
+        match to.attributes.iter().find(|a| a.id == old.id) {
+            Some(new) => {
+                if old.name != new.name {
+                    changes.push(DomainChange::RenameAttribute {
+                        entity: to.name.clone(),
+                        from: old.clone(),
+                        to: new.clone(),
+                    });
+                }
+                if old.vtype != new.vtype
+                    || old.storage != new.storage
+                    || old.constraints != new.constraints
+                {
+                    changes.push(DomainChange::RetypeAttribute {
+                        entity: to.name.clone(),
+                        from: old.clone(),
+                        to: new.clone(),
+                    });
+                }
+            }
+            None => changes.push(DomainChange::RemoveAttribute {
+                entity: to.name.clone(),
+                attribute: old.clone(),
+            }),
+        }
+    }
+    for new in &to.attributes {
+        if !from.attributes.iter().any(|a| a.id == new.id) {
+            changes.push(DomainChange::AddAttribute {
+                entity: to.name.clone(),
+                attribute: new.clone(),
+            });
+        }
+    }
+
+    for old in &from.references {
+        match to.references.iter().find(|r| r.id == old.id) {
+            Some(new) => {
+                if old.name != new.name {
+                    changes.push(DomainChange::RenameReference {
+                        entity: to.name.clone(),
+                        from: old.clone(),
+                        to: new.clone(),
+                    });
+                }
+                let same_through = match (&old.through, &new.through) {
+                    (Some(a), Some(b)) => same_entity(from_domain, a, to_domain, b),
+                    (a, b) => a == b,
+                };
+                if old.reftype != new.reftype
+                    || !same_entity(from_domain, &old.other, to_domain, &new.other)
+                    || !same_through
+                {
+                    changes.push(DomainChange::RetypeReference {
+                        entity: to.name.clone(),
+                        from: old.clone(),
+                        to: new.clone(),
+                    });
+                }
+            }
+            None => changes.push(DomainChange::RemoveReference {
+                entity: to.name.clone(),
+                reference: old.clone(),
+            }),
+        }
+    }
+    for new in &to.references {
+        if !from.references.iter().any(|r| r.id == new.id) {
+            changes.push(DomainChange::AddReference {
+                entity: to.name.clone(),
+                reference: new.clone(),
+            });
+        }
+    }
+}
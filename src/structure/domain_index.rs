@@ -0,0 +1,215 @@
+//! Lookup tables over the elements of a `Domain`
+//!
+//! The index is built the first time it is needed and kept up to date by
+//! the methods of `Domain` that add, rename or remove elements. An entity
+//! handed out by `Domain::get_entity_mut` is indexed again, on its own, on
+//! the next lookup. Changes made through the public fields of a `Domain`
+//! are noticed when they change the number of entities or enums, which
+//! rebuilds the index, and positions found in the index are checked against
+//! the domain before they are used. Use `Domain::reindex` after other
+//! changes through the fields, such as new ids or names.
+
+use super::domain::{Domain, Entity};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+/// Where an attribute or a reference is, by the position of its entity and
+/// its position in the entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElementPosition {
+    Attribute(usize, usize),
+    Reference(usize, usize),
+}
+
+impl ElementPosition {
+    fn entity(&self) -> usize {
+        match *self {
+            ElementPosition::Attribute(e, _) | ElementPosition::Reference(e, _) => e,
+        }
+    }
+}
+
+/// The numbers of entities and enums of a `Domain`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DomainShape {
+    pub entities: usize,
+    pub enums: usize,
+}
+
+impl DomainShape {
+    pub fn of(domain: &Domain) -> Self {
+        DomainShape {
+            entities: domain.entities.len(),
+            enums: domain.types.enums.len(),
+        }
+    }
+}
+
+/// The keys an entity was indexed under, to take it out of the index again
+#[derive(Debug, Clone, Default)]
+struct IndexedEntity {
+    id: i32,
+    name: String,
+    elements: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DomainIndex {
+    /// The shape of the domain the index is for
+    pub shape: DomainShape,
+    /// Entity ids to positions
+    pub entities: HashMap<i32, usize>,
+    /// Entity names to positions
+    pub names: HashMap<String, usize>,
+    pub elements: HashMap<i32, ElementPosition>,
+    /// The highest id of an entity, enum, attribute or reference
+    pub max_id: i32,
+    /// Positions of entities that may have changed since they were indexed
+    pub stale: Vec<usize>,
+    indexed: Vec<IndexedEntity>,
+}
+
+impl DomainIndex {
+    pub fn build(domain: &Domain) -> Self {
+        let mut index = DomainIndex {
+            shape: DomainShape::of(domain),
+            ..DomainIndex::default()
+        };
+        for def in &domain.types.enums {
+            index.max_id = index.max_id.max(def.id);
+        }
+        for (position, entity) in domain.entities.iter().enumerate() {
+            index.indexed.push(IndexedEntity::default());
+            index.index_entity(position, entity);
+        }
+        index
+    }
+
+    /// Whether the index still fits `domain`, as far as its numbers of
+    /// entities and enums tell
+    pub fn fits(&self, domain: &Domain) -> bool {
+        self.shape == DomainShape::of(domain)
+    }
+
+    /// Index the entities of `domain` that went stale
+    pub fn refresh(&mut self, domain: &Domain) {
+        for position in std::mem::take(&mut self.stale) {
+            if let Some(entity) = domain.entities.get(position) {
+                self.refresh_entity(position, entity);
+            }
+        }
+    }
+
+    fn index_entity(&mut self, position: usize, entity: &Entity) {
+        self.entities.insert(entity.id, position);
+        self.names.insert(entity.name.clone(), position);
+        self.max_id = self.max_id.max(entity.id);
+        self.indexed[position] = IndexedEntity {
+            id: entity.id,
+            name: entity.name.clone(),
+            elements: Vec::new(),
+        };
+        for (i, attribute) in entity.attributes.iter().enumerate() {
+            self.add_element(attribute.id, ElementPosition::Attribute(position, i));
+        }
+        for (i, reference) in entity.references.iter().enumerate() {
+            self.add_element(reference.id, ElementPosition::Reference(position, i));
+        }
+    }
+
+    /// Take the entity at `position` and its elements out of the index
+    fn unindex_entity(&mut self, position: usize) {
+        let old = std::mem::take(&mut self.indexed[position]);
+        if self.entities.get(&old.id) == Some(&position) {
+            self.entities.remove(&old.id);
+        }
+        if self.names.get(&old.name) == Some(&position) {
+            self.names.remove(&old.name);
+        }
+        for id in old.elements {
+            if matches!(self.elements.get(&id), Some(found) if found.entity() == position) {
+                self.elements.remove(&id);
+            }
+        }
+    }
+
+    /// Index `entity` at `position` again, after it changed
+    pub fn refresh_entity(&mut self, position: usize, entity: &Entity) {
+        self.unindex_entity(position);
+        self.index_entity(position, entity);
+    }
+
+    /// Record `entity`, added at the end of the entities
+    pub fn add_entity(&mut self, entity: &Entity) {
+        let position = self.indexed.len();
+        self.indexed.push(IndexedEntity::default());
+        self.index_entity(position, entity);
+        self.shape.entities += 1;
+    }
+
+    /// Forget the entity at `position`, the entities after it move up
+    pub fn remove_entity(&mut self, position: usize) {
+        self.unindex_entity(position);
+        self.indexed.remove(position);
+        self.stale.retain(|&stale| stale != position);
+        let moved = |p: &mut usize| {
+            if *p > position {
+                *p -= 1;
+            }
+        };
+        self.entities.values_mut().for_each(moved);
+        self.names.values_mut().for_each(moved);
+        self.stale.iter_mut().for_each(moved);
+        for element in self.elements.values_mut() {
+            match element {
+                ElementPosition::Attribute(e, _) | ElementPosition::Reference(e, _) => moved(e),
+            }
+        }
+        self.shape.entities -= 1;
+    }
+
+    pub fn add_enum(&mut self, id: i32) {
+        self.max_id = self.max_id.max(id);
+        self.shape.enums += 1;
+    }
+
+    pub fn remove_enum(&mut self) {
+        self.shape.enums -= 1;
+    }
+
+    /// Record attribute or reference `id` at `position`
+    pub fn add_element(&mut self, id: i32, position: ElementPosition) {
+        self.elements.insert(id, position);
+        self.indexed[position.entity()].elements.push(id);
+        self.max_id = self.max_id.max(id);
+    }
+}
+
+/// The index of a `Domain`, left out of its JSON and its comparisons
+#[derive(Default)]
+pub struct DomainIndexCache(Mutex<Option<DomainIndex>>);
+
+impl DomainIndexCache {
+    pub fn lock(&self) -> MutexGuard<'_, Option<DomainIndex>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Clone for DomainIndexCache {
+    fn clone(&self) -> Self {
+        DomainIndexCache(Mutex::new(self.lock().clone()))
+    }
+}
+
+impl PartialEq for DomainIndexCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for DomainIndexCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DomainIndexCache")
+    }
+}
@@ -57,7 +57,7 @@ pub enum FnLanguage {
     AssemblyScript
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct FnDefinition {
     pub id: i32,
     pub lang: FnLanguage,
@@ -70,7 +70,7 @@ pub struct FnDefinition {
 
 pub type FnDefinitions = Vec<FnDefinition>;
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Fngroup {
     fns: FnDefinitions,
 }
@@ -1,6 +1,9 @@
 pub mod common;
 pub mod data;
 pub mod domain;
+pub mod domain_diff;
+pub mod domain_index;
+pub mod record;
 pub mod xflow;
 pub mod fngroup;
 pub mod modelx;
@@ -0,0 +1,211 @@
+//! Records, the instances of an `Entity`
+//!
+//! A record is a JSON object. Persistent attributes are kept under their
+//! name, references under the name of the reference followed by `_id` for
+//! `belongs_to` and `has_one`, or `_ids` for `has_many` and `many_to_many`,
+//! holding the ids of the other records. The id of a record is kept by the
+//! store, next to the body.
+
+use super::data::{VType, VTypeContainer};
+use super::domain::{AttributeStorage, Domain, Entity, Reference, ReferenceType};
+use serde_json::Value;
+use std::fmt;
+use uuid::Uuid;
+
+/// A problem with a record, tied to the property it was found at
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordViolation {
+    /// None when the record as a whole is concerned
+    pub property: Option<String>,
+    pub message: String,
+}
+
+impl RecordViolation {
+    pub fn new(property: Option<&str>, message: &str) -> Self {
+        RecordViolation {
+            property: property.map(str::to_owned),
+            message: message.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for RecordViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.property {
+            Some(property) => write!(f, "{}: {}", property, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn is_many(reference: &Reference) -> bool {
+    match reference.reftype {
+        ReferenceType::HasMany | ReferenceType::ManyToMany => true,
+        ReferenceType::BelongsTo | ReferenceType::HasOne => false,
+    }
+}
+
+/// The property of a record holding the ids for `reference`
+pub fn reference_property(reference: &Reference) -> String {
+    if is_many(reference) {
+        format!("{}_ids", reference.name)
+    } else {
+        format!("{}_id", reference.name)
+    }
+}
+
+/// The ids of the other records a record refers to through `reference`.
+/// Ids that can not be read are left out, `check_record` reports them.
+pub fn referenced_ids(reference: &Reference, body: &Value) -> Vec<Uuid> {
+    let ids = match body.get(reference_property(reference)) {
+        Some(Value::Array(ids)) => ids.iter().collect(),
+        Some(id) => vec![id],
+        None => vec![],
+    };
+    ids.into_iter()
+        .filter_map(|id| id.as_str())
+        .filter_map(|id| Uuid::parse_str(id).ok())
+        .collect()
+}
+
+/// The pattern, for the `@>` operator of JSONB, of the record bodies that
+/// refer to record `id` through `reference`
+pub fn reference_pattern(reference: &Reference, id: &Uuid) -> Value {
+    let id = Value::from(id.to_string());
+    let ids = if is_many(reference) {
+        Value::Array(vec![id])
+    } else {
+        id
+    };
+    let mut pattern = serde_json::Map::new();
+    pattern.insert(reference_property(reference), ids);
+    Value::Object(pattern)
+}
+
+fn check_reference(reference: &Reference, value: &Value) -> Result<(), String> {
+    let is_id = |id: &Value| matches!(id.as_str().map(Uuid::parse_str), Some(Ok(_)));
+    if is_many(reference) {
+        match value {
+            Value::Array(ids) if ids.iter().all(is_id) => Ok(()),
+            _ => Err("expected a list of record ids".to_owned()),
+        }
+    } else if value.is_null() || is_id(value) {
+        Ok(())
+    } else {
+        Err("expected a record id".to_owned())
+    }
+}
+
+/// Check that the values of enums are part of their enum in the type
+/// catalog of `domain`
+fn check_enum_values(
+    domain: &Domain,
+    vtype: &VType,
+    container: &VTypeContainer,
+) -> Result<(), String> {
+    match (vtype, container) {
+        (VType::VTypeEnum(t), VTypeContainer::VTypeEnumContainer(c)) => {
+            let def = domain
+                .types
+                .enums
+                .iter()
+                .find(|def| def.name == t.name)
+                .ok_or_else(|| format!("enum {} does not exist", t.name))?;
+            if def.values.contains(&c.value) {
+                Ok(())
+            } else {
+                Err(format!("{} is not a value of enum {}", c.value, t.name))
+            }
+        }
+        (VType::VTypeList(t), VTypeContainer::VTypeListContainer(c)) => c
+            .value
+            .iter()
+            .try_for_each(|item| check_enum_values(domain, &t.item, item)),
+        _ => Ok(()),
+    }
+}
+
+/// Check the body of a record of `entity` against its attributes and
+/// references, reporting every problem found. Whether unique values are
+/// unique and referenced records exist is up to the store.
+pub fn check_record(domain: &Domain, entity: &Entity, body: &Value) -> Vec<RecordViolation> {
+    let mut violations = Vec::<RecordViolation>::new();
+    let properties = match body {
+        Value::Object(properties) => properties,
+        _ => {
+            violations.push(RecordViolation::new(None, "a record is a JSON object"));
+            return violations;
+        }
+    };
+
+    for property in properties.keys() {
+        let known = entity.attributes.iter().any(|a| &a.name == property)
+            || entity
+                .references
+                .iter()
+                .any(|r| &reference_property(r) == property);
+        if property == "id" {
+            violations.push(RecordViolation::new(
+                Some(property),
+                "the id of a record is kept by the store",
+            ));
+        } else if !known {
+            violations.push(RecordViolation::new(
+                Some(property),
+                &format!("{} has no property {}", entity.name, property),
+            ));
+        }
+    }
+
+    for attribute in &entity.attributes {
+        let name = Some(attribute.name.as_str());
+        let value = properties.get(&attribute.name).filter(|v| !v.is_null());
+        match (&attribute.storage, value) {
+            (AttributeStorage::Persistent, _) => {}
+            (AttributeStorage::Transient, Some(_)) => {
+                violations.push(RecordViolation::new(
+                    name,
+                    "transient attributes are not stored",
+                ));
+                continue;
+            }
+            (AttributeStorage::Computed { .. }, Some(_)) => {
+                violations.push(RecordViolation::new(
+                    name,
+                    "computed attributes are read only",
+                ));
+                continue;
+            }
+            (_, None) => continue,
+        }
+
+        let container = match value.map(|v| attribute.vtype.container_from_json(v)) {
+            Some(Ok(container)) => Some(container),
+            Some(Err(err)) => {
+                violations.push(RecordViolation::new(name, &err));
+                continue;
+            }
+            None => None,
+        };
+        if let Some(container) = &container {
+            if let Err(err) = check_enum_values(domain, &attribute.vtype, container) {
+                violations.push(RecordViolation::new(name, &err));
+                continue;
+            }
+        }
+        if let Err(err) = attribute.check_value(container.as_ref()) {
+            violations.push(RecordViolation::new(name, &err.to_string()));
+        }
+    }
+
+    for reference in &entity.references {
+        let property = reference_property(reference);
+        if let Some(value) = properties.get(&property) {
+            if let Err(err) = check_reference(reference, value) {
+                violations.push(RecordViolation::new(Some(&property), &err));
+            }
+        }
+    }
+
+    violations
+}
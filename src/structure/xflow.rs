@@ -2,7 +2,7 @@
 use crate as root;
 use serde_tuple::*;
 use std::collections::HashSet;
-use super::data::{DocumentVariables, VariableDefinition, Position};
+use super::data::{DocumentVariables, VariableDefinition, VariableScope, Position, VType};
 
 use super::common::{Document};
 
@@ -39,7 +39,7 @@ pub struct XFlowEdge {
     pub target: i32,
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Clone, PartialEq)]
 // partof: SPC-serialization-json
 pub struct XFlow {
     pub requirements: Vec<XFlowRequirement>,
@@ -49,13 +49,213 @@ pub struct XFlow {
     pub branches: Vec<XFlowBranch>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum XFlowError {
     NoEntryNode,
     NoTerminalNode,
     MultipleEntryNodes,
-    DuplicateNodeIDs,
+    /// More than one node has this id
+    DuplicateNodeIDs(i32),
+    /// The edge refers to a node that does not exist
+    NodeNotFound(i32, XFlowEdge),
+    /// The edge of a branch refers to a node that does not exist
+    BranchNodeNotFound(i32, XFlowEdge),
+    /// The node can not be reached from the entry node
+    UnreachableNode(i32),
+    /// No terminal node can be reached from the node
+    DeadEndNode(i32),
+    /// The nodes form a cycle that no branch leads out of
+    UnintendedCycle(Vec<i32>),
+    /// The parameters of the node are not those of its type
+    ParametersMismatch(i32, XFlowNodeType),
+    /// The edge is in the flow more than once
+    DuplicateEdge(XFlowEdge),
+    /// The branch of the edge is on a variable the flow does not have
+    BranchVariableNotFound(XFlowEdge, String),
+    /// The branch of the edge is for values of another type than its
+    /// variable
+    BranchTypeMismatch(XFlowEdge, String),
+    /// There is no node with this id
+    UnknownNode(i32),
+    /// There is no such edge
+    UnknownEdge(XFlowEdge),
+    /// The edge has no branch on this variable
+    UnknownBranch(XFlowEdge, String),
+    /// The flow has no variable by this name
+    UnknownVariable(String),
+    /// More than one variable of the flow has this name
+    DuplicateVariableName(String),
+    /// More than one variable of the flow has this id
+    DuplicateVariableID(i32),
+}
+
+impl std::fmt::Display for XFlowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            XFlowError::NoEntryNode => write!(f, "the flow has no entry node"),
+            XFlowError::NoTerminalNode => write!(f, "the flow has no terminal node"),
+            XFlowError::MultipleEntryNodes => write!(f, "the flow has more than one entry node"),
+            XFlowError::DuplicateNodeIDs(id) => write!(f, "more than one node has id {}", id),
+            XFlowError::NodeNotFound(id, edge) => {
+                write!(f, "edge {} -> {}: node {} does not exist", edge.source, edge.target, id)
+            }
+            XFlowError::BranchNodeNotFound(id, edge) => write!(
+                f,
+                "branch of edge {} -> {}: node {} does not exist",
+                edge.source, edge.target, id
+            ),
+            XFlowError::UnreachableNode(id) => {
+                write!(f, "node {} can not be reached from the entry node", id)
+            }
+            XFlowError::DeadEndNode(id) => {
+                write!(f, "node {} does not lead to a terminal node", id)
+            }
+            XFlowError::UnintendedCycle(ids) => write!(
+                f,
+                "nodes {} form a cycle no branch decides to leave",
+                ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+            ),
+            XFlowError::ParametersMismatch(id, nodetype) => write!(
+                f,
+                "node {} does not have the parameters of a {} node",
+                id,
+                format!("{:?}", nodetype).to_lowercase()
+            ),
+            XFlowError::DuplicateEdge(edge) => {
+                write!(f, "edge {} -> {} is there more than once", edge.source, edge.target)
+            }
+            XFlowError::BranchVariableNotFound(edge, name) => write!(
+                f,
+                "branch of edge {} -> {}: variable {} does not exist",
+                edge.source, edge.target, name
+            ),
+            XFlowError::BranchTypeMismatch(edge, name) => write!(
+                f,
+                "branch of edge {} -> {}: the value is not of the type of variable {}",
+                edge.source, edge.target, name
+            ),
+            XFlowError::UnknownNode(id) => write!(f, "node {} does not exist", id),
+            XFlowError::UnknownEdge(edge) => {
+                write!(f, "edge {} -> {} does not exist", edge.source, edge.target)
+            }
+            XFlowError::UnknownBranch(edge, name) => write!(
+                f,
+                "edge {} -> {} has no branch on variable {}",
+                edge.source, edge.target, name
+            ),
+            XFlowError::UnknownVariable(name) => write!(f, "variable {} does not exist", name),
+            XFlowError::DuplicateVariableName(name) => {
+                write!(f, "more than one variable is named {}", name)
+            }
+            XFlowError::DuplicateVariableID(id) => {
+                write!(f, "more than one variable has id {}", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for XFlowError {}
+
+impl XFlowError {
+    /// Whether the error is one every flow has while it is being built:
+    /// nodes that are not connected yet, loops that are not left yet and
+    /// branches on variables that were removed
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self,
+            XFlowError::UnreachableNode(_)
+                | XFlowError::DeadEndNode(_)
+                | XFlowError::UnintendedCycle(_)
+                | XFlowError::BranchVariableNotFound(_, _)
+        )
+    }
+}
+
+#[derive(GraphQLEnum, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum XFlowDiagnosticCode {
+    NoEntryNode,
+    NoTerminalNode,
+    MultipleEntryNodes,
+    DuplicateNodeId,
     NodeNotFound,
+    BranchNodeNotFound,
+    UnreachableNode,
+    DeadEndNode,
+    UnintendedCycle,
+    ParametersMismatch,
+    DuplicateEdge,
+    EdgeNotFound,
+    BranchNotFound,
+    VariableNotFound,
+    BranchTypeMismatch,
+    DuplicateVariableName,
+    DuplicateVariableId,
+}
+
+/// A single problem found by `XFlow::validate`, `node_ids` are the nodes it
+/// is about, or the ends of the offending edge
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct XFlowDiagnostic {
+    pub code: XFlowDiagnosticCode,
+    pub message: String,
+    pub node_ids: Vec<i32>,
+}
+
+impl From<&XFlowError> for XFlowDiagnostic {
+    fn from(err: &XFlowError) -> Self {
+        let (code, node_ids) = match err {
+            XFlowError::NoEntryNode => (XFlowDiagnosticCode::NoEntryNode, vec![]),
+            XFlowError::NoTerminalNode => (XFlowDiagnosticCode::NoTerminalNode, vec![]),
+            XFlowError::MultipleEntryNodes => (XFlowDiagnosticCode::MultipleEntryNodes, vec![]),
+            XFlowError::DuplicateNodeIDs(id) => (XFlowDiagnosticCode::DuplicateNodeId, vec![*id]),
+            XFlowError::NodeNotFound(_, edge) => {
+                (XFlowDiagnosticCode::NodeNotFound, vec![edge.source, edge.target])
+            }
+            XFlowError::BranchNodeNotFound(_, edge) => {
+                (XFlowDiagnosticCode::BranchNodeNotFound, vec![edge.source, edge.target])
+            }
+            XFlowError::UnreachableNode(id) => (XFlowDiagnosticCode::UnreachableNode, vec![*id]),
+            XFlowError::DeadEndNode(id) => (XFlowDiagnosticCode::DeadEndNode, vec![*id]),
+            XFlowError::UnintendedCycle(ids) => (XFlowDiagnosticCode::UnintendedCycle, ids.clone()),
+            XFlowError::ParametersMismatch(id, _) => {
+                (XFlowDiagnosticCode::ParametersMismatch, vec![*id])
+            }
+            XFlowError::DuplicateEdge(edge) => {
+                (XFlowDiagnosticCode::DuplicateEdge, vec![edge.source, edge.target])
+            }
+            XFlowError::BranchVariableNotFound(edge, _) => {
+                (XFlowDiagnosticCode::VariableNotFound, vec![edge.source, edge.target])
+            }
+            XFlowError::BranchTypeMismatch(edge, _) => {
+                (XFlowDiagnosticCode::BranchTypeMismatch, vec![edge.source, edge.target])
+            }
+            XFlowError::UnknownNode(id) => (XFlowDiagnosticCode::NodeNotFound, vec![*id]),
+            XFlowError::UnknownEdge(edge) => {
+                (XFlowDiagnosticCode::EdgeNotFound, vec![edge.source, edge.target])
+            }
+            XFlowError::UnknownBranch(edge, _) => {
+                (XFlowDiagnosticCode::BranchNotFound, vec![edge.source, edge.target])
+            }
+            XFlowError::UnknownVariable(_) => (XFlowDiagnosticCode::VariableNotFound, vec![]),
+            XFlowError::DuplicateVariableName(_) => {
+                (XFlowDiagnosticCode::DuplicateVariableName, vec![])
+            }
+            XFlowError::DuplicateVariableID(_) => (XFlowDiagnosticCode::DuplicateVariableId, vec![]),
+        };
+        XFlowDiagnostic {
+            code,
+            message: err.to_string(),
+            node_ids,
+        }
+    }
+}
+
+/// A variable after it was renamed or removed, `warnings` are about the
+/// branches and flox nodes that still use its old name
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct XFlowVariableEdit {
+    pub variable: VariableDefinition,
+    pub warnings: Vec<XFlowDiagnostic>,
 }
 
 #[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
@@ -64,7 +264,7 @@ pub struct XFlowRequirement {
     pub version: i32,
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct XFlowNode {
     pub id: i32,
     pub nodetype: XFlowNodeType,
@@ -84,7 +284,7 @@ pub enum XFlowNodeType {
     Call,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum XFlowNodeParameters {
     #[serde(rename = "flow")]
     Flow(FlowParameters),
@@ -94,6 +294,18 @@ pub enum XFlowNodeParameters {
     Call(CallParameters),
 }
 
+impl XFlowNodeParameters {
+    /// The parameters of a new node of `nodetype`, `None` for flox nodes,
+    /// which need an expression
+    pub fn for_type(nodetype: &XFlowNodeType) -> Option<Self> {
+        match nodetype {
+            XFlowNodeType::Flow => Some(XFlowNodeParameters::Flow(FlowParameters::default())),
+            XFlowNodeType::Flox => None,
+            XFlowNodeType::Call => Some(XFlowNodeParameters::Call(CallParameters {})),
+        }
+    }
+}
+
 graphql_union!(XFlowNodeParameters: () where Scalar = <S> |&self| {
     instance_resolvers: |_| {
         &FlowParameters => match *self { XFlowNodeParameters::Flow(ref h) => Some(h), _ => None },
@@ -111,7 +323,7 @@ impl Default for FlowParameters {
     }
 }
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct FloxParameters {
     pub expression: String,
     pub returns: VariableDefinition,
@@ -120,7 +332,7 @@ pub struct FloxParameters {
 #[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct CallParameters {}
 
-#[derive(GraphQLObject, Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[derive(GraphQLObject, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct XFlowBranch {
     #[graphql(skip)]
     pub edge: XFlowEdge,
@@ -242,6 +454,457 @@ impl XFlow {
             _ => None,
         }
     }
+
+    /// Check the structure of the flow, every problem found is reported
+    ///
+    /// Cycles are allowed when a branch leads out of them, any other cycle
+    /// would run forever once entered.
+    pub fn validate(&self) -> Vec<XFlowError> {
+        let mut errors = Vec::<XFlowError>::new();
+
+        let mut ids = Vec::<i32>::new();
+        for node in &self.nodes {
+            if !ids.contains(&node.id) {
+                ids.push(node.id);
+            } else if !errors.contains(&XFlowError::DuplicateNodeIDs(node.id)) {
+                errors.push(XFlowError::DuplicateNodeIDs(node.id));
+            }
+            let matches = matches!(
+                (&node.nodetype, &node.parameters),
+                (XFlowNodeType::Flow, XFlowNodeParameters::Flow(_))
+                    | (XFlowNodeType::Flox, XFlowNodeParameters::Flox(_))
+                    | (XFlowNodeType::Call, XFlowNodeParameters::Call(_))
+            );
+            if !matches {
+                errors.push(XFlowError::ParametersMismatch(node.id, node.nodetype.clone()));
+            }
+        }
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            for id in &[edge.source, edge.target] {
+                if !ids.contains(id) {
+                    errors.push(XFlowError::NodeNotFound(*id, edge.clone()));
+                }
+            }
+            let duplicate = XFlowError::DuplicateEdge(edge.clone());
+            if self.edges[..i].contains(edge) && !errors.contains(&duplicate) {
+                errors.push(duplicate);
+            }
+        }
+        let (mut names, mut variable_ids) = (HashSet::<&str>::new(), HashSet::<i32>::new());
+        for xvar in self.variables.iter() {
+            let name = XFlowError::DuplicateVariableName(xvar.name.clone());
+            if !names.insert(&xvar.name) && !errors.contains(&name) {
+                errors.push(name);
+            }
+            let id = XFlowError::DuplicateVariableID(xvar.id);
+            if !variable_ids.insert(xvar.id) && !errors.contains(&id) {
+                errors.push(id);
+            }
+        }
+
+        for branch in &self.branches {
+            for id in &[branch.edge.source, branch.edge.target] {
+                if !ids.contains(id) {
+                    errors.push(XFlowError::BranchNodeNotFound(*id, branch.edge.clone()));
+                }
+            }
+            let (edge, name) = (branch.edge.clone(), branch.xvar.name.clone());
+            match self.variables.get_variable(&name) {
+                None => errors.push(XFlowError::BranchVariableNotFound(edge, name)),
+                Some(xvar) if xvar.vtype.type_name() != branch.xvar.vtype.type_name() => {
+                    errors.push(XFlowError::BranchTypeMismatch(edge, name))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let entries: Vec<i32> = self.get_nodes_by(&XFlowNodeType::Flow, "start")
+            .iter()
+            .map(|node| node.id)
+            .collect();
+        if let Err(err) = self.get_entry_node() {
+            errors.push(err);
+        }
+        if !entries.is_empty() {
+            let reachable = self.reachable(&entries, true);
+            for id in ids.iter().filter(|id| !reachable.contains(id)) {
+                errors.push(XFlowError::UnreachableNode(*id));
+            }
+        }
+
+        match self.get_terminal_nodes() {
+            Ok(terminals) => {
+                let terminals: Vec<i32> = terminals.iter().map(|node| node.id).collect();
+                let leading = self.reachable(&terminals, false);
+                for id in ids.iter().filter(|id| !leading.contains(id)) {
+                    errors.push(XFlowError::DeadEndNode(*id));
+                }
+            }
+            Err(err) => errors.push(err),
+        }
+
+        // A cycle ends when one of its nodes chooses between an edge out of
+        // it and another, by branches on either. An edge without branches is
+        // taken when no branch matches.
+        for cycle in self.cycles(&ids) {
+            let leaves = cycle.iter().any(|id| {
+                let out: Vec<&XFlowEdge> =
+                    self.edges.iter().filter(|edge| edge.source == *id).collect();
+                out.iter().any(|edge| !cycle.contains(&edge.target))
+                    && out.iter().any(|edge| !self.get_branches_for(edge).is_empty())
+            });
+            if !leaves {
+                errors.push(XFlowError::UnintendedCycle(cycle));
+            }
+        }
+
+        errors
+    }
+
+    /// The ids of the nodes reachable from `from`, following the edges
+    /// `forward` or backward, `from` included
+    fn reachable(&self, from: &[i32], forward: bool) -> HashSet<i32> {
+        let mut found: HashSet<i32> = from.iter().cloned().collect();
+        let mut todo: Vec<i32> = from.to_vec();
+        while let Some(id) = todo.pop() {
+            for edge in &self.edges {
+                let (near, far) = if forward {
+                    (edge.source, edge.target)
+                } else {
+                    (edge.target, edge.source)
+                };
+                if near == id && found.insert(far) {
+                    todo.push(far);
+                }
+            }
+        }
+        found
+    }
+
+    /// The cycles among the nodes `ids`, each as the sorted ids of a set of
+    /// nodes that all reach each other
+    fn cycles(&self, ids: &[i32]) -> Vec<Vec<i32>> {
+        let reach: Vec<HashSet<i32>> = ids.iter().map(|id| self.reachable(&[*id], true)).collect();
+        let mut cycles = Vec::<Vec<i32>>::new();
+        for (i, id) in ids.iter().enumerate() {
+            if cycles.iter().any(|cycle| cycle.contains(id)) {
+                continue;
+            }
+            let mut cycle: Vec<i32> = ids
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| reach[i].contains(other) && reach[*j].contains(id))
+                .map(|(_, other)| *other)
+                .collect();
+            let looped = self.edges.iter().any(|edge| edge.source == *id && edge.target == *id);
+            if cycle.len() > 1 || looped {
+                cycle.sort();
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    /// The next free node id
+    pub fn next_id(&self) -> i32 {
+        self.nodes.iter().map(|node| node.id).max().unwrap_or(0) + 1
+    }
+
+    /// Apply `edit`, and undo it when it fails or leaves the flow with a
+    /// problem it did not have before. Nodes that are not connected yet
+    /// and loops no branch leaves yet are left to later edits.
+    fn edit<T, F>(&mut self, edit: F) -> Result<T, XFlowError>
+    where
+        F: FnOnce(&mut XFlow) -> Result<T, XFlowError>,
+    {
+        let before = self.validate();
+        let backup = self.clone();
+        let result = edit(self).and_then(|value| {
+            let new = self
+                .validate()
+                .into_iter()
+                .find(|err| !err.is_incomplete() && !before.contains(err));
+            match new {
+                Some(err) => Err(err),
+                None => Ok(value),
+            }
+        });
+        if result.is_err() {
+            *self = backup;
+        }
+        result
+    }
+
+    fn node_mut(&mut self, id: i32) -> Result<&mut XFlowNode, XFlowError> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.id == id)
+            .ok_or(XFlowError::UnknownNode(id))
+    }
+
+    /// The parameters of a flox node computing `expression` into the
+    /// variable `returns`
+    pub fn flox_parameters(
+        &self,
+        expression: &str,
+        returns: &str,
+    ) -> Result<XFlowNodeParameters, XFlowError> {
+        let returns = self
+            .variables
+            .get_variable(returns)
+            .ok_or_else(|| XFlowError::UnknownVariable(returns.to_owned()))?;
+        Ok(XFlowNodeParameters::Flox(FloxParameters {
+            expression: expression.to_owned(),
+            returns: returns.clone(),
+        }))
+    }
+
+    pub fn add_node(
+        &mut self,
+        nodetype: XFlowNodeType,
+        action: &str,
+        label: &str,
+        position: Position,
+        parameters: XFlowNodeParameters,
+    ) -> Result<XFlowNode, XFlowError> {
+        self.edit(|xflow| {
+            let node = XFlowNode {
+                id: xflow.next_id(),
+                nodetype,
+                position,
+                action: action.to_owned(),
+                label: label.to_owned(),
+                parameters,
+            };
+            xflow.nodes.push(node.clone());
+            Ok(node)
+        })
+    }
+
+    /// Change the action, label or parameters of node `id`, `None` leaves
+    /// them as they are
+    pub fn update_node(
+        &mut self,
+        id: i32,
+        action: Option<&str>,
+        label: Option<&str>,
+        parameters: Option<XFlowNodeParameters>,
+    ) -> Result<XFlowNode, XFlowError> {
+        self.edit(|xflow| {
+            let node = xflow.node_mut(id)?;
+            if let Some(action) = action {
+                node.action = action.to_owned();
+            }
+            if let Some(label) = label {
+                node.label = label.to_owned();
+            }
+            if let Some(parameters) = parameters {
+                node.parameters = parameters;
+            }
+            Ok(node.clone())
+        })
+    }
+
+    pub fn move_node(&mut self, id: i32, position: Position) -> Result<XFlowNode, XFlowError> {
+        self.edit(|xflow| {
+            let node = xflow.node_mut(id)?;
+            node.position = position;
+            Ok(node.clone())
+        })
+    }
+
+    /// Remove node `id` together with its edges and their branches
+    pub fn remove_node(&mut self, id: i32) -> Result<XFlowNode, XFlowError> {
+        self.edit(|xflow| {
+            let position = xflow
+                .nodes
+                .iter()
+                .position(|node| node.id == id)
+                .ok_or(XFlowError::UnknownNode(id))?;
+            let node = xflow.nodes.remove(position);
+            xflow.edges.retain(|edge| edge.source != id && edge.target != id);
+            xflow
+                .branches
+                .retain(|branch| branch.edge.source != id && branch.edge.target != id);
+            Ok(node)
+        })
+    }
+
+    /// Add an edge from node `source` to node `target`
+    pub fn connect(&mut self, source: i32, target: i32) -> Result<XFlowEdge, XFlowError> {
+        self.edit(|xflow| {
+            let edge = XFlowEdge { source, target };
+            for id in &[source, target] {
+                if xflow.get_node_id(*id).is_none() {
+                    return Err(XFlowError::UnknownNode(*id));
+                }
+            }
+            xflow.edges.push(edge.clone());
+            Ok(edge)
+        })
+    }
+
+    /// Remove the edge from node `source` to node `target` and its branches
+    pub fn disconnect(&mut self, source: i32, target: i32) -> Result<XFlowEdge, XFlowError> {
+        self.edit(|xflow| {
+            let edge = XFlowEdge { source, target };
+            let position = xflow
+                .edges
+                .iter()
+                .position(|e| *e == edge)
+                .ok_or_else(|| XFlowError::UnknownEdge(edge.clone()))?;
+            xflow.edges.remove(position);
+            xflow.branches.retain(|branch| branch.edge != edge);
+            Ok(edge)
+        })
+    }
+
+    /// Branch the edge from node `source` to node `target` on variable
+    /// `name`, the edge is taken when the variable has the default value of
+    /// `vtype`
+    pub fn add_branch(
+        &mut self,
+        source: i32,
+        target: i32,
+        name: &str,
+        vtype: VType,
+    ) -> Result<XFlowBranch, XFlowError> {
+        self.edit(|xflow| {
+            let edge = XFlowEdge { source, target };
+            if !xflow.edges.contains(&edge) {
+                return Err(XFlowError::UnknownEdge(edge));
+            }
+            let id = xflow
+                .variables
+                .get_variable(name)
+                .map(|xvar| xvar.id)
+                .ok_or_else(|| XFlowError::UnknownVariable(name.to_owned()))?;
+            let branch = XFlowBranch {
+                edge,
+                xvar: VariableDefinition {
+                    id,
+                    name: name.to_owned(),
+                    vtype,
+                },
+            };
+            xflow.branches.push(branch.clone());
+            Ok(branch)
+        })
+    }
+
+    /// Remove the branches on variable `name` of the edge from node
+    /// `source` to node `target`
+    pub fn remove_branch(
+        &mut self,
+        source: i32,
+        target: i32,
+        name: &str,
+    ) -> Result<XFlowBranch, XFlowError> {
+        self.edit(|xflow| {
+            let edge = XFlowEdge { source, target };
+            let found = |branch: &XFlowBranch| branch.edge == edge && branch.xvar.name == name;
+            let branch = xflow
+                .branches
+                .iter()
+                .find(|branch| found(branch))
+                .cloned()
+                .ok_or_else(|| XFlowError::UnknownBranch(edge.clone(), name.to_owned()))?;
+            xflow.branches.retain(|branch| !found(branch));
+            Ok(branch)
+        })
+    }
+
+    /// Add the variable `name` of type `vtype` to `scope`, with a new id.
+    /// Names are unique across the input, local and output variables.
+    pub fn add_variable(
+        &mut self,
+        scope: VariableScope,
+        name: &str,
+        vtype: VType,
+    ) -> Result<VariableDefinition, XFlowError> {
+        self.edit(|xflow| {
+            if xflow.variables.get_variable(name).is_some() {
+                return Err(XFlowError::DuplicateVariableName(name.to_owned()));
+            }
+            let xvar = VariableDefinition {
+                id: xflow.variables.next_id(),
+                name: name.to_owned(),
+                vtype,
+            };
+            xflow.variables.scope_mut(scope).push(xvar.clone());
+            Ok(xvar)
+        })
+    }
+
+    fn variable_mut(&mut self, name: &str) -> Result<&mut VariableDefinition, XFlowError> {
+        self.variables
+            .get_variable_mut(name)
+            .ok_or_else(|| XFlowError::UnknownVariable(name.to_owned()))
+    }
+
+    /// Rename the variable `name` to `new_name`, along with the branches on
+    /// it and the flox nodes returning it. Flox expressions are left as
+    /// they are.
+    pub fn rename_variable(
+        &mut self,
+        name: &str,
+        new_name: &str,
+    ) -> Result<VariableDefinition, XFlowError> {
+        self.edit(|xflow| {
+            if name != new_name && xflow.variables.get_variable(new_name).is_some() {
+                return Err(XFlowError::DuplicateVariableName(new_name.to_owned()));
+            }
+            let xvar = xflow.variable_mut(name)?;
+            xvar.name = new_name.to_owned();
+            let xvar = xvar.clone();
+            for branch in xflow.branches.iter_mut().filter(|b| b.xvar.name == name) {
+                branch.xvar.name = new_name.to_owned();
+            }
+            for node in &mut xflow.nodes {
+                if let XFlowNodeParameters::Flox(parameters) = &mut node.parameters {
+                    if parameters.returns.name == name {
+                        parameters.returns = xvar.clone();
+                    }
+                }
+            }
+            Ok(xvar)
+        })
+    }
+
+    /// Change the type of variable `name` to `vtype`, along with that of the
+    /// flox nodes returning it. Branches on the variable have to be removed
+    /// first when the type changes.
+    pub fn retype_variable(
+        &mut self,
+        name: &str,
+        vtype: VType,
+    ) -> Result<VariableDefinition, XFlowError> {
+        self.edit(|xflow| {
+            let xvar = xflow.variable_mut(name)?;
+            xvar.vtype = vtype;
+            let xvar = xvar.clone();
+            for node in &mut xflow.nodes {
+                if let XFlowNodeParameters::Flox(parameters) = &mut node.parameters {
+                    if parameters.returns.name == name {
+                        parameters.returns = xvar.clone();
+                    }
+                }
+            }
+            Ok(xvar)
+        })
+    }
+
+    /// Remove the variable `name`. Branches and flox nodes that use it are
+    /// kept, to be changed or removed by later edits.
+    pub fn remove_variable(&mut self, name: &str) -> Result<VariableDefinition, XFlowError> {
+        self.edit(|xflow| {
+            xflow
+                .variables
+                .remove_variable(name)
+                .ok_or_else(|| XFlowError::UnknownVariable(name.to_owned()))
+        })
+    }
 }
 
 impl Default for XFlow {
@@ -7,6 +7,8 @@ use std::io::prelude::*;
 use std::path::Path;
 
 use self::serde::de::DeserializeOwned;
+use gearsx::structure::data::{VType, VTypeEnum, VTypeInteger, VTypeString};
+use gearsx::structure::domain::{AttributeConstraints, Domain, ReferenceType};
 
 #[allow(dead_code)]
 pub fn load_doc<T>(path: &str) -> T
@@ -36,3 +38,105 @@ pub fn read_json_file(filename: &str) -> String {
 
     s
 }
+
+#[allow(dead_code)]
+pub fn string() -> VType {
+    VType::VTypeString(VTypeString { default: None })
+}
+
+/// Authors and their books: an enum `genre` of `genres`, an entity `author`
+/// with a `name` and an entity `book` with a `genre`, referring to each
+/// other. Tests add what they need on top.
+#[allow(dead_code)]
+pub fn library_domain(genres: &[&str]) -> Domain {
+    let mut domain = Domain::default();
+    let genres: Vec<String> = genres.iter().map(|g| g.to_string()).collect();
+    domain.add_enum("genre", &genres).unwrap();
+
+    let author = domain.add_entity("author").unwrap();
+    domain.add_attribute(author.id, "name", string()).unwrap();
+
+    let book = domain.add_entity("book").unwrap();
+    domain
+        .add_attribute(
+            book.id,
+            "genre",
+            VType::VTypeEnum(VTypeEnum {
+                name: "genre".into(),
+                default: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .add_reference(author.id, "books", ReferenceType::HasMany, "book")
+        .unwrap();
+    domain
+        .add_reference(book.id, "author", ReferenceType::BelongsTo, "author")
+        .unwrap();
+    domain
+}
+
+/// Customers and their orders: an enum `order_status`, an entity `customer`
+/// with a unique `email` and an entity `order` with an indexed `status` and
+/// a `quantity`, belonging to a customer. Tests add what they need on top.
+#[allow(dead_code)]
+pub fn shop_domain() -> Domain {
+    let mut domain = Domain::default();
+    domain
+        .add_enum("order_status", &["open".to_string(), "shipped".to_string()])
+        .unwrap();
+
+    let customer = domain.add_entity("customer").unwrap();
+    let email = domain
+        .add_attribute(customer.id, "email", string())
+        .unwrap();
+    domain
+        .set_attribute_constraints(
+            customer.id,
+            email.id,
+            AttributeConstraints {
+                required: true,
+                unique: true,
+                max_length: Some(64),
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+
+    let order = domain.add_entity("order").unwrap();
+    let status = domain
+        .add_attribute(
+            order.id,
+            "status",
+            VType::VTypeEnum(VTypeEnum {
+                name: "order_status".into(),
+                default: Some("open".into()),
+            }),
+        )
+        .unwrap();
+    domain
+        .set_attribute_constraints(
+            order.id,
+            status.id,
+            AttributeConstraints {
+                indexed: true,
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            order.id,
+            "quantity",
+            VType::VTypeInteger(VTypeInteger {
+                default: Some(1),
+                min: Some(1),
+                max: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .add_reference(order.id, "customer", ReferenceType::BelongsTo, "customer")
+        .unwrap();
+    domain
+}
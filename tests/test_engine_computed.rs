@@ -0,0 +1,77 @@
+extern crate gearsx;
+use gearsx::engine::computed::derive;
+use gearsx::structure::data::{VType, VTypeInteger, VTypeString};
+use gearsx::structure::domain::{AttributeStorage, Domain};
+use gearsx::structure::record::{check_record, RecordViolation};
+use serde_json::json;
+
+fn post_domain(expression: &str) -> Domain {
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    domain
+        .add_attribute(
+            post.id,
+            "title",
+            VType::VTypeString(VTypeString { default: None }),
+        )
+        .unwrap();
+    let words = domain
+        .add_attribute(
+            post.id,
+            "words",
+            VType::VTypeInteger(VTypeInteger {
+                default: None,
+                min: None,
+                max: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .set_attribute_storage(
+            post.id,
+            words.id,
+            AttributeStorage::Computed {
+                expression: expression.into(),
+            },
+        )
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_derive() {
+    let domain = post_domain("len(title) * 2");
+    let post = &domain.entities[0];
+    let stored = json!({"title": "hello"});
+
+    let read = derive(post, stored.clone()).unwrap();
+    assert_eq!(read, json!({"title": "hello", "words": 10}));
+    assert_eq!(
+        derive(post, json!({})).unwrap(),
+        json!({"words": null}),
+        "no value without the attributes the expression uses"
+    );
+
+    // What is read can not be written back, computed values are never stored
+    assert_eq!(check_record(&domain, post, &stored), vec![]);
+    assert_eq!(
+        check_record(&domain, post, &read),
+        vec![RecordViolation::new(
+            Some("words"),
+            "computed attributes are read only"
+        )]
+    );
+
+    // Domains refuse such expressions, stored ones may predate that
+    let mut domain = post_domain("len(title)");
+    domain.entities[0].attributes[1].storage = AttributeStorage::Computed {
+        expression: "upper(title)".into(),
+    };
+    let err = derive(&domain.entities[0], stored).unwrap_err();
+    assert_eq!(err.attribute, "words");
+    assert_eq!(
+        err.to_string(),
+        "computed attribute words: column 1: the expression is of type string, the attribute of \
+         type integer"
+    );
+}
@@ -0,0 +1,130 @@
+extern crate env_logger;
+
+extern crate gearsx;
+use gearsx::engine::events::{fire, EventError, FlowRunner, RecordEvent, XFlowRunner};
+use gearsx::structure::common::DocumentReference;
+use gearsx::structure::domain::{Domain, Events};
+use gearsx::structure::xflow::XFlowDocument;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// Answers every run with the output given for the flow, by flow name
+struct ScriptedRunner(Vec<(&'static str, Result<Value, String>)>);
+
+impl FlowRunner for ScriptedRunner {
+    fn run(
+        &self,
+        xflow: &XFlowDocument,
+        input: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, String> {
+        assert_eq!(input["entity"], json!("post"));
+        assert!(input["record"].get("id").is_some());
+        let (_, output) = self.0.iter().find(|(name, _)| *name == xflow.name).unwrap();
+        output
+            .clone()
+            .map(|output| output.as_object().unwrap().clone())
+    }
+}
+
+fn xflow(name: &str) -> XFlowDocument {
+    XFlowDocument {
+        name: name.to_string(),
+        ..XFlowDocument::default()
+    }
+}
+
+fn reference(xflow: &XFlowDocument) -> DocumentReference {
+    DocumentReference {
+        id: xflow.id,
+        doctype: "xflow".to_string(),
+    }
+}
+
+#[test]
+fn test_event_xflows() {
+    let (a, b, c) = (xflow("a"), xflow("b"), xflow("c"));
+    let events = Events {
+        change: vec![reference(&a)],
+        update: vec![reference(&b), reference(&a)],
+        read: vec![reference(&c)],
+        delete: vec![],
+        all: vec![reference(&c)],
+    };
+    let ids =
+        |event: RecordEvent| -> Vec<Uuid> { event.xflows(&events).iter().map(|r| r.id).collect() };
+    assert_eq!(ids(RecordEvent::Create), vec![a.id, c.id]);
+    assert_eq!(ids(RecordEvent::Update), vec![b.id, a.id, c.id]);
+    assert_eq!(ids(RecordEvent::Read), vec![c.id]);
+    assert_eq!(ids(RecordEvent::Delete), vec![a.id, c.id]);
+}
+
+#[test]
+fn test_fire() {
+    let _ = env_logger::try_init();
+
+    let entity = Domain::default().add_entity("post").unwrap();
+    let id = Uuid::new_v4();
+    let (stamp, observe, deny) = (xflow("stamp"), xflow("observe"), xflow("deny"));
+    let runner = ScriptedRunner(vec![
+        (
+            "stamp",
+            Ok(json!({"record": {"title": "Hello", "stamped": true}})),
+        ),
+        ("observe", Ok(json!({"veto": false, "record": null}))),
+        (
+            "deny",
+            Ok(json!({"veto": true, "message": "posts are closed"})),
+        ),
+    ]);
+    let body = json!({"title": "Hello"});
+
+    let flows = vec![stamp.clone(), observe.clone()];
+    let result = fire(
+        RecordEvent::Create,
+        &entity,
+        &id,
+        body.clone(),
+        None,
+        &flows,
+        &runner,
+    );
+    assert_eq!(result, Ok(json!({"title": "Hello", "stamped": true})));
+
+    let flows = vec![observe, deny.clone(), stamp];
+    let result = fire(
+        RecordEvent::Update,
+        &entity,
+        &id,
+        body.clone(),
+        Some(&body),
+        &flows,
+        &runner,
+    );
+    assert_eq!(
+        result,
+        Err(EventError::Vetoed {
+            xflow: deny.id,
+            message: "posts are closed".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_fire_xflow_runner() {
+    let _ = env_logger::try_init();
+
+    // The default flow goes from start to end and leaves the record alone
+    let entity = Domain::default().add_entity("post").unwrap();
+    let body = json!({"title": "Hello"});
+    let flows = vec![xflow("default")];
+    let result = fire(
+        RecordEvent::Delete,
+        &entity,
+        &Uuid::new_v4(),
+        body.clone(),
+        None,
+        &flows,
+        &XFlowRunner,
+    );
+    assert_eq!(result, Ok(body));
+}
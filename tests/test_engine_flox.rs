@@ -0,0 +1,292 @@
+extern crate gearsx;
+use gearsx::engine::flox::check::{check, nodes_using};
+use gearsx::engine::flox::eval::{evaluate, run};
+use gearsx::engine::flox::parser::parse;
+use gearsx::engine::flox::{FloxError, FloxType, FloxValue};
+use gearsx::engine::xflow::{self, Variables};
+use gearsx::structure::data::{
+    DocumentVariables, VType, VTypeBoolean, VTypeContainer, VTypeDate, VTypeFloat,
+    VTypeFloatContainer, VTypeInteger, VTypeString, VariableDefinition,
+};
+use gearsx::structure::xflow::{
+    FloxParameters, XFlow, XFlowEdge, XFlowNodeParameters, XFlowNodeType,
+};
+use serde_json::{json, Map};
+
+fn variable(id: i32, name: &str, vtype: VType) -> VariableDefinition {
+    VariableDefinition {
+        id,
+        name: name.to_string(),
+        vtype,
+    }
+}
+
+fn integer(default: i32) -> VType {
+    VType::VTypeInteger(VTypeInteger {
+        default: Some(default),
+        min: None,
+        max: None,
+    })
+}
+
+fn string(default: &str) -> VType {
+    VType::VTypeString(VTypeString {
+        default: Some(default.to_string()),
+    })
+}
+
+fn float() -> VType {
+    VType::VTypeFloat(VTypeFloat {
+        default: None,
+        min: None,
+        max: None,
+    })
+}
+
+fn definitions() -> DocumentVariables {
+    DocumentVariables {
+        input: vec![
+            variable(1, "name", string("  Ursula ")),
+            variable(2, "count", integer(3)),
+        ],
+        local: vec![variable(
+            3,
+            "done",
+            VType::VTypeBoolean(VTypeBoolean {
+                default: Some(false),
+            }),
+        )],
+        output: vec![
+            variable(4, "born", VType::VTypeDate(VTypeDate { default: None })),
+            variable(5, "ratio", float()),
+        ],
+    }
+}
+
+fn values(definitions: &DocumentVariables) -> Variables {
+    let all = definitions
+        .input
+        .iter()
+        .chain(&definitions.local)
+        .chain(&definitions.output);
+    all.map(|xvar| (xvar.name.clone(), xvar.vtype.new_instance()))
+        .collect()
+}
+
+fn eval(expression: &str) -> Result<FloxValue, FloxError> {
+    let definitions = definitions();
+    let node = parse(expression)?;
+    check(&node, &definitions)?;
+    evaluate(&node, &values(&definitions))
+}
+
+fn error(expression: &str) -> String {
+    eval(expression).unwrap_err().to_string()
+}
+
+#[test]
+fn test_evaluate() {
+    let string = |s: &str| Ok(FloxValue::String(s.to_string()));
+    assert_eq!(eval("1 + 2 * 3 - 4 / 2"), Ok(FloxValue::Integer(5)));
+    assert_eq!(eval("(1 + 2) * -count % 5"), Ok(FloxValue::Integer(-4)));
+    assert_eq!(eval("count / 2.0"), Ok(FloxValue::Float(1.5)));
+    assert_eq!(eval("count >= 3 && !done"), Ok(FloxValue::Boolean(true)));
+    assert_eq!(eval("done || count == 3.0"), Ok(FloxValue::Boolean(true)));
+    assert_eq!(
+        eval("\"a\" < \"b\" == true"),
+        Err(FloxError::new(
+            10,
+            "comparisons can not be chained, use parentheses"
+        ))
+    );
+    assert_eq!(
+        eval("(\"a\" < \"b\") == true"),
+        Ok(FloxValue::Boolean(true))
+    );
+    assert_eq!(eval("upper(trim(name)) + \"!\""), string("URSULA!"));
+    assert_eq!(
+        eval("substring(name, 2, 3) + string(count)"),
+        string("Urs3")
+    );
+    assert_eq!(eval("replace(\"a-b\", \"-\", \"\\\"\")"), string("a\"b"));
+    assert_eq!(
+        eval("len(name) > 5 && starts_with(trim(name), \"U\")"),
+        Ok(FloxValue::Boolean(true))
+    );
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        error(""),
+        "column 1: expected an expression, found the end of the expression"
+    );
+    assert_eq!(
+        error("1 +"),
+        "column 4: expected an expression, found the end of the expression"
+    );
+    assert_eq!(
+        error("(1 + 2"),
+        "column 7: expected `)`, found the end of the expression"
+    );
+    assert_eq!(
+        error("count count"),
+        "column 7: expected an operator, found `count`"
+    );
+    assert_eq!(error("count = 1"), "column 7: unexpected character `=`");
+    assert_eq!(error("\"open"), "column 1: the string is not closed");
+    assert_eq!(error("99999999999"), "column 1: the number is too large");
+    let nested =
+        |depth: usize, open: &str, close: &str| open.repeat(depth) + "1" + &close.repeat(depth);
+    assert_eq!(eval(&nested(64, "(", ")")), Ok(FloxValue::Integer(1)));
+    for (open, close) in &[("(", ")"), ("-", ""), ("!", "")] {
+        assert_eq!(
+            error(&nested(300, open, close)),
+            "column 65: the expression is nested too deeply"
+        );
+    }
+    assert_eq!(
+        error(&nested(300, "string(", ")")),
+        "column 455: the expression is nested too deeply"
+    );
+
+    assert_eq!(error("1 + nothing"), "column 5: unknown variable nothing");
+    assert_eq!(
+        error("born"),
+        "column 1: variable born is a date, which expressions can not use"
+    );
+    assert_eq!(
+        error("name - 1"),
+        "column 6: - can not be applied to string and integer"
+    );
+    assert_eq!(
+        error("done && 1"),
+        "column 9: expected boolean, found integer"
+    );
+    assert_eq!(error("shout(name)"), "column 1: unknown function shout");
+    assert_eq!(
+        error("len(name, 1)"),
+        "column 1: len takes 1 argument(s), not 2"
+    );
+    assert_eq!(
+        error("len(count)"),
+        "column 5: expected string, found integer"
+    );
+
+    assert_eq!(error("count / (count - 3)"), "column 7: division by zero");
+    assert_eq!(
+        error("2147483647 + count"),
+        "column 12: the integer is out of range"
+    );
+    // Unchecked expressions fail the same way when they are evaluated
+    let values = values(&definitions());
+    for (expression, message) in &[
+        ("len()", "column 1: len takes 1 argument(s), not 0"),
+        (
+            "substring(name, 1)",
+            "column 1: substring takes 3 argument(s), not 2",
+        ),
+        (
+            "string(1, 2)",
+            "column 1: string takes 1 argument(s), not 2",
+        ),
+        ("shout(name)", "column 1: unknown function shout"),
+    ] {
+        let err = evaluate(&parse(expression).unwrap(), &values).unwrap_err();
+        assert_eq!(err.to_string(), *message);
+    }
+
+    let node = parse("count * 1.5").unwrap();
+    assert_eq!(check(&node, &definitions()), Ok(FloxType::Float));
+}
+
+#[test]
+fn test_run() {
+    let definitions = definitions();
+    let mut parameters = FloxParameters {
+        expression: "count / 4".to_string(),
+        returns: variable(5, "ratio", float()),
+    };
+    let value = run(&parameters, &definitions, &values(&definitions));
+    assert_eq!(
+        value,
+        Ok(VTypeContainer::VTypeFloatContainer(VTypeFloatContainer {
+            value: 0.0
+        }))
+    );
+
+    parameters.returns = variable(5, "ratio", integer(0));
+    let err = run(&parameters, &definitions, &values(&definitions)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "column 1: variable ratio is of type float, the node returns integer"
+    );
+
+    parameters.returns = variable(6, "average", float());
+    let err = run(&parameters, &definitions, &values(&definitions)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "column 1: the node returns unknown variable average"
+    );
+}
+
+#[test]
+fn test_run_flox_node() {
+    let mut xflow = XFlow {
+        variables: definitions(),
+        ..XFlow::default()
+    };
+    xflow.variables.output.remove(0);
+    let mut node = xflow.nodes[0].clone();
+    node.id = 3;
+    node.nodetype = XFlowNodeType::Flox;
+    node.parameters = XFlowNodeParameters::Flox(FloxParameters {
+        expression: "count / 2.0".to_string(),
+        returns: variable(5, "ratio", float()),
+    });
+    xflow.nodes.push(node);
+    xflow.edges = vec![
+        XFlowEdge {
+            source: 1,
+            target: 3,
+        },
+        XFlowEdge {
+            source: 3,
+            target: 2,
+        },
+    ];
+
+    let mut input = Map::new();
+    input.insert("count".to_string(), json!(5));
+    let output = xflow::run(&xflow, &input).unwrap();
+    assert_eq!(output.get("ratio"), Some(&json!(2.5)));
+
+    input.insert("count".to_string(), json!(0));
+    if let XFlowNodeParameters::Flox(parameters) = &mut xflow.nodes[2].parameters {
+        parameters.expression = "1 / count".to_string();
+    }
+    let err = xflow::run(&xflow, &input).unwrap_err();
+    assert_eq!(err.to_string(), "node 3: column 3: division by zero");
+}
+
+#[test]
+fn test_nodes_using() {
+    let node = parse("len(name) + count * count > limit(name)").unwrap();
+    assert_eq!(node.variables(), vec!["name", "count"]);
+
+    let mut xflow = XFlow {
+        variables: definitions(),
+        ..XFlow::default()
+    };
+    let mut node = xflow.nodes[0].clone();
+    node.id = 3;
+    node.nodetype = XFlowNodeType::Flox;
+    node.parameters = XFlowNodeParameters::Flox(FloxParameters {
+        expression: "count / 2.0".to_string(),
+        returns: variable(5, "ratio", float()),
+    });
+    xflow.nodes.push(node);
+    assert_eq!(nodes_using(&xflow, "count"), vec![3]);
+    assert_eq!(nodes_using(&xflow, "ratio"), vec![3]);
+    assert_eq!(nodes_using(&xflow, "name"), Vec::<i32>::new());
+}
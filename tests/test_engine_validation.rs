@@ -0,0 +1,102 @@
+extern crate env_logger;
+
+extern crate gearsx;
+use gearsx::engine::events::FlowRunner;
+use gearsx::engine::validation::{validate_record, validation_xflows};
+use gearsx::structure::common::DocumentReference;
+use gearsx::structure::data::{VType, VTypeString};
+use gearsx::structure::domain::{Domain, Entity, Validation};
+use gearsx::structure::record::RecordViolation;
+use gearsx::structure::xflow::XFlowDocument;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// Passes values that are not empty strings
+struct NotEmptyRunner;
+
+impl FlowRunner for NotEmptyRunner {
+    fn run(
+        &self,
+        _xflow: &XFlowDocument,
+        input: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, String> {
+        assert_eq!(input["entity"], json!("author"));
+        assert!(input["record"].get("id").is_some());
+        let mut output = Map::new();
+        output.insert("valid".to_string(), json!(input["value"] != json!("")));
+        Ok(output)
+    }
+}
+
+fn validation(xflow: Uuid, message: &str) -> Validation {
+    Validation {
+        message: message.to_string(),
+        xflow: DocumentReference {
+            id: xflow,
+            doctype: "xflow".to_string(),
+        },
+    }
+}
+
+fn author(not_empty: &XFlowDocument) -> Entity {
+    let mut domain = Domain::default();
+    let author = domain.add_entity("author").unwrap();
+    let string = VType::VTypeString(VTypeString { default: None });
+    domain
+        .add_attribute(author.id, "name", string.clone())
+        .unwrap();
+    domain.add_attribute(author.id, "bio", string).unwrap();
+
+    let mut author = domain.entities.pop().unwrap();
+    author.attributes[0]
+        .validations
+        .push(validation(not_empty.id, "a name is needed"));
+    author.attributes[1]
+        .validations
+        .push(validation(not_empty.id, "a bio is needed"));
+    author.attributes[1]
+        .validations
+        .push(validation(Uuid::nil(), "never checked"));
+    author
+}
+
+#[test]
+fn test_validate_record() {
+    let _ = env_logger::try_init();
+
+    let not_empty = XFlowDocument::default();
+    let author = author(&not_empty);
+    assert_eq!(validation_xflows(&author), vec![not_empty.id, Uuid::nil()]);
+
+    let xflows = vec![not_empty];
+    let id = Uuid::new_v4();
+    let violations = validate_record(
+        &author,
+        Some(&id),
+        &json!({"name": ""}),
+        &xflows,
+        &NotEmptyRunner,
+    );
+    assert_eq!(
+        violations,
+        vec![RecordViolation::new(Some("name"), "a name is needed")]
+    );
+
+    let violations = validate_record(
+        &author,
+        Some(&id),
+        &json!({"name": "Ursula", "bio": ""}),
+        &xflows,
+        &NotEmptyRunner,
+    );
+    assert_eq!(
+        violations,
+        vec![
+            RecordViolation::new(Some("bio"), "a bio is needed"),
+            RecordViolation::new(
+                Some("bio"),
+                "the validation could not be run: xflow 00000000-0000-0000-0000-000000000000 does not exist"
+            ),
+        ]
+    );
+}
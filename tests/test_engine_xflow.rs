@@ -0,0 +1,113 @@
+extern crate gearsx;
+use gearsx::engine::xflow::{run, XFlowRunError};
+use gearsx::structure::data::{VType, VTypeBoolean, VTypeInteger, VTypeString, VariableDefinition};
+use gearsx::structure::xflow::{
+    CallParameters, XFlow, XFlowBranch, XFlowEdge, XFlowNodeParameters, XFlowNodeType,
+};
+use serde_json::{json, Map, Value};
+
+fn variable(id: i32, name: &str, vtype: VType) -> VariableDefinition {
+    VariableDefinition {
+        id,
+        name: name.to_string(),
+        vtype,
+    }
+}
+
+fn boolean(default: bool) -> VType {
+    VType::VTypeBoolean(VTypeBoolean {
+        default: Some(default),
+    })
+}
+
+fn edge(source: i32, target: i32) -> XFlowEdge {
+    XFlowEdge { source, target }
+}
+
+fn input(value: Value) -> Map<String, Value> {
+    value.as_object().unwrap().clone()
+}
+
+/// start -> 3 -> end, with a call node 4 after 3 when `fail` is true
+fn flow() -> XFlow {
+    let mut xflow = XFlow::default();
+    xflow
+        .variables
+        .input
+        .push(variable(5, "fail", boolean(false)));
+    xflow.variables.output.push(variable(
+        6,
+        "greeting",
+        VType::VTypeString(VTypeString {
+            default: Some("hello".to_string()),
+        }),
+    ));
+    let mut choice = xflow.nodes[0].clone();
+    choice.id = 3;
+    choice.action = "choice".to_string();
+    let mut call = choice.clone();
+    call.id = 4;
+    call.nodetype = XFlowNodeType::Call;
+    call.parameters = XFlowNodeParameters::Call(CallParameters {});
+    xflow.nodes.push(choice);
+    xflow.nodes.push(call);
+    xflow.edges = vec![edge(1, 3), edge(3, 4), edge(3, 2), edge(4, 2)];
+    xflow.branches.push(XFlowBranch {
+        edge: edge(3, 4),
+        xvar: variable(5, "fail", boolean(true)),
+    });
+    xflow
+}
+
+#[test]
+fn test_run() {
+    let output = run(&XFlow::default(), &Map::new()).unwrap();
+    assert_eq!(output, Map::new());
+
+    let xflow = flow();
+    let output = run(&xflow, &Map::new()).unwrap();
+    assert_eq!(output, input(json!({"greeting": "hello"})));
+
+    let err = run(&xflow, &input(json!({"fail": true}))).unwrap_err();
+    assert_eq!(err.to_string(), "node 4: Call nodes can not be run yet");
+
+    let err = run(&xflow, &input(json!({"fail": "yes"}))).unwrap_err();
+    assert!(matches!(err, XFlowRunError::Input(ref name, _) if name == "fail"));
+}
+
+#[test]
+fn test_run_branches() {
+    let mut xflow = flow();
+    xflow.branches.push(XFlowBranch {
+        edge: edge(3, 2),
+        xvar: variable(5, "fail", boolean(false)),
+    });
+    assert!(run(&xflow, &Map::new()).is_ok());
+
+    // Both edges match
+    xflow.branches[1].xvar = variable(5, "fail", boolean(true));
+    let err = run(&xflow, &input(json!({"fail": true}))).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "node 3: the branches of more than one edge match"
+    );
+
+    // Neither edge matches, and there is no edge to fall back on
+    let err = run(&xflow, &Map::new()).unwrap_err();
+    assert!(matches!(err, XFlowRunError::Node(3, _)));
+
+    xflow.branches[1].xvar = variable(
+        5,
+        "fail",
+        VType::VTypeInteger(VTypeInteger {
+            default: Some(1),
+            min: None,
+            max: None,
+        }),
+    );
+    let err = run(&xflow, &Map::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "node 3: the branch on variable fail is for integer values"
+    );
+}
@@ -0,0 +1,141 @@
+extern crate gearsx;
+
+use gearsx::generator::erd::{document_to_erd, domain_to_erd, relationships, ErdFormat};
+use gearsx::structure::data::{VType, VTypeList};
+use gearsx::structure::domain::*;
+
+mod common;
+use crate::common::string;
+
+/// The common library, with unique author names, the tags of a book and
+/// reviews of books
+fn library_domain() -> Domain {
+    let mut domain = common::library_domain(&["fiction", "poetry"]);
+    let author = domain.entities[0].id;
+    let name = domain.entities[0].attributes[0].id;
+    domain
+        .set_attribute_constraints(
+            author,
+            name,
+            AttributeConstraints {
+                required: true,
+                unique: true,
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    let book = domain.entities[1].id;
+    domain
+        .add_attribute(
+            book,
+            "tags",
+            VType::VTypeList(VTypeList {
+                item: Box::new(string()),
+            }),
+        )
+        .unwrap();
+
+    let review = domain.add_entity("review").unwrap();
+    domain
+        .add_reference(review.id, "book", ReferenceType::BelongsTo, "book")
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_erd_plantuml() {
+    let expected = "@startuml\n\
+title library\n\
+\n\
+entity author {\n\
+\x20 * id : uuid <<PK>>\n\
+\x20 --\n\
+\x20 * name : string\n\
+}\n\
+\n\
+entity book {\n\
+\x20 * id : uuid <<PK>>\n\
+\x20 --\n\
+\x20 genre : genre\n\
+\x20 tags : string[]\n\
+}\n\
+\n\
+entity review {\n\
+\x20 * id : uuid <<PK>>\n\
+\x20 --\n\
+}\n\
+\n\
+author ||--o{ book : books\n\
+review }o--|| book : book\n\
+\n\
+@enduml\n";
+    let doc = DomainDocument {
+        name: "library".into(),
+        body: library_domain(),
+        ..DomainDocument::default()
+    };
+    assert_eq!(document_to_erd(&doc, ErdFormat::PlantUml), expected);
+}
+
+#[test]
+fn test_erd_mermaid() {
+    let expected = "erDiagram\n\
+\x20 author {\n\
+\x20   uuid id PK\n\
+\x20   string name UK\n\
+\x20 }\n\
+\x20 book {\n\
+\x20   uuid id PK\n\
+\x20   genre genre\n\
+\x20   string[] tags\n\
+\x20 }\n\
+\x20 review {\n\
+\x20   uuid id PK\n\
+\x20 }\n\
+\x20 author ||--o{ book : \"books\"\n\
+\x20 review }o--|| book : \"book\"\n";
+    assert_eq!(
+        domain_to_erd(&library_domain(), ErdFormat::Mermaid, None),
+        expected
+    );
+}
+
+#[test]
+fn test_erd_dot() {
+    let expected = "digraph \"domain\" {\n\
+\x20 rankdir=LR;\n\
+\x20 node [shape=record];\n\
+\x20 \"author\" [label=\"{author|id : uuid\\lname : string\\l}\"];\n\
+\x20 \"book\" [label=\"{book|id : uuid\\lgenre : genre\\ltags : string[]\\l}\"];\n\
+\x20 \"review\" [label=\"{review|id : uuid\\l}\"];\n\
+\x20 \"author\" -> \"book\" [label=\"books\", dir=both, arrowtail=teetee, arrowhead=crowodot];\n\
+\x20 \"review\" -> \"book\" [label=\"book\", dir=both, arrowtail=crowodot, arrowhead=teetee];\n\
+}\n";
+    assert_eq!(
+        domain_to_erd(&library_domain(), ErdFormat::Dot, None),
+        expected
+    );
+}
+
+#[test]
+fn test_erd_many_to_many_drawn_once() {
+    let mut domain = Domain::default();
+    let book = domain.add_entity("book").unwrap();
+    let shelf = domain.add_entity("shelf").unwrap();
+    domain
+        .add_reference(shelf.id, "books", ReferenceType::ManyToMany, "book")
+        .unwrap();
+    domain
+        .add_reference(book.id, "shelves", ReferenceType::ManyToMany, "shelf")
+        .unwrap();
+
+    let names: Vec<String> = relationships(&domain)
+        .iter()
+        .filter(|r| r.to == "book" || r.to == "shelf")
+        .filter(|r| r.from == "book" || r.from == "shelf")
+        .map(|r| r.name.clone())
+        .collect();
+    assert_eq!(names, vec!["shelves".to_string()]);
+    assert_eq!("dot".parse::<ErdFormat>(), Ok(ErdFormat::Dot));
+    assert!("svg".parse::<ErdFormat>().is_err());
+}
@@ -0,0 +1,209 @@
+extern crate env_logger;
+
+extern crate chrono;
+extern crate gearsx;
+#[macro_use]
+extern crate serde_json;
+use chrono::NaiveDate;
+use gearsx::generator::json_schema::{
+    document_to_json_schema, domain_to_json_schema, vtype_schema,
+};
+use gearsx::generator::GeneratorError;
+use gearsx::structure::data::{
+    VType, VTypeDateTime, VTypeDecimal, VTypeEnum, VTypeInteger, VTypeList,
+};
+use gearsx::structure::domain::*;
+
+mod common;
+use crate::common::string;
+
+/// The common library, with constraints on author names, a default genre
+/// and more attributes of books
+fn library_domain() -> Domain {
+    let mut domain = common::library_domain(&["fiction", "poetry"]);
+    let author = domain.entities[0].id;
+    let name = domain.entities[0].attributes[0].id;
+    domain
+        .set_attribute_constraints(
+            author,
+            name,
+            AttributeConstraints {
+                required: true,
+                min_length: Some(1),
+                pattern: Some("^[A-Z]".into()),
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+
+    let book = domain.entities[1].clone();
+    let genre = VType::VTypeEnum(VTypeEnum {
+        name: "genre".into(),
+        default: Some("fiction".into()),
+    });
+    domain
+        .update_attribute(book.id, book.attributes[0].id, None, Some(genre))
+        .unwrap();
+    domain
+        .add_attribute(
+            book.id,
+            "pages",
+            VType::VTypeInteger(VTypeInteger {
+                default: Some(1),
+                min: Some(1),
+                max: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            book.id,
+            "price",
+            VType::VTypeDecimal(VTypeDecimal {
+                default: Some("9.99".into()),
+                precision: Some(6),
+                scale: Some(2),
+            }),
+        )
+        .unwrap();
+    let tags = domain
+        .add_attribute(
+            book.id,
+            "tags",
+            VType::VTypeList(VTypeList {
+                item: Box::new(string()),
+            }),
+        )
+        .unwrap();
+    domain
+        .set_attribute_constraints(
+            book.id,
+            tags.id,
+            AttributeConstraints {
+                max_length: Some(5),
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+
+    domain
+}
+
+#[test]
+fn test_domain_to_json_schema() {
+    let _ = env_logger::try_init();
+
+    let defs = domain_to_json_schema(&library_domain()).unwrap();
+    let id = json!({ "type": "string", "format": "uuid", "readOnly": true });
+    assert_eq!(
+        serde_json::Value::Object(defs),
+        json!({
+            "genre": {
+                "title": "genre",
+                "type": "string",
+                "enum": ["fiction", "poetry"],
+            },
+            "author": {
+                "title": "author",
+                "type": "object",
+                "properties": {
+                    "id": id,
+                    "name": {
+                        "type": "string",
+                        "minLength": 1,
+                        "pattern": "^[A-Z]",
+                    },
+                    "books": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/book" },
+                    },
+                },
+                "required": ["name"],
+                "additionalProperties": false,
+            },
+            "book": {
+                "title": "book",
+                "type": "object",
+                "properties": {
+                    "id": id,
+                    "genre": { "$ref": "#/$defs/genre", "default": "fiction" },
+                    "pages": { "type": "integer", "minimum": 1, "default": 1 },
+                    "price": {
+                        "type": "string",
+                        "pattern": "^[-+]?[0-9]{1,4}(\\.[0-9]{1,2})?$",
+                        "default": "9.99",
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "maxItems": 5,
+                    },
+                    "author": { "$ref": "#/$defs/author" },
+                },
+                "additionalProperties": false,
+            },
+        })
+    );
+}
+
+#[test]
+fn test_document_to_json_schema() {
+    let _ = env_logger::try_init();
+
+    let doc = DomainDocument {
+        name: "library".into(),
+        body: library_domain(),
+        ..DomainDocument::default()
+    };
+    let schema = document_to_json_schema(&doc).unwrap();
+    assert_eq!(
+        schema["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    assert_eq!(schema["$id"], json!(format!("urn:uuid:{}", doc.id)));
+    assert_eq!(schema["title"], "library");
+    assert!(schema["$defs"]["book"].is_object());
+}
+
+#[test]
+fn test_domain_to_json_schema_refuses_name_clashes() {
+    let _ = env_logger::try_init();
+
+    let mut domain = library_domain();
+    let book = domain.entities[1].id;
+    domain.add_attribute(book, "author", string()).unwrap();
+
+    assert_eq!(
+        domain_to_json_schema(&domain),
+        Err(GeneratorError::InvalidDomain(vec![
+            "Reference book.author has the name of an attribute".to_string()
+        ]))
+    );
+
+    let mut domain = library_domain();
+    let author = domain.entities[0].id;
+    domain
+        .add_reference(author, "id", ReferenceType::HasOne, "book")
+        .unwrap();
+    assert_eq!(
+        domain_to_json_schema(&domain),
+        Err(GeneratorError::InvalidDomain(vec![
+            "Reference author.id clashes with the primary key".to_string()
+        ]))
+    );
+}
+
+#[test]
+fn test_date_time_schema() {
+    let published = VType::VTypeDateTime(VTypeDateTime {
+        default: Some(NaiveDate::from_ymd(2020, 2, 1).and_hms(9, 15, 8)),
+    });
+    assert_eq!(
+        serde_json::Value::Object(vtype_schema(&published)),
+        json!({
+            "type": "string",
+            "pattern": "^[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}(\\.[0-9]+)?$",
+            "default": "2020-02-01T09:15:08",
+        })
+    );
+}
@@ -0,0 +1,253 @@
+extern crate env_logger;
+
+extern crate chrono;
+extern crate gearsx;
+use chrono::NaiveDate;
+use gearsx::generator::migration::{domain_migration, Migration};
+use gearsx::structure::data::{VType, VTypeInteger};
+use gearsx::structure::domain::*;
+use gearsx::structure::domain_diff::{DomainChange, DomainDiff};
+
+mod common;
+use crate::common::string;
+
+fn blog_domain() -> Domain {
+    let mut domain = Domain::default();
+    let author = domain.add_entity("author").unwrap();
+    let email = domain.add_attribute(author.id, "email", string()).unwrap();
+    domain
+        .set_attribute_constraints(
+            author.id,
+            email.id,
+            AttributeConstraints {
+                unique: true,
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    domain
+        .add_attribute(author.id, "nickname", string())
+        .unwrap();
+
+    let post = domain.add_entity("post").unwrap();
+    domain.add_attribute(post.id, "title", string()).unwrap();
+    domain
+        .add_reference(post.id, "author", ReferenceType::BelongsTo, "author")
+        .unwrap();
+    domain
+}
+
+/// `blog_domain` with authors renamed to writers, and a few attribute
+/// changes
+fn changed_blog_domain() -> Domain {
+    let mut domain = blog_domain();
+    let writer = domain.entities[0].id;
+    let post = domain.entities[1].id;
+    domain.rename_entity(writer, "writer").unwrap();
+
+    let email = domain.entities[0].attributes[0].id;
+    domain
+        .update_attribute(writer, email, Some("mail"), None)
+        .unwrap();
+    let nickname = domain.entities[0].attributes[1].id;
+    domain.remove_attribute(writer, nickname).unwrap();
+
+    let title = domain.entities[1].attributes[0].id;
+    domain
+        .set_attribute_constraints(
+            post,
+            title,
+            AttributeConstraints {
+                required: true,
+                max_length: Some(80),
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            post,
+            "likes",
+            VType::VTypeInteger(VTypeInteger {
+                default: Some(0),
+                min: None,
+                max: None,
+            }),
+        )
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_domain_diff() {
+    let _ = env_logger::try_init();
+
+    let from = blog_domain();
+    let to = changed_blog_domain();
+    assert!(DomainDiff::between(&from, &from).is_empty());
+
+    let diff = DomainDiff::between(&from, &to);
+    let summary: Vec<String> = diff
+        .changes
+        .iter()
+        .map(|change| match change {
+            DomainChange::RenameEntity { from, to } => format!("rename {} {}", from.name, to.name),
+            DomainChange::RenameAttribute { entity, from, to } => {
+                format!("rename {}.{} {}", entity, from.name, to.name)
+            }
+            DomainChange::RemoveAttribute { entity, attribute } => {
+                format!("remove {}.{}", entity, attribute.name)
+            }
+            DomainChange::RetypeAttribute { entity, to, .. } => {
+                format!("retype {}.{}", entity, to.name)
+            }
+            DomainChange::AddAttribute { entity, attribute } => {
+                format!("add {}.{}", entity, attribute.name)
+            }
+            other => format!("{:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            "rename author writer",
+            "rename writer.email mail",
+            "remove writer.nickname",
+            "retype post.title",
+            "add post.likes",
+        ]
+    );
+}
+
+#[test]
+fn test_domain_migration() {
+    let _ = env_logger::try_init();
+
+    let migration = domain_migration(&blog_domain(), &changed_blog_domain()).unwrap();
+    assert_eq!(
+        migration.up,
+        r#"ALTER TABLE "author" RENAME TO "writer";
+
+ALTER TABLE "writer" RENAME CONSTRAINT "author_email_key" TO "writer_email_key";
+
+ALTER TABLE "writer" RENAME COLUMN "email" TO "mail";
+
+ALTER TABLE "writer" RENAME CONSTRAINT "writer_email_key" TO "writer_mail_key";
+
+ALTER TABLE "writer" DROP COLUMN "nickname";
+
+ALTER TABLE "post" ALTER COLUMN "title" SET NOT NULL;
+
+ALTER TABLE "post" ADD CONSTRAINT "post_title_check" CHECK (char_length("title") <= 80);
+
+ALTER TABLE "post" ADD COLUMN "likes" INTEGER DEFAULT 0;
+"#
+    );
+    assert_eq!(
+        migration.down,
+        r#"ALTER TABLE "writer" RENAME TO "author";
+
+ALTER TABLE "author" RENAME CONSTRAINT "writer_mail_key" TO "author_mail_key";
+
+ALTER TABLE "author" RENAME COLUMN "mail" TO "email";
+
+ALTER TABLE "author" RENAME CONSTRAINT "author_mail_key" TO "author_email_key";
+
+ALTER TABLE "post" DROP COLUMN "likes";
+
+ALTER TABLE "author" ADD COLUMN "nickname" TEXT;
+
+ALTER TABLE "post" DROP CONSTRAINT "post_title_check";
+
+ALTER TABLE "post" ALTER COLUMN "title" DROP NOT NULL;
+"#
+    );
+
+    let unchanged = domain_migration(&blog_domain(), &blog_domain()).unwrap();
+    assert!(unchanged.is_empty());
+}
+
+#[test]
+fn test_domain_migration_of_new_entities() {
+    let _ = env_logger::try_init();
+
+    let from = blog_domain();
+    let mut to = blog_domain();
+    let comment = to.add_entity("comment").unwrap();
+    to.add_attribute(comment.id, "body", string()).unwrap();
+    to.add_reference(comment.id, "post", ReferenceType::BelongsTo, "post")
+        .unwrap();
+
+    let migration = domain_migration(&from, &to).unwrap();
+    assert_eq!(
+        migration.up,
+        r#"CREATE TABLE "comment" (
+  "id" UUID PRIMARY KEY NOT NULL,
+  "body" TEXT
+);
+
+ALTER TABLE "comment" ADD COLUMN "post_id" UUID,
+  ADD CONSTRAINT "comment_post_id_fkey" FOREIGN KEY ("post_id") REFERENCES "post" ("id");
+"#
+    );
+    assert_eq!(
+        migration.down,
+        r#"ALTER TABLE "comment" DROP CONSTRAINT "comment_post_id_fkey";
+
+DROP TABLE "comment";
+"#
+    );
+}
+
+#[test]
+fn test_domain_migration_of_a_second_has_many() {
+    let _ = env_logger::try_init();
+
+    let mut from = blog_domain();
+    let post = from.entities[1].id;
+    let comment = from.add_entity("comment").unwrap();
+    from.add_attribute(comment.id, "body", string()).unwrap();
+    from.add_reference(post, "comments", ReferenceType::HasMany, "comment")
+        .unwrap();
+    let mut to = from.clone();
+    to.add_reference(post, "drafts", ReferenceType::HasMany, "comment")
+        .unwrap();
+
+    let migration = domain_migration(&from, &to).unwrap();
+    assert_eq!(
+        migration.up,
+        r#"ALTER TABLE "comment" ADD COLUMN "drafts_id" UUID,
+  ADD CONSTRAINT "comment_drafts_id_fkey" FOREIGN KEY ("drafts_id") REFERENCES "post" ("id");
+"#
+    );
+    assert_eq!(
+        migration.down,
+        r#"ALTER TABLE "comment" DROP COLUMN "drafts_id";
+"#
+    );
+}
+
+#[test]
+fn test_migration_layout() {
+    let _ = env_logger::try_init();
+
+    let at = NaiveDate::from_ymd(2020, 2, 1).and_hms(9, 15, 8);
+    assert_eq!(
+        Migration::directory_name("rename_authors", &at),
+        "2020-02-01-091508_rename_authors"
+    );
+
+    let migration = domain_migration(&blog_domain(), &changed_blog_domain()).unwrap();
+    let dir = std::env::temp_dir().join(format!("gearsx-migrations-{}", std::process::id()));
+    let path = migration.write(&dir, "rename_authors", &at).unwrap();
+    assert_eq!(path, dir.join("2020-02-01-091508_rename_authors"));
+    assert_eq!(
+        std::fs::read_to_string(path.join("up.sql")).unwrap(),
+        migration.up
+    );
+    assert_eq!(
+        std::fs::read_to_string(path.join("down.sql")).unwrap(),
+        migration.down
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
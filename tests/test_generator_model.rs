@@ -0,0 +1,167 @@
+extern crate env_logger;
+
+extern crate gearsx;
+use gearsx::generator::model::{
+    document_models, domain_to_rust, domain_to_typescript, pascal_case, ReferenceStyle,
+};
+use gearsx::generator::GeneratorError;
+use gearsx::structure::data::{VType, VTypeDecimal, VTypeList};
+use gearsx::structure::domain::*;
+
+mod common;
+use crate::common::string;
+
+/// The common library, with books as editions, their price and a property
+/// named like a Rust keyword
+fn library_domain() -> Domain {
+    let mut domain = common::library_domain(&["fiction", "short story"]);
+    let author = domain.entities[0].id;
+    let name = domain.entities[0].attributes[0].id;
+    domain
+        .set_attribute_constraints(
+            author,
+            name,
+            AttributeConstraints {
+                required: true,
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    let book = domain.entities[1].id;
+    domain.rename_entity(book, "book_edition").unwrap();
+    domain
+        .add_attribute(
+            book,
+            "price",
+            VType::VTypeDecimal(VTypeDecimal {
+                default: None,
+                precision: Some(8),
+                scale: Some(2),
+            }),
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            book,
+            "type",
+            VType::VTypeList(VTypeList {
+                item: Box::new(string()),
+            }),
+        )
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_domain_to_rust() {
+    let _ = env_logger::try_init();
+
+    let expected = r#"use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Genre {
+    #[serde(rename = "fiction")]
+    Fiction,
+    #[serde(rename = "short story")]
+    ShortStory,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Author {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub books_ids: Vec<uuid::Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BookEdition {
+    pub id: uuid::Uuid,
+    pub genre: Option<Genre>,
+    pub price: Option<String>,
+    pub r#type: Option<Vec<String>>,
+    pub author_id: Option<uuid::Uuid>,
+}
+"#;
+    assert_eq!(
+        domain_to_rust(&library_domain(), ReferenceStyle::Ids).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_domain_to_typescript() {
+    let _ = env_logger::try_init();
+
+    let expected = r#"export type Genre = "fiction" | "short story";
+
+export interface Author {
+  id: string;
+  name: string;
+  books: BookEdition[];
+}
+
+export interface BookEdition {
+  id: string;
+  genre?: Genre;
+  price?: string;
+  type?: string[];
+  author?: Author;
+}
+"#;
+    assert_eq!(
+        domain_to_typescript(&library_domain(), ReferenceStyle::Nested).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_models_of_clashing_names() {
+    let _ = env_logger::try_init();
+
+    assert_eq!(pascal_case("book shelf"), "BookShelf");
+    assert_eq!(pascal_case("2nd_edition"), "_2ndEdition");
+
+    let mut domain = library_domain();
+    let author = domain.add_entity("Author").unwrap();
+    domain
+        .add_attribute(author.id, "books_ids", string())
+        .unwrap();
+    domain
+        .add_reference(author.id, "books", ReferenceType::HasMany, "book_edition")
+        .unwrap();
+    assert_eq!(
+        domain_to_rust(&domain, ReferenceStyle::Ids),
+        Err(GeneratorError::InvalidDomain(vec![
+            "Entity Author clashes with another type".to_string(),
+            "Property Author.books_ids clashes with another property".to_string(),
+        ]))
+    );
+}
+
+#[test]
+fn test_models_write() {
+    let _ = env_logger::try_init();
+
+    let doc = DomainDocument {
+        name: "library".into(),
+        body: library_domain(),
+        ..DomainDocument::default()
+    };
+    let models = document_models(&doc, ReferenceStyle::Ids).unwrap();
+    let header = format!(
+        "// Generated from domain \"library\" ({}) version {}\n\n",
+        doc.id, doc.version
+    );
+    assert!(models.rust.starts_with(&header));
+    assert!(models.typescript.starts_with(&header));
+
+    let dir = std::env::temp_dir().join(format!("gearsx-models-{}", std::process::id()));
+    let paths = models.write(&dir).unwrap();
+    assert_eq!(paths, vec![dir.join("models.rs"), dir.join("models.ts")]);
+    assert_eq!(std::fs::read_to_string(&paths[0]).unwrap(), models.rust);
+    assert_eq!(
+        std::fs::read_to_string(&paths[1]).unwrap(),
+        models.typescript
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,141 @@
+extern crate env_logger;
+
+extern crate gearsx;
+use gearsx::generator::sql::{document_to_ddl, domain_to_ddl};
+use gearsx::generator::GeneratorError;
+use gearsx::structure::domain::*;
+
+mod common;
+use crate::common::string;
+
+/// The common shop, with a transient order total and the orders of a
+/// customer
+fn shop_domain() -> Domain {
+    let mut domain = common::shop_domain();
+    let customer = domain.entities[0].id;
+    let order = domain.entities[1].id;
+    let total = domain.add_attribute(order, "total", string()).unwrap();
+    domain
+        .set_attribute_storage(order, total.id, AttributeStorage::Transient)
+        .unwrap();
+    domain
+        .add_reference(customer, "orders", ReferenceType::HasMany, "order")
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_domain_to_ddl() {
+    let _ = env_logger::try_init();
+
+    let ddl = domain_to_ddl(&shop_domain()).unwrap();
+    assert_eq!(
+        ddl,
+        r#"CREATE TYPE "order_status" AS ENUM ('open', 'shipped');
+
+CREATE TABLE "customer" (
+  "id" UUID PRIMARY KEY NOT NULL,
+  "email" TEXT NOT NULL CONSTRAINT "customer_email_key" UNIQUE CONSTRAINT "customer_email_check" CHECK (char_length("email") <= 64)
+);
+
+CREATE TABLE "order" (
+  "id" UUID PRIMARY KEY NOT NULL,
+  "status" "order_status" DEFAULT 'open',
+  "quantity" INTEGER DEFAULT 1 CONSTRAINT "order_quantity_check" CHECK ("quantity" >= 1),
+  "customer_id" UUID
+);
+
+CREATE INDEX "order_status_idx" ON "order" ("status");
+
+ALTER TABLE "order"
+  ADD CONSTRAINT "order_customer_id_fkey" FOREIGN KEY ("customer_id") REFERENCES "customer" ("id");
+"#
+    );
+    assert_eq!(domain_to_ddl(&shop_domain()).unwrap(), ddl);
+}
+
+#[test]
+fn test_document_to_ddl() {
+    let _ = env_logger::try_init();
+
+    let doc = DomainDocument {
+        name: "shop".into(),
+        body: shop_domain(),
+        ..DomainDocument::default()
+    };
+
+    let ddl = document_to_ddl(&doc).unwrap();
+    let header = format!("-- Generated from domain 'shop' ({}) version 0\n\n", doc.id);
+    assert!(ddl.starts_with(&header));
+}
+
+#[test]
+fn test_domain_to_ddl_refuses_invalid_domains() {
+    let _ = env_logger::try_init();
+
+    let mut domain = shop_domain();
+    let customer = domain.entities[0].id;
+    domain.add_attribute(customer, "id", string()).unwrap();
+
+    match domain_to_ddl(&domain) {
+        Err(GeneratorError::InvalidDomain(problems)) => assert_eq!(problems.len(), 1),
+        other => panic!("Expected an invalid domain, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_domain_to_ddl_foreign_key_names() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    let comment = domain.add_entity("comment").unwrap();
+    domain.add_attribute(comment.id, "body", string()).unwrap();
+    domain
+        .add_reference(post.id, "comments", ReferenceType::HasMany, "comment")
+        .unwrap();
+    domain
+        .add_reference(post.id, "drafts", ReferenceType::HasMany, "comment")
+        .unwrap();
+
+    // Without an inverse the columns are named after the references
+    let ddl = domain_to_ddl(&domain).unwrap();
+    assert!(ddl.contains(
+        r#"CREATE TABLE "comment" (
+  "id" UUID PRIMARY KEY NOT NULL,
+  "body" TEXT,
+  "comments_id" UUID,
+  "drafts_id" UUID
+);"#
+    ));
+    assert!(ddl.contains(r#"ADD CONSTRAINT "comment_comments_id_fkey""#));
+    assert!(ddl.contains(r#"ADD CONSTRAINT "comment_drafts_id_fkey""#));
+
+    // Another has_many of the same name on a second entity
+    let mut clashing = domain.clone();
+    let page = clashing.add_entity("page").unwrap();
+    clashing
+        .add_reference(page.id, "comments", ReferenceType::HasMany, "comment")
+        .unwrap();
+    assert_eq!(
+        domain_to_ddl(&clashing),
+        Err(GeneratorError::InvalidDomain(vec![
+            "Foreign key comment.comments_id stems from more than one reference".into()
+        ]))
+    );
+
+    // An attribute named like the column of a belongs_to
+    let mut clashing = domain.clone();
+    clashing
+        .add_attribute(comment.id, "post_id", string())
+        .unwrap();
+    clashing
+        .add_reference(comment.id, "post", ReferenceType::BelongsTo, "post")
+        .unwrap();
+    assert_eq!(
+        domain_to_ddl(&clashing),
+        Err(GeneratorError::InvalidDomain(vec![
+            "Foreign key comment.post_id clashes with an attribute".into()
+        ]))
+    );
+}
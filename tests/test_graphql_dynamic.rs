@@ -0,0 +1,171 @@
+extern crate env_logger;
+
+extern crate gearsx;
+use gearsx::graphql::dynamic::{create_record_schema, ProjectApi, ScalarKind};
+use gearsx::structure::data::{VType, VTypeInteger, VTypeList, VTypeString};
+use gearsx::structure::domain::*;
+use uuid::Uuid;
+
+fn shop_document() -> DomainDocument {
+    let mut domain = Domain::default();
+    let customer = domain.add_entity("customer").unwrap();
+    domain
+        .add_attribute(
+            customer.id,
+            "full_name",
+            VType::VTypeString(VTypeString { default: None }),
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            customer.id,
+            "lucky_numbers",
+            VType::VTypeList(VTypeList {
+                item: Box::new(VType::VTypeInteger(VTypeInteger {
+                    default: None,
+                    min: None,
+                    max: None,
+                })),
+            }),
+        )
+        .unwrap();
+    let integer = VType::VTypeInteger(VTypeInteger {
+        default: None,
+        min: None,
+        max: None,
+    });
+    let name_length = domain
+        .add_attribute(customer.id, "name_length", integer.clone())
+        .unwrap();
+    domain
+        .set_attribute_storage(
+            customer.id,
+            name_length.id,
+            AttributeStorage::Computed {
+                expression: "len(full_name)".to_string(),
+            },
+        )
+        .unwrap();
+    let password = domain
+        .add_attribute(customer.id, "password", integer)
+        .unwrap();
+    domain
+        .set_attribute_storage(customer.id, password.id, AttributeStorage::Transient)
+        .unwrap();
+    let order = domain.add_entity("order").unwrap();
+    domain
+        .add_reference(customer.id, "orders", ReferenceType::HasMany, "order")
+        .unwrap();
+    domain
+        .add_reference(order.id, "customer", ReferenceType::BelongsTo, "customer")
+        .unwrap();
+    // Named like a GraphQL type
+    domain.add_entity("string").unwrap();
+
+    DomainDocument {
+        name: "shop".to_string(),
+        body: domain,
+        ..DomainDocument::default()
+    }
+}
+
+#[test]
+fn test_project_api_layout() {
+    let _ = env_logger::try_init();
+
+    let api = ProjectApi::new(Uuid::new_v4(), vec![shop_document()]);
+    let names: Vec<&str> = api.entities.iter().map(|e| e.type_name.as_str()).collect();
+    assert_eq!(names, vec!["Customer", "Order", "ShopString"]);
+
+    let customer = &api.entities[0];
+    assert_eq!(customer.get_field(), "customer");
+    assert_eq!(customer.list_field(), "customerList");
+    assert_eq!(customer.create_field(), "createCustomer");
+    let attributes: Vec<(&str, &str, ScalarKind, bool)> = customer
+        .attributes
+        .iter()
+        .map(|a| (a.name.as_str(), a.property.as_str(), a.kind, a.list))
+        .collect();
+    assert_eq!(
+        attributes,
+        vec![
+            ("fullName", "full_name", ScalarKind::String, false),
+            ("luckyNumbers", "lucky_numbers", ScalarKind::Int, true),
+            ("nameLength", "name_length", ScalarKind::Int, false),
+        ]
+    );
+    let read_only: Vec<bool> = customer.attributes.iter().map(|a| a.read_only).collect();
+    assert_eq!(read_only, vec![false, false, true]);
+
+    let orders = &customer.references[0];
+    assert_eq!(orders.name, "orders");
+    assert_eq!(orders.ids_name, "ordersIds");
+    assert_eq!(orders.property, "orders_ids");
+    assert_eq!(orders.other, 1);
+    assert!(orders.many);
+    assert_eq!(orders.inverse, Some(("customer_id".to_string(), false)));
+
+    let customer_ref = &api.entities[1].references[0];
+    assert_eq!(customer_ref.ids_name, "customerId");
+    assert_eq!(customer_ref.inverse, None);
+}
+
+#[test]
+fn test_record_schema() {
+    let _ = env_logger::try_init();
+
+    let api = ProjectApi::new(Uuid::new_v4(), vec![shop_document()]);
+    let schema = create_record_schema(api);
+    let field_type = |type_name: &str, field: &str| {
+        schema
+            .schema
+            .concrete_type_by_name(type_name)
+            .and_then(|t| t.field_by_name(field))
+            .map(|f| f.field_type.to_string())
+    };
+
+    assert_eq!(field_type("Customer", "id"), Some("ID!".to_string()));
+    assert_eq!(
+        field_type("Customer", "fullName"),
+        Some("String".to_string())
+    );
+    assert_eq!(
+        field_type("Customer", "luckyNumbers"),
+        Some("[Int]".to_string())
+    );
+    assert_eq!(
+        field_type("Customer", "nameLength"),
+        Some("Int".to_string())
+    );
+    assert_eq!(field_type("Customer", "password"), None);
+    let arguments: Vec<String> = schema
+        .schema
+        .concrete_type_by_name("Mutation")
+        .and_then(|t| t.field_by_name("createCustomer"))
+        .and_then(|f| f.arguments.as_ref())
+        .map(|arguments| arguments.iter().map(|a| a.name.clone()).collect())
+        .unwrap_or_default();
+    assert_eq!(arguments, vec!["fullName", "luckyNumbers", "ordersIds"]);
+    assert_eq!(
+        field_type("Customer", "orders"),
+        Some("[Order!]!".to_string())
+    );
+    assert_eq!(
+        field_type("Order", "customer"),
+        Some("Customer".to_string())
+    );
+    assert_eq!(field_type("Order", "customerId"), Some("ID".to_string()));
+    assert_eq!(field_type("Query", "order"), Some("Order".to_string()));
+    assert_eq!(
+        field_type("Query", "orderList"),
+        Some("[Order!]!".to_string())
+    );
+    assert_eq!(
+        field_type("Mutation", "createOrder"),
+        Some("Order!".to_string())
+    );
+    assert_eq!(
+        field_type("Mutation", "deleteShopString"),
+        Some("Boolean!".to_string())
+    );
+}
@@ -0,0 +1,183 @@
+extern crate env_logger;
+
+extern crate gearsx;
+#[macro_use]
+extern crate serde_json;
+use gearsx::generator::json_schema::document_to_json_schema;
+use gearsx::generator::sql::domain_to_ddl;
+use gearsx::importer::json_schema::import_json_schema;
+use gearsx::importer::sql::import_sql;
+use gearsx::importer::ImportError;
+use gearsx::structure::data::{VType, VTypeEnum, VTypeInteger, VTypeList};
+use gearsx::structure::domain::*;
+
+mod common;
+use crate::common::string;
+
+/// The common shop, with a pattern on emails, at most 99 of an order and
+/// notes on it
+fn shop_domain() -> Domain {
+    let mut domain = common::shop_domain();
+    domain.entities[0].attributes[0].constraints.pattern = Some("@".into());
+    let order = domain.entities[1].id;
+    let quantity = domain.entities[1].attributes[1].id;
+    let vtype = VType::VTypeInteger(VTypeInteger {
+        default: Some(1),
+        min: Some(1),
+        max: Some(99),
+    });
+    domain
+        .update_attribute(order, quantity, None, Some(vtype))
+        .unwrap();
+    domain
+        .add_attribute(
+            order,
+            "notes",
+            VType::VTypeList(VTypeList {
+                item: Box::new(string()),
+            }),
+        )
+        .unwrap();
+    domain
+}
+
+#[test]
+fn test_import_sql_round_trip() {
+    let _ = env_logger::try_init();
+
+    let ddl = domain_to_ddl(&shop_domain()).unwrap();
+    let import = import_sql(&ddl).unwrap();
+    assert_eq!(import.issues, vec![]);
+    assert_eq!(domain_to_ddl(&import.domain).unwrap(), ddl);
+    assert_eq!(import.domain.next_id(), shop_domain().next_id());
+}
+
+#[test]
+fn test_import_sql_reports_what_it_leaves_out() {
+    let _ = env_logger::try_init();
+
+    let import = import_sql(
+        r#"
+        CREATE TABLE public.account (
+            id uuid PRIMARY KEY,
+            name varchar(40) NOT NULL,
+            balance bigint DEFAULT 0,
+            location point,
+            updated timestamptz DEFAULT now()
+        );
+        CREATE TABLE audit.entry (
+            id uuid PRIMARY KEY,
+            account_id uuid REFERENCES account (id) ON DELETE CASCADE
+        );
+        CREATE VIEW rich AS SELECT * FROM account;
+        "#,
+    )
+    .unwrap();
+
+    let issues: Vec<String> = import.issues.iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "account.balance: 64 bit integers are imported as integers",
+            "account.location: type POINT is not supported, column skipped",
+            "account.updated: the time zone of timestamps is left out",
+            "account.updated: default NOW ( ) is not supported",
+            "entry: schema audit is left out",
+            "entry.account_id: ON DELETE CASCADE is not supported",
+            "schema: statement CREATE VIEW RICH ... is skipped",
+        ]
+    );
+
+    let account = &import.domain.entities[0];
+    let names: Vec<&str> = account.attributes.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["name", "balance", "updated"]);
+    assert_eq!(account.attributes[0].constraints.max_length, Some(40));
+    assert!(account.attributes[0].constraints.required);
+
+    let entry = &import.domain.entities[1];
+    assert_eq!(entry.references[0].name, "account");
+    assert_eq!(entry.references[0].reftype, ReferenceType::BelongsTo);
+    assert_eq!(entry.references[0].other, "account");
+}
+
+#[test]
+fn test_import_sql_syntax_error() {
+    let _ = env_logger::try_init();
+
+    match import_sql("CREATE TABLE broken (name text") {
+        Err(ImportError::Syntax(_)) => {}
+        other => panic!("Expected a syntax error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_import_json_schema_round_trip() {
+    let _ = env_logger::try_init();
+
+    let mut domain = shop_domain();
+    let customer = domain.entities[0].id;
+    domain
+        .add_reference(customer, "orders", ReferenceType::HasMany, "order")
+        .unwrap();
+    let doc = DomainDocument {
+        body: domain,
+        ..DomainDocument::default()
+    };
+    let schema = document_to_json_schema(&doc).unwrap();
+
+    let import = import_json_schema(&schema).unwrap();
+    assert_eq!(import.issues, vec![]);
+    let imported = DomainDocument {
+        body: import.domain,
+        ..doc.clone()
+    };
+    assert_eq!(document_to_json_schema(&imported).unwrap(), schema);
+}
+
+#[test]
+fn test_import_json_schema_reports_what_it_leaves_out() {
+    let _ = env_logger::try_init();
+
+    let schema = json!({
+        "title": "person",
+        "type": "object",
+        "description": "Someone we know",
+        "properties": {
+            "name": { "type": "string", "maxLength": 80 },
+            "nickname": { "type": ["string", "null"] },
+            "born": { "type": "string", "format": "date", "default": "yesterday" },
+            "homepage": { "type": "string", "format": "uri" },
+            "mood": { "type": "string", "enum": ["happy", "sad"] },
+            "contact": { "oneOf": [{ "type": "string" }, { "type": "integer" }] },
+        },
+        "required": ["name"],
+    });
+    let import = import_json_schema(&schema).unwrap();
+
+    let issues: Vec<String> = import.issues.iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        issues,
+        vec![
+            "person: keyword description is not supported",
+            "person.born: default \"yesterday\" is not a valid value",
+            "person.contact: keyword oneOf is not supported",
+            "person.homepage: format uri is imported as a plain string",
+        ]
+    );
+
+    let person = &import.domain.entities[0];
+    assert_eq!(person.name, "person");
+    let attribute = |name: &str| person.attributes.iter().find(|a| a.name == name).unwrap();
+    assert!(attribute("name").constraints.required);
+    assert_eq!(attribute("name").constraints.max_length, Some(80));
+    assert_eq!(attribute("nickname").vtype, string());
+    assert_eq!(attribute("contact").vtype.type_name(), "json");
+    assert_eq!(
+        attribute("mood").vtype,
+        VType::VTypeEnum(VTypeEnum {
+            name: "person_mood".into(),
+            default: None,
+        })
+    );
+    assert!(import.domain.types.get_enum("person_mood").is_some());
+}
@@ -1,7 +1,13 @@
 extern crate env_logger;
 
 extern crate gearsx;
+use gearsx::structure::common::DocumentReference;
+use gearsx::structure::data::{
+    VType, VTypeBoolean, VTypeContainer, VTypeDecimal, VTypeEnum, VTypeFloat, VTypeInteger,
+    VTypeList, VTypeString, VTypeStringContainer,
+};
 use gearsx::structure::domain::*;
+use std::collections::HashSet;
 
 mod common;
 use crate::common::load_doc;
@@ -66,3 +72,534 @@ fn test_add_and_remove_entities_error_scenarios() {
     let remove_entity_2 = domain.remove_entity(order_entity.id);
     assert_eq!(remove_entity_2, Ok(()));
 }
+
+#[test]
+fn test_enum_type_catalog() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let values = vec!["open".to_string(), "shipped".to_string()];
+    domain
+        .add_enum("status", &values)
+        .expect("Expect to be able to add a status enum");
+    assert_eq!(domain.types.enums.len(), 1);
+    assert_eq!(
+        domain.add_enum("status", &values),
+        Err(DomainError::TypeAlreadyExists("status".into()))
+    );
+
+    let status = VType::VTypeEnum(VTypeEnum {
+        name: "status".into(),
+        default: Some("open".into()),
+    });
+    assert_eq!(domain.check_vtype(&status), Ok(()));
+
+    let bad_default = VType::VTypeEnum(VTypeEnum {
+        name: "status".into(),
+        default: Some("lost".into()),
+    });
+    assert_eq!(
+        domain.check_vtype(&bad_default),
+        Err(DomainError::InconsistentVType("status".into()))
+    );
+
+    let missing = VType::VTypeList(VTypeList {
+        item: Box::new(VType::VTypeEnum(
+            VTypeEnum {
+                name: "colour".into(),
+                default: None,
+            },
+        )),
+    });
+    assert_eq!(
+        domain.check_vtype(&missing),
+        Err(DomainError::TypeDoesNotExist("colour".into()))
+    );
+
+    domain
+        .remove_enum("status")
+        .expect("Expect to be able to remove an unused enum");
+    assert_eq!(domain.types.enums.len(), 0);
+}
+
+#[test]
+fn test_add_update_and_remove_attributes() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let order = domain
+        .add_entity("Order")
+        .expect("Expect to be able to add an Order Entity");
+
+    let total = domain
+        .add_attribute(
+            order.id,
+            "total",
+            VType::VTypeDecimal(VTypeDecimal {
+                default: None,
+                precision: Some(10),
+                scale: Some(2),
+            }),
+        )
+        .expect("Expect to be able to add a total Attribute");
+    assert_eq!(total.id, 2);
+
+    let duplicate = domain.add_attribute(
+        order.id,
+        "total",
+        VType::VTypeBoolean(VTypeBoolean { default: None }),
+    );
+    assert_eq!(
+        duplicate,
+        Err(DomainError::AttributeAlreadyExists(order.id, "total".into()))
+    );
+
+    let updated = domain
+        .update_attribute(
+            order.id,
+            total.id,
+            Some("amount"),
+            Some(VType::VTypeFloat(VTypeFloat {
+                default: None,
+                min: Some(0.0),
+                max: None,
+            })),
+        )
+        .expect("Expect to be able to update the total Attribute");
+    assert_eq!(updated.name, "amount");
+    assert_eq!(updated.vtype.type_name(), "float");
+
+    let missing = domain.update_attribute(order.id, 99, Some("other"), None);
+    assert_eq!(missing, Err(DomainError::AttributeDoesNotExist(order.id, 99)));
+
+    let removed = domain
+        .remove_attribute(order.id, total.id)
+        .expect("Expect to be able to remove the amount Attribute");
+    assert_eq!(removed.name, "amount");
+    assert_eq!(
+        domain.remove_attribute(order.id, total.id),
+        Err(DomainError::AttributeDoesNotExist(order.id, total.id))
+    );
+}
+
+#[test]
+fn test_add_update_and_remove_references() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    let comment = domain.add_entity("comment").unwrap();
+
+    let missing = domain.add_reference(post.id, "tags", ReferenceType::HasMany, "tag");
+    assert_eq!(
+        missing,
+        Err(DomainError::EntityNameDoesNotExist("tag".into()))
+    );
+
+    let (reference, inverse) = domain
+        .add_reference_with_inverse(
+            post.id,
+            "comment",
+            ReferenceType::HasMany,
+            "comment",
+            None,
+            None,
+        )
+        .expect("Expect to be able to add a has_many reference with its inverse");
+    assert_eq!(reference.other, "comment");
+    assert_eq!(inverse.name, "post");
+    assert_eq!(inverse.reftype, ReferenceType::BelongsTo);
+    assert_eq!(inverse.other, "post");
+    assert_eq!(domain.get_entity(comment.id).unwrap().references.len(), 1);
+
+    // The inverse of a reference from post to post is named post as well,
+    // so it can not be added and neither is the reference
+    let before = domain.clone();
+    let self_reference = domain.add_reference_with_inverse(
+        post.id,
+        "post",
+        ReferenceType::BelongsTo,
+        "post",
+        None,
+        None,
+    );
+    assert_eq!(
+        self_reference,
+        Err(DomainError::ReferenceAlreadyExists(post.id, "post".into()))
+    );
+    assert_eq!(domain, before);
+
+    let duplicate = domain.add_reference(post.id, "comment", ReferenceType::HasMany, "comment");
+    assert_eq!(
+        duplicate,
+        Err(DomainError::ReferenceAlreadyExists(post.id, "comment".into()))
+    );
+
+    let updated = domain
+        .update_reference(post.id, reference.id, Some("comments"), None, None)
+        .expect("Expect to be able to rename a reference");
+    assert_eq!(updated.name, "comments");
+
+    let bad_target = domain.update_reference(post.id, reference.id, None, None, Some("tag"));
+    assert_eq!(
+        bad_target,
+        Err(DomainError::EntityNameDoesNotExist("tag".into()))
+    );
+
+    domain
+        .remove_reference(comment.id, inverse.id)
+        .expect("Expect to be able to remove the inverse reference");
+    assert_eq!(
+        domain.remove_reference(comment.id, inverse.id),
+        Err(DomainError::ReferenceDoesNotExist(comment.id, inverse.id))
+    );
+}
+
+#[test]
+fn test_many_to_many_references() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    let tag = domain.add_entity("tag").unwrap();
+
+    let (reference, inverse) = domain
+        .add_reference_with_inverse(post.id, "tags", ReferenceType::ManyToMany, "tag", None, None)
+        .expect("Expect to be able to add a many_to_many reference");
+    assert_eq!(reference.through, Some("post_tag".into()));
+    assert_eq!(inverse.reftype, ReferenceType::ManyToMany);
+    assert_eq!(inverse.through, Some("post_tag".into()));
+
+    let join = domain.join_entity("tag", "post").unwrap();
+    assert_eq!(join.name, "post_tag");
+    assert_eq!(join.references.len(), 2);
+    assert_eq!(domain.entities.len(), 3);
+
+    let has_one = domain
+        .add_reference(tag.id, "owner", ReferenceType::HasOne, "post")
+        .unwrap();
+    assert_eq!(has_one.reftype.inverse(), ReferenceType::BelongsTo);
+
+    let invalid =
+        domain.add_reference_through(tag.id, "author", ReferenceType::HasOne, "post", Some("post_tag"));
+    assert_eq!(
+        invalid,
+        Err(DomainError::InvalidReference(tag.id, "author".into()))
+    );
+}
+
+#[test]
+fn test_reference_type_serialization() {
+    let reference: Reference = serde_json::from_str(
+        r#"{"id": 1, "name": "tags", "reftype": "many_to_many", "other": "tag", "through": "post_tag"}"#,
+    )
+    .unwrap();
+    assert_eq!(reference.reftype, ReferenceType::ManyToMany);
+
+    let reference: Reference =
+        serde_json::from_str(r#"{"id": 2, "name": "profile", "reftype": "has_one", "other": "profile"}"#)
+            .unwrap();
+    assert_eq!(reference.through, None);
+    assert!(!serde_json::to_string(&reference).unwrap().contains("through"));
+}
+
+#[test]
+fn test_validate_domain() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    domain.add_entity("comment").unwrap();
+    domain
+        .add_reference(post.id, "comments", ReferenceType::HasMany, "comment")
+        .unwrap();
+    domain
+        .add_attribute(post.id, "title", VType::VTypeBoolean(VTypeBoolean { default: None }))
+        .unwrap();
+    assert_eq!(domain.validate(), vec![]);
+
+    let xflow_id = uuid::Uuid::new_v4();
+    domain.events.all.push(DocumentReference {
+        id: xflow_id,
+        doctype: "xflow".into(),
+    });
+
+    // Break the domain in ways the Domain methods would not allow
+    let mut broken = domain.clone();
+    broken.entities[1].name = "post".into();
+    broken.entities[0].references[0].other = "reply".into();
+    broken.entities[0].attributes[0].id = broken.entities[1].id;
+    broken.entities[0].attributes[0].vtype = VType::VTypeInteger(VTypeInteger {
+        default: Some(10),
+        min: None,
+        max: Some(5),
+    });
+
+    let codes: Vec<DomainDiagnosticCode> = broken
+        .validate_with_xflows(&HashSet::new())
+        .into_iter()
+        .map(|d| d.code)
+        .collect();
+    assert_eq!(
+        codes,
+        vec![
+            DomainDiagnosticCode::DuplicateEntityName,
+            DomainDiagnosticCode::DuplicateId,
+            DomainDiagnosticCode::MissingReferenceTarget,
+            DomainDiagnosticCode::InconsistentVType,
+            DomainDiagnosticCode::MissingXFlow,
+        ]
+    );
+
+    let mut known = HashSet::new();
+    known.insert(xflow_id);
+    assert_eq!(domain.validate_with_xflows(&known), vec![]);
+}
+
+#[test]
+fn test_rename_and_remove_referenced_entities() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let post = domain.add_entity("post").unwrap();
+    let comment = domain.add_entity("comment").unwrap();
+    let (_, inverse) = domain
+        .add_reference_with_inverse(
+            post.id,
+            "comments",
+            ReferenceType::HasMany,
+            "comment",
+            None,
+            None,
+        )
+        .unwrap();
+
+    let renamed = domain.rename_entity(post.id, "article").unwrap();
+    assert_eq!(renamed.name, "article");
+    assert_eq!(
+        domain.get_entity(comment.id).unwrap().references[0].other,
+        "article"
+    );
+    assert_eq!(
+        domain.rename_entity(post.id, "comment"),
+        Err(DomainError::EntityAlreadyExists("comment".into()))
+    );
+
+    assert_eq!(
+        domain.remove_entity(post.id),
+        Err(DomainError::EntityIsReferenced(post.id, vec![inverse.id]))
+    );
+
+    let removed = domain.remove_entity_cascade(post.id).unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].id, inverse.id);
+    assert_eq!(domain.entities.len(), 1);
+    assert_eq!(domain.get_entity(comment.id).unwrap().references.len(), 0);
+    assert_eq!(domain.validate(), vec![]);
+}
+
+#[test]
+fn test_attribute_constraints() {
+    let _ = env_logger::try_init();
+
+    let mut domain = Domain::default();
+    let user = domain.add_entity("user").unwrap();
+    let email = domain
+        .add_attribute(user.id, "email", VType::VTypeString(VTypeString { default: None }))
+        .unwrap();
+    let active = domain
+        .add_attribute(user.id, "active", VType::VTypeBoolean(VTypeBoolean { default: None }))
+        .unwrap();
+
+    let constraints = AttributeConstraints {
+        required: true,
+        unique: true,
+        indexed: true,
+        min_length: Some(3),
+        max_length: Some(64),
+        pattern: Some("^[^@]+@[^@]+$".into()),
+    };
+    let email = domain
+        .set_attribute_constraints(user.id, email.id, constraints.clone())
+        .unwrap();
+
+    let value = |s: &str| {
+        VTypeContainer::VTypeStringContainer(VTypeStringContainer {
+            value: s.to_string(),
+        })
+    };
+    assert_eq!(email.check_value(Some(&value("me@example.com"))), Ok(()));
+    assert_eq!(email.check_value(None), Err(ConstraintViolation::Required));
+    assert_eq!(
+        email.check_value(Some(&value("a@"))),
+        Err(ConstraintViolation::TooShort(3))
+    );
+    assert_eq!(
+        email.check_value(Some(&value("example.com"))),
+        Err(ConstraintViolation::PatternMismatch("^[^@]+@[^@]+$".into()))
+    );
+
+    let invalid = domain.set_attribute_constraints(user.id, active.id, constraints);
+    match invalid {
+        Err(DomainError::InvalidConstraint(id, _)) => assert_eq!(id, active.id),
+        _ => panic!("Expected a length constraint on a boolean to be refused"),
+    }
+
+    let json = serde_json::to_string(&email).unwrap();
+    assert!(json.contains(r#""constraints":{"required":true,"unique":true,"indexed":true"#));
+    let attribute: Attribute =
+        serde_json::from_str(r#"{"id": 1, "name": "a", "vtype": {"type": "string"}, "validations": []}"#)
+            .unwrap();
+    assert_eq!(attribute.constraints, AttributeConstraints::default());
+}
+
+#[test]
+fn test_attribute_storage() {
+    let _ = env_logger::try_init();
+
+    let doc = load_doc::<serde_json::Value>("resource/docs/domain/good/basic.json");
+    let domain: Domain = serde_json::from_value(doc["body"].clone()).unwrap();
+    let comment = &domain.entities[1];
+    assert_eq!(comment.attributes[0].storage, AttributeStorage::Persistent);
+    assert_eq!(domain.validate(), vec![]);
+
+    let mut domain = domain.clone();
+    let post_id = domain.entities[0].id;
+    let title_id = domain.entities[0].attributes[0].id;
+    let storage = AttributeStorage::Computed {
+        expression: "upper(body)".into(),
+    };
+    domain
+        .set_attribute_storage(post_id, title_id, storage.clone())
+        .unwrap();
+    let post = domain.get_entity(post_id).unwrap();
+    assert_eq!(post.stored_attributes().count(), 2);
+
+    let json = serde_json::to_value(&post.attributes[0]).unwrap();
+    assert_eq!(
+        json["storage"],
+        serde_json::json!({"computed": {"expression": "upper(body)"}})
+    );
+
+    let unique = AttributeConstraints {
+        unique: true,
+        ..AttributeConstraints::default()
+    };
+    match domain.set_attribute_constraints(post_id, title_id, unique) {
+        Err(DomainError::InvalidConstraint(id, _)) => assert_eq!(id, title_id),
+        _ => panic!("Expected a unique computed attribute to be refused"),
+    }
+    match domain.set_attribute_storage(
+        post_id,
+        title_id,
+        AttributeStorage::Computed {
+            expression: " ".into(),
+        },
+    ) {
+        Err(DomainError::InvalidStorage(id, _)) => assert_eq!(id, title_id),
+        _ => panic!("Expected an empty expression to be refused"),
+    }
+    for (expression, message) in &[
+        ("upper(summary)", "column 7: unknown variable summary"),
+        ("upper(title)", "column 7: unknown variable title"),
+        (
+            "len(body)",
+            "column 1: the expression is of type integer, the attribute of type string",
+        ),
+    ] {
+        let storage = AttributeStorage::Computed {
+            expression: expression.to_string(),
+        };
+        match domain.set_attribute_storage(post_id, title_id, storage) {
+            Err(DomainError::InvalidStorage(id, err)) => {
+                assert_eq!((id, err.as_str()), (title_id, *message))
+            }
+            other => panic!("Expected {} to be refused, got {:?}", expression, other),
+        }
+    }
+
+    // Expressions that stop checking after a change are reported
+    let body_id = domain.entities[0].attributes[1].id;
+    domain
+        .update_attribute(post_id, body_id, Some("text"), None)
+        .unwrap();
+    let diagnostics = domain.validate();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DomainDiagnosticCode::InvalidStorage);
+    assert_eq!(diagnostics[0].element_id, Some(title_id));
+    assert_eq!(
+        diagnostics[0].message,
+        "Attribute post.title has an invalid storage mode (column 7: unknown variable body)"
+    );
+}
+
+#[test]
+fn test_domain_index() {
+    let _ = env_logger::try_init();
+
+    let string = || VType::VTypeString(VTypeString { default: None });
+    let mut domain = Domain::default();
+    let mut ids = Vec::<i32>::new();
+    for i in 0..200 {
+        let entity = domain.add_entity(&format!("entity_{}", i)).unwrap();
+        let attribute = domain.add_attribute(entity.id, "name", string()).unwrap();
+        ids.push(entity.id);
+        ids.push(attribute.id);
+    }
+    let post = domain.get_entity_name("entity_42").unwrap().clone();
+    let (entity, attribute) = domain.find_attribute(post.id + 1).unwrap();
+    assert_eq!((entity.id, attribute.name.as_str()), (post.id, "name"));
+    assert_eq!(domain.next_id(), ids.iter().max().unwrap() + 1);
+
+    domain.rename_entity(post.id, "post").unwrap();
+    assert!(!domain.has_entity_name("entity_42"));
+    assert_eq!(domain.get_entity_name("post").unwrap().id, post.id);
+
+    domain.remove_entity(ids[0]).unwrap();
+    assert_eq!(domain.get_entity(post.id).unwrap().name, "post");
+    assert!(domain.find_attribute(ids[1]).is_none());
+
+    // Entities changed through get_entity_mut are indexed again
+    domain.get_entity_mut(post.id).unwrap().name = "article".into();
+    assert!(!domain.has_entity_name("post"));
+    assert_eq!(domain.get_entity_name("article").unwrap().id, post.id);
+    let mut title = domain.get_entity(post.id).unwrap().attributes[0].clone();
+    title.id = 2000;
+    title.name = "title".into();
+    domain
+        .get_entity_mut(post.id)
+        .unwrap()
+        .attributes
+        .push(title);
+    assert_eq!(domain.next_id(), 2001);
+    let (entity, attribute) = domain.find_attribute(2000).unwrap();
+    assert_eq!((entity.id, attribute.name.as_str()), (post.id, "title"));
+    domain.remove_attribute(post.id, post.id + 1).unwrap();
+    assert!(domain.find_attribute(post.id + 1).is_none());
+    assert_eq!(domain.find_attribute(2000).unwrap().1.name, "title");
+
+    // Entities added or removed through the fields are picked up, as are
+    // elements that moved
+    let mut reply = domain.get_entity(post.id).unwrap().clone();
+    reply.id = 1000;
+    reply.name = "reply".into();
+    domain.entities.push(reply);
+    assert!(domain.has_entity(1000));
+    assert_eq!(domain.next_id(), 2001);
+    domain.entities[0].attributes.clear();
+    assert!(domain.find_attribute(ids[3]).is_none());
+
+    // Other changes through the fields need a reindex
+    domain.entities[1].name = "renamed".into();
+    domain.reindex();
+    assert_eq!(domain.get_entity_name("renamed").unwrap().id, ids[4]);
+
+    // The index is not part of the JSON or of comparisons
+    let json = serde_json::to_value(&domain).unwrap();
+    assert_eq!(
+        json.as_object().unwrap().keys().collect::<Vec<_>>(),
+        vec!["entities", "events", "types"]
+    );
+    let copy: Domain = serde_json::from_value(json).unwrap();
+    assert_eq!(copy, domain);
+}
@@ -0,0 +1,178 @@
+extern crate env_logger;
+
+extern crate gearsx;
+#[macro_use]
+extern crate serde_json;
+use gearsx::structure::data::{VType, VTypeEnum, VTypeInteger, VTypeList};
+use gearsx::structure::domain::*;
+use gearsx::structure::record::{check_record, reference_pattern, referenced_ids, RecordViolation};
+
+mod common;
+use crate::common::string;
+
+fn blog_domain() -> Domain {
+    let mut domain = Domain::default();
+    domain
+        .add_enum("status", &["draft".to_string(), "published".to_string()])
+        .unwrap();
+
+    let post = domain.add_entity("post").unwrap();
+    let title = domain.add_attribute(post.id, "title", string()).unwrap();
+    domain
+        .set_attribute_constraints(
+            post.id,
+            title.id,
+            AttributeConstraints {
+                required: true,
+                max_length: Some(10),
+                ..AttributeConstraints::default()
+            },
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            post.id,
+            "status",
+            VType::VTypeEnum(VTypeEnum {
+                name: "status".into(),
+                default: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .add_attribute(
+            post.id,
+            "ratings",
+            VType::VTypeList(VTypeList {
+                item: Box::new(VType::VTypeInteger(VTypeInteger {
+                    default: Some(1),
+                    min: Some(1),
+                    max: Some(5),
+                })),
+            }),
+        )
+        .unwrap();
+    let words = domain
+        .add_attribute(
+            post.id,
+            "words",
+            VType::VTypeInteger(VTypeInteger {
+                default: None,
+                min: None,
+                max: None,
+            }),
+        )
+        .unwrap();
+    domain
+        .set_attribute_storage(
+            post.id,
+            words.id,
+            AttributeStorage::Computed {
+                expression: "len(title)".into(),
+            },
+        )
+        .unwrap();
+
+    let comment = domain.add_entity("comment").unwrap();
+    domain.add_attribute(comment.id, "body", string()).unwrap();
+    domain
+        .add_reference(comment.id, "post", ReferenceType::BelongsTo, "post")
+        .unwrap();
+    domain
+        .add_reference(post.id, "comments", ReferenceType::HasMany, "comment")
+        .unwrap();
+    domain
+}
+
+fn entity<'a>(domain: &'a Domain, name: &str) -> &'a Entity {
+    domain.entities.iter().find(|e| e.name == name).unwrap()
+}
+
+#[test]
+fn test_check_valid_record() {
+    let _ = env_logger::try_init();
+
+    let domain = blog_domain();
+    let post = json!({
+        "title": "Hello",
+        "status": "draft",
+        "ratings": [1, 5],
+        "comments_ids": ["e4f0518a-fd2d-403e-9c20-79041c1c14ae"],
+    });
+    assert_eq!(
+        check_record(&domain, entity(&domain, "post"), &post),
+        vec![]
+    );
+
+    let comment = json!({ "body": null, "post_id": "e4f0518a-fd2d-403e-9c20-79041c1c14ae" });
+    let comment_entity = entity(&domain, "comment");
+    assert_eq!(check_record(&domain, comment_entity, &comment), vec![]);
+    assert_eq!(
+        referenced_ids(&comment_entity.references[0], &comment)
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>(),
+        vec!["e4f0518a-fd2d-403e-9c20-79041c1c14ae".to_string()]
+    );
+
+    // The patterns that find the records referring to a record
+    let id = referenced_ids(&comment_entity.references[0], &comment)[0];
+    assert_eq!(
+        reference_pattern(&comment_entity.references[0], &id),
+        json!({ "post_id": "e4f0518a-fd2d-403e-9c20-79041c1c14ae" })
+    );
+    assert_eq!(
+        reference_pattern(&entity(&domain, "post").references[0], &id),
+        json!({ "comments_ids": ["e4f0518a-fd2d-403e-9c20-79041c1c14ae"] })
+    );
+}
+
+#[test]
+fn test_check_invalid_record() {
+    let _ = env_logger::try_init();
+
+    let domain = blog_domain();
+    let post = entity(&domain, "post");
+    assert_eq!(
+        check_record(&domain, post, &json!([])),
+        vec![RecordViolation::new(None, "a record is a JSON object")]
+    );
+
+    let record = json!({
+        "id": "e4f0518a-fd2d-403e-9c20-79041c1c14ae",
+        "subtitle": "Hi",
+        "status": "retracted",
+        "ratings": [1, 6],
+        "words": 3,
+        "comments_ids": "e4f0518a-fd2d-403e-9c20-79041c1c14ae",
+    });
+    let messages: Vec<String> = check_record(&domain, post, &record)
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "id: the id of a record is kept by the store",
+            "subtitle: post has no property subtitle",
+            "title: a value is required",
+            "status: retracted is not a value of enum status",
+            "ratings: item 1: 6 is out of range",
+            "words: computed attributes are read only",
+            "comments_ids: expected a list of record ids",
+        ]
+    );
+
+    let record = json!({ "title": "Far too long a title", "status": 1 });
+    let messages: Vec<String> = check_record(&domain, post, &record)
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "title: longer than 10",
+            "status: expected a value of type enum",
+        ]
+    );
+}
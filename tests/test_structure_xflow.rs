@@ -0,0 +1,303 @@
+extern crate gearsx;
+use gearsx::structure::data::{
+    Position, VType, VTypeBoolean, VTypeInteger, VTypeString, VariableDefinition, VariableScope,
+};
+use gearsx::structure::xflow::{
+    CallParameters, FlowParameters, FloxParameters, XFlow, XFlowBranch, XFlowDiagnostic,
+    XFlowDiagnosticCode, XFlowEdge, XFlowError, XFlowNode, XFlowNodeParameters, XFlowNodeType,
+};
+
+fn node(id: i32, action: &str) -> XFlowNode {
+    XFlowNode {
+        id,
+        nodetype: XFlowNodeType::Flow,
+        position: Position { x: 0, y: 0 },
+        action: action.to_string(),
+        label: action.to_string(),
+        parameters: XFlowNodeParameters::Flow(FlowParameters::default()),
+    }
+}
+
+fn edge(source: i32, target: i32) -> XFlowEdge {
+    XFlowEdge { source, target }
+}
+
+fn done() -> VariableDefinition {
+    VariableDefinition {
+        id: 1,
+        name: "done".to_string(),
+        vtype: VType::VTypeBoolean(VTypeBoolean { default: None }),
+    }
+}
+
+fn branch(source: i32, target: i32) -> XFlowBranch {
+    XFlowBranch {
+        edge: edge(source, target),
+        xvar: done(),
+    }
+}
+
+/// The default flow, with a boolean local variable `done`
+fn xflow() -> XFlow {
+    let mut xflow = XFlow::default();
+    xflow.variables.local.push(done());
+    xflow
+}
+
+#[test]
+fn test_validate_default() {
+    assert_eq!(XFlow::default().validate(), vec![]);
+}
+
+#[test]
+fn test_validate() {
+    let mut xflow = xflow();
+    xflow.nodes.push(node(3, "step"));
+    xflow.nodes.push(node(3, "other"));
+    xflow.nodes[3].parameters = XFlowNodeParameters::Call(CallParameters {});
+    xflow.nodes.push(node(4, "stray"));
+    xflow.edges = vec![edge(1, 3), edge(3, 5), edge(3, 2)];
+    xflow.branches = vec![branch(6, 2)];
+
+    assert_eq!(
+        xflow.validate(),
+        vec![
+            XFlowError::DuplicateNodeIDs(3),
+            XFlowError::ParametersMismatch(3, XFlowNodeType::Flow),
+            XFlowError::NodeNotFound(5, edge(3, 5)),
+            XFlowError::BranchNodeNotFound(6, edge(6, 2)),
+            XFlowError::UnreachableNode(4),
+            XFlowError::DeadEndNode(4),
+        ]
+    );
+    assert_eq!(
+        XFlowDiagnostic::from(&xflow.validate()[2]),
+        XFlowDiagnostic {
+            code: XFlowDiagnosticCode::NodeNotFound,
+            message: "edge 3 -> 5: node 5 does not exist".to_string(),
+            node_ids: vec![3, 5],
+        }
+    );
+
+    xflow.edges.push(edge(1, 3));
+    xflow.branches[0] = branch(1, 3);
+    xflow.branches[0].xvar.name = "gone".to_string();
+    xflow.branches.push(branch(1, 3));
+    xflow.branches[1].xvar.vtype = VType::VTypeString(VTypeString { default: None });
+    assert_eq!(
+        xflow.validate(),
+        vec![
+            XFlowError::DuplicateNodeIDs(3),
+            XFlowError::ParametersMismatch(3, XFlowNodeType::Flow),
+            XFlowError::NodeNotFound(5, edge(3, 5)),
+            XFlowError::DuplicateEdge(edge(1, 3)),
+            XFlowError::BranchVariableNotFound(edge(1, 3), "gone".to_string()),
+            XFlowError::BranchTypeMismatch(edge(1, 3), "done".to_string()),
+            XFlowError::UnreachableNode(4),
+            XFlowError::DeadEndNode(4),
+        ]
+    );
+
+    xflow.nodes.retain(|node| node.id == 1);
+    xflow.edges.clear();
+    xflow.branches.clear();
+    assert_eq!(xflow.validate(), vec![XFlowError::NoTerminalNode]);
+}
+
+#[test]
+fn test_validate_cycles() {
+    let mut xflow = xflow();
+    xflow.nodes.push(node(3, "loop"));
+    xflow.nodes.push(node(4, "again"));
+    xflow.edges = vec![edge(1, 3), edge(3, 4), edge(4, 3), edge(4, 2), edge(2, 2)];
+    assert_eq!(
+        xflow.validate(),
+        vec![
+            XFlowError::UnintendedCycle(vec![2]),
+            XFlowError::UnintendedCycle(vec![3, 4]),
+        ]
+    );
+
+    // A branch out of the loop makes it a loop that ends
+    xflow.edges.pop();
+    xflow.branches.push(branch(4, 2));
+    assert_eq!(xflow.validate(), vec![]);
+}
+
+#[test]
+fn test_validate_cycle_with_a_branch_back() {
+    // The edge out of the loop is taken when the branch back does not match
+    let mut xflow = xflow();
+    xflow.nodes.push(node(3, "poll"));
+    let mut set_done = node(4, "finish");
+    set_done.nodetype = XFlowNodeType::Flox;
+    set_done.parameters = XFlowNodeParameters::Flox(FloxParameters {
+        expression: "true".to_string(),
+        returns: done(),
+    });
+    xflow.nodes.push(set_done);
+    xflow.edges = vec![edge(1, 3), edge(3, 4), edge(4, 3), edge(3, 2)];
+    xflow.branches.push(branch(3, 4));
+    assert_eq!(xflow.validate(), vec![]);
+}
+
+#[test]
+fn test_edit() {
+    let mut xflow = xflow();
+    let flow = XFlowNodeParameters::Flow(FlowParameters::default());
+    let at = |x| Position { x, y: 0 };
+
+    let step = xflow
+        .add_node(XFlowNodeType::Flow, "step", "Step", at(500), flow.clone())
+        .unwrap();
+    assert_eq!(step.id, 3);
+    assert_eq!(xflow.next_id(), 4);
+    let check = xflow
+        .add_node(XFlowNodeType::Call, "check", "Check", at(700), flow.clone())
+        .unwrap_err();
+    assert_eq!(
+        check,
+        XFlowError::ParametersMismatch(4, XFlowNodeType::Call)
+    );
+    let err = xflow
+        .add_node(XFlowNodeType::Flow, "start", "Again", at(0), flow)
+        .unwrap_err();
+    assert_eq!(err, XFlowError::MultipleEntryNodes);
+    assert_eq!(xflow.nodes.len(), 3);
+    // An unconnected node is fine while the flow is being built
+    assert_eq!(
+        xflow.validate(),
+        vec![XFlowError::UnreachableNode(3), XFlowError::DeadEndNode(3)]
+    );
+
+    xflow.connect(1, 3).unwrap();
+    xflow.connect(3, 2).unwrap();
+    assert_eq!(
+        xflow.connect(3, 2),
+        Err(XFlowError::DuplicateEdge(edge(3, 2)))
+    );
+    assert_eq!(xflow.connect(3, 9), Err(XFlowError::UnknownNode(9)));
+    xflow.connect(3, 1).unwrap();
+    assert_eq!(
+        xflow.validate(),
+        vec![XFlowError::UnintendedCycle(vec![1, 3])]
+    );
+
+    let vtype = VType::VTypeBoolean(VTypeBoolean {
+        default: Some(true),
+    });
+    let branch = xflow.add_branch(3, 2, "done", vtype.clone()).unwrap();
+    assert_eq!(branch.xvar.id, 1);
+    assert_eq!(
+        xflow.add_branch(3, 2, "gone", vtype.clone()),
+        Err(XFlowError::UnknownVariable("gone".to_string()))
+    );
+    assert_eq!(
+        xflow.add_branch(2, 3, "done", vtype),
+        Err(XFlowError::UnknownEdge(edge(2, 3)))
+    );
+    assert_eq!(xflow.validate(), vec![]);
+
+    let moved = xflow.move_node(3, at(400)).unwrap();
+    assert_eq!(moved.position, at(400));
+    let updated = xflow.update_node(3, None, Some("Loop"), None).unwrap();
+    assert_eq!(
+        (updated.action.as_str(), updated.label.as_str()),
+        ("step", "Loop")
+    );
+
+    assert_eq!(xflow.disconnect(3, 2), Ok(edge(3, 2)));
+    assert!(xflow.branches.is_empty());
+    xflow.connect(3, 2).unwrap();
+    assert_eq!(xflow.remove_node(1), Err(XFlowError::NoEntryNode));
+    assert_eq!(xflow.remove_node(3).map(|node| node.id), Ok(3));
+    assert_eq!(xflow.edges, vec![edge(1, 2)]);
+    assert_eq!(
+        xflow.remove_branch(3, 2, "done"),
+        Err(XFlowError::UnknownBranch(edge(3, 2), "done".to_string()))
+    );
+}
+
+#[test]
+fn test_variables() {
+    let mut xflow = xflow();
+    let string = || VType::VTypeString(VTypeString { default: None });
+    let name = xflow
+        .add_variable(VariableScope::Input, "name", string())
+        .unwrap();
+    assert_eq!(name.id, 2);
+    assert_eq!(
+        xflow.add_variable(VariableScope::Output, "done", string()),
+        Err(XFlowError::DuplicateVariableName("done".to_string()))
+    );
+    xflow
+        .add_variable(VariableScope::Output, "greeting", string())
+        .unwrap();
+    assert_eq!(xflow.variables.get_all_ids().len(), 3);
+    assert!(xflow.variables.all_variable_names().contains("greeting"));
+    assert_eq!(xflow.variables.next_id(), 4);
+
+    let mut node = node(3, "greet");
+    node.nodetype = XFlowNodeType::Flox;
+    node.parameters = XFlowNodeParameters::Flox(FloxParameters {
+        expression: "\"Hi \" + name".to_string(),
+        returns: xflow.variables.output[0].clone(),
+    });
+    xflow.nodes.push(node);
+    xflow.edges = vec![edge(1, 3), edge(3, 2), edge(3, 1)];
+    xflow.branches.push(branch(3, 2));
+    assert_eq!(xflow.validate(), vec![]);
+
+    assert_eq!(
+        xflow.rename_variable("greeting", "name"),
+        Err(XFlowError::DuplicateVariableName("name".to_string()))
+    );
+    let renamed = xflow.rename_variable("greeting", "message").unwrap();
+    assert_eq!((renamed.id, renamed.name.as_str()), (3, "message"));
+    match &xflow.nodes[2].parameters {
+        XFlowNodeParameters::Flox(parameters) => assert_eq!(parameters.returns, renamed),
+        _ => unreachable!(),
+    }
+    xflow.rename_variable("done", "finished").unwrap();
+    assert_eq!(xflow.branches[0].xvar.name, "finished");
+
+    let integer = VType::VTypeInteger(VTypeInteger {
+        default: None,
+        min: None,
+        max: None,
+    });
+    assert_eq!(
+        xflow.retype_variable("finished", integer.clone()),
+        Err(XFlowError::BranchTypeMismatch(
+            edge(3, 2),
+            "finished".to_string()
+        ))
+    );
+    let retyped = xflow.retype_variable("name", integer).unwrap();
+    assert_eq!((retyped.id, retyped.vtype.type_name()), (2, "integer"));
+
+    // Branches on a removed variable stay, for later edits to deal with
+    assert_eq!(xflow.remove_variable("finished").map(|xvar| xvar.id), Ok(1));
+    assert_eq!(
+        xflow.validate(),
+        vec![XFlowError::BranchVariableNotFound(
+            edge(3, 2),
+            "finished".to_string()
+        )]
+    );
+    assert_eq!(
+        xflow.remove_variable("finished"),
+        Err(XFlowError::UnknownVariable("finished".to_string()))
+    );
+
+    xflow.variables.local.push(done());
+    xflow.variables.local.push(done());
+    assert_eq!(
+        xflow.validate(),
+        vec![
+            XFlowError::DuplicateVariableName("done".to_string()),
+            XFlowError::DuplicateVariableID(1),
+            XFlowError::BranchVariableNotFound(edge(3, 2), "finished".to_string()),
+        ]
+    );
+}